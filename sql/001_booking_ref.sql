@@ -0,0 +1,12 @@
+-- 为每条预定生成对外展示的预订号(6 位字母数字)
+ALTER TABLE Reservation
+    ADD COLUMN booking_ref CHAR(6) NULL;
+
+-- 旧数据用自增 id 的 36 进制补齐，保证唯一
+UPDATE Reservation
+SET booking_ref = LPAD(CONV(id, 10, 36), 6, '0')
+WHERE booking_ref IS NULL;
+
+ALTER TABLE Reservation
+    MODIFY booking_ref CHAR(6) NOT NULL,
+    ADD UNIQUE INDEX uk_booking_ref (booking_ref);
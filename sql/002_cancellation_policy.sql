@@ -0,0 +1,38 @@
+-- 出发/入住时间，用于计算取消手续费
+ALTER TABLE Flights ADD COLUMN depart_time DATETIME NULL;
+ALTER TABLE Bus ADD COLUMN depart_time DATETIME NULL;
+ALTER TABLE Hotels ADD COLUMN check_in_time DATETIME NULL;
+
+-- 取消政策：res_id 为 NULL 时对整个类型(1 航班, 2 酒店, 3 大巴)生效，
+-- 否则只对对应的航班/酒店/大巴生效，后者优先。
+-- 出发/入住前 free_hours 小时之前免费取消，之后收取 fee_percent% 的手续费；
+-- refundable = 0 表示不可退款。没有配置时免费取消。
+CREATE TABLE CancellationPolicy
+(
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    res_type    INT         NOT NULL,
+    res_id      VARCHAR(20) NULL,
+    free_hours  INT         NOT NULL DEFAULT 0,
+    fee_percent INT         NOT NULL DEFAULT 0,
+    refundable  BOOLEAN     NOT NULL DEFAULT TRUE,
+    UNIQUE INDEX uk_policy (res_type, res_id)
+);
+
+-- 取消预定时记录的退款
+CREATE TABLE Refund
+(
+    reservation_id INT          NOT NULL PRIMARY KEY,
+    booking_ref    CHAR(6)      NOT NULL,
+    customer_id    INT          NOT NULL,
+    res_type       INT          NOT NULL,
+    res_id         VARCHAR(20)  NOT NULL,
+    price          INT UNSIGNED NOT NULL,
+    fee            INT UNSIGNED NOT NULL,
+    refund         INT UNSIGNED NOT NULL,
+    cancelled_at   DATETIME     NOT NULL,
+    INDEX idx_refund_customer (customer_id)
+);
+
+-- 例：航班起飞前 24 小时内取消收取 20% 手续费
+-- INSERT INTO CancellationPolicy (res_type, res_id, free_hours, fee_percent, refundable)
+-- VALUES (1, NULL, 24, 20, TRUE);
@@ -0,0 +1,39 @@
+-- 火车票：一行对应一个车次的一种席别，res_type = 4
+CREATE TABLE Trains
+(
+    train_num     VARCHAR(20)  NOT NULL PRIMARY KEY,
+    price         INT UNSIGNED NOT NULL,
+    num_seat      INT UNSIGNED NOT NULL,
+    num_available INT UNSIGNED NOT NULL,
+    from_city     VARCHAR(20)  NOT NULL,
+    arrive_city   VARCHAR(20)  NOT NULL,
+    seat_class    VARCHAR(20)  NOT NULL,
+    depart_time   DATETIME     NULL
+);
+
+-- 和其它类型一样，由触发器维护余票数量
+DELIMITER //
+CREATE TRIGGER train_reserved
+    AFTER INSERT
+    ON Reservation
+    FOR EACH ROW
+BEGIN
+    IF NEW.res_type = 4 THEN
+        UPDATE Trains SET num_available = num_available - 1 WHERE train_num = NEW.res_id;
+    END IF;
+END //
+
+CREATE TRIGGER train_released
+    AFTER DELETE
+    ON Reservation
+    FOR EACH ROW
+BEGIN
+    IF OLD.res_type = 4 THEN
+        UPDATE Trains SET num_available = num_available + 1 WHERE train_num = OLD.res_id;
+    END IF;
+END //
+DELIMITER ;
+
+-- 例：
+-- INSERT INTO Trains (train_num, price, num_seat, num_available, from_city, arrive_city, seat_class, depart_time)
+-- VALUES ('G101-2', 553, 500, 500, '北京', '上海', '二等座', '2024-05-01 07:00');
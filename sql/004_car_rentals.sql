@@ -0,0 +1,21 @@
+-- 租车：按日期预定，res_type = 5
+-- 预定记录日期区间，其它类别这两列为 NULL
+ALTER TABLE Reservation
+    ADD COLUMN start_date DATE NULL,
+    ADD COLUMN end_date   DATE NULL;
+
+CREATE TABLE CarRentals
+(
+    car_num       VARCHAR(20)  NOT NULL PRIMARY KEY,
+    price         INT UNSIGNED NOT NULL,
+    num_cars      INT UNSIGNED NOT NULL,
+    -- 车辆按日期轮流出租，不随预定增减，由日期不重叠的预定共享 num_cars
+    num_available INT UNSIGNED NOT NULL,
+    vehicle_class VARCHAR(20)  NOT NULL,
+    pickup_city   VARCHAR(20)  NOT NULL,
+    dropoff_city  VARCHAR(20)  NOT NULL
+);
+
+-- 例：
+-- INSERT INTO CarRentals (car_num, price, num_cars, num_available, vehicle_class, pickup_city, dropoff_city)
+-- VALUES ('C-SH-01', 260, 8, 8, '紧凑型', '上海', '杭州');
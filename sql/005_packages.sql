@@ -0,0 +1,50 @@
+-- 套餐：管理员把指定的航班/酒店/大巴组合成一个套餐，以套餐价格整体出售
+CREATE TABLE Package
+(
+    package_id VARCHAR(20)  NOT NULL PRIMARY KEY,
+    name       VARCHAR(50)  NOT NULL,
+    price      INT UNSIGNED NOT NULL
+);
+
+-- res_type 同 Reservation(1 航班, 2 酒店, 3 大巴, ...)
+CREATE TABLE PackageItem
+(
+    package_id VARCHAR(20) NOT NULL,
+    res_type   INT         NOT NULL,
+    res_id     VARCHAR(20) NOT NULL,
+    PRIMARY KEY (package_id, res_type, res_id)
+);
+
+-- 预定的套餐，price 为预定时的套餐价格
+CREATE TABLE PackageBooking
+(
+    booking_ref CHAR(6)      NOT NULL PRIMARY KEY,
+    customer_id INT          NOT NULL,
+    package_id  VARCHAR(20)  NOT NULL,
+    price       INT UNSIGNED NOT NULL,
+    booked_at   DATETIME     NOT NULL,
+    INDEX idx_package_customer (customer_id)
+);
+
+-- 套餐中的每一项仍是一条预定，通过 package_ref 关联到所属的套餐，只能随套餐一起取消
+ALTER TABLE Reservation
+    ADD COLUMN package_ref CHAR(6) NULL,
+    ADD INDEX idx_package_ref (package_ref);
+
+-- 取消套餐时记录的退款，取消政策按 res_type = 0、res_id = 套餐号配置
+CREATE TABLE PackageRefund
+(
+    booking_ref  CHAR(6)      NOT NULL PRIMARY KEY,
+    customer_id  INT          NOT NULL,
+    package_id   VARCHAR(20)  NOT NULL,
+    price        INT UNSIGNED NOT NULL,
+    fee          INT UNSIGNED NOT NULL,
+    refund       INT UNSIGNED NOT NULL,
+    cancelled_at DATETIME     NOT NULL,
+    INDEX idx_package_refund_customer (customer_id)
+);
+
+-- 例：
+-- INSERT INTO Package (package_id, name, price) VALUES ('P-SH-01', '上海三日游', 1800);
+-- INSERT INTO PackageItem (package_id, res_type, res_id)
+-- VALUES ('P-SH-01', 1, 'MU5101'), ('P-SH-01', 2, 'H-SH-01'), ('P-SH-01', 3, 'B-SH-01');
@@ -0,0 +1,15 @@
+-- 客户资料，除姓名外都可以为空；格式由程序检查
+ALTER TABLE Customer
+    ADD COLUMN email             VARCHAR(100) NULL,
+    ADD COLUMN phone             VARCHAR(20)  NULL,
+    ADD COLUMN birth_date        DATE         NULL,
+    ADD COLUMN id_number         VARCHAR(20)  NULL,
+    ADD COLUMN nationality       VARCHAR(50)  NULL,
+    ADD COLUMN emergency_contact VARCHAR(100) NULL,
+    -- 管理员可以使用管理菜单，例如按姓名、邮箱或电话查找客户
+    ADD COLUMN is_admin          BOOLEAN      NOT NULL DEFAULT FALSE,
+    ADD INDEX idx_customer_email (email),
+    ADD INDEX idx_customer_phone (phone);
+
+-- 例：
+-- UPDATE Customer SET is_admin = TRUE WHERE id = 1;
@@ -0,0 +1,16 @@
+-- 待发送的通知邮件，发送失败的保留在表中，下次按 next_attempt_at 重试
+CREATE TABLE Notification
+(
+    id              INT AUTO_INCREMENT PRIMARY KEY,
+    customer_id     INT          NOT NULL,
+    recipient       VARCHAR(100) NOT NULL,
+    subject         VARCHAR(200) NOT NULL,
+    body            TEXT         NOT NULL,
+    attempts        INT UNSIGNED NOT NULL DEFAULT 0,
+    last_error      VARCHAR(200) NULL,
+    created_at      DATETIME     NOT NULL,
+    next_attempt_at DATETIME     NOT NULL,
+    -- 发送成功的时间，NULL 表示还在队列中
+    sent_at         DATETIME     NULL,
+    INDEX idx_notification_pending (sent_at, next_attempt_at)
+);
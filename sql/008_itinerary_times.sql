@@ -0,0 +1,5 @@
+-- 到达/退房时间，导出行程日历时作为事件的结束时间，可以为空
+ALTER TABLE Flights ADD COLUMN arrive_time DATETIME NULL;
+ALTER TABLE Bus ADD COLUMN arrive_time DATETIME NULL;
+ALTER TABLE Hotels ADD COLUMN check_out_time DATETIME NULL;
+ALTER TABLE Trains ADD COLUMN arrive_time DATETIME NULL;
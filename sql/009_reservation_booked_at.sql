@@ -0,0 +1,3 @@
+-- 预定时间，用于按月统计营业额；已有的预定记为执行本脚本的时间
+ALTER TABLE Reservation
+    ADD COLUMN booked_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP;
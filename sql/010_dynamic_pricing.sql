@@ -0,0 +1,25 @@
+-- 动态定价规则：res_type 为 NULL 时对所有类型生效，其余条件为 NULL 时不限制。
+-- min_load/max_load：已预定数占总数的百分比；min_days/max_days：距出发/入住的天数；
+-- weekday：出发/入住是星期几(1 星期一 ... 7 星期日)。
+-- 条件全部满足的规则都会生效，价格按 adjust_percent 之和调整(负数为降价)。
+CREATE TABLE PricingRule
+(
+    id             INT AUTO_INCREMENT PRIMARY KEY,
+    res_type       INT  NULL,
+    min_load       INT  NULL,
+    max_load       INT  NULL,
+    min_days       INT  NULL,
+    max_days       INT  NULL,
+    weekday        INT  NULL,
+    adjust_percent INT  NOT NULL
+);
+
+-- 预定时报出的价格(按天预定的为总价)，之后调整规则不影响已有的预定；
+-- 旧数据和套餐中的项目为 NULL，按目录价格计算
+ALTER TABLE Reservation
+    ADD COLUMN quoted_price INT UNSIGNED NULL;
+
+-- 例：满座率 80% 以上涨价 20%，出发前 30 天以上早鸟 9 折，周五出发的航班涨价 10%
+-- INSERT INTO PricingRule (res_type, min_load, adjust_percent) VALUES (NULL, 80, 20);
+-- INSERT INTO PricingRule (res_type, min_days, adjust_percent) VALUES (NULL, 30, -10);
+-- INSERT INTO PricingRule (res_type, weekday, adjust_percent) VALUES (1, 5, 10);
@@ -0,0 +1,14 @@
+-- 积分流水：每次积分变化一行，余额为 points 之和。
+-- kind：earn 预定获得，redeem 预定时抵扣(负数)，reverse 取消时扣回获得的积分(负数)，
+-- restore 取消时退还抵扣的积分；booking_ref 为对应的预定或套餐预订号
+CREATE TABLE LoyaltyLedger
+(
+    id          INT AUTO_INCREMENT PRIMARY KEY,
+    customer_id INT         NOT NULL,
+    booking_ref CHAR(6)     NULL,
+    kind        VARCHAR(10) NOT NULL,
+    points      INT         NOT NULL,
+    created_at  DATETIME    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_loyalty_customer (customer_id),
+    INDEX idx_loyalty_booking_ref (booking_ref)
+);
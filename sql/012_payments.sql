@@ -0,0 +1,19 @@
+-- 预定的付款和退款，每次向支付渠道发起的请求一行。
+-- kind：charge 付款，refund 退款(charge_id 为对应的付款)；
+-- status：pending 已发起还没有结果，approved 成功，declined 被拒绝，timeout 超时(退款下次启动时重试)
+CREATE TABLE Payment
+(
+    id             INT AUTO_INCREMENT PRIMARY KEY,
+    customer_id    INT          NOT NULL,
+    booking_ref    CHAR(6)      NOT NULL,
+    kind           VARCHAR(10)  NOT NULL,
+    amount         INT UNSIGNED NOT NULL,
+    status         VARCHAR(10)  NOT NULL,
+    provider       VARCHAR(20)  NOT NULL,
+    transaction_id VARCHAR(64)  NULL,
+    charge_id      INT          NULL,
+    last_error     VARCHAR(200) NULL,
+    created_at     DATETIME     NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    INDEX idx_payment_booking_ref (booking_ref),
+    INDEX idx_payment_customer (customer_id)
+);
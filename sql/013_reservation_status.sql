@@ -0,0 +1,29 @@
+-- 预定的状态：held 等待付款时暂留，到 hold_until 未付款则变为 expired；
+-- confirmed 已确认，cancelled 已取消，completed 行程已结束。
+-- 取消和过期的预定不再删除，保留为历史记录；status_at 为最近一次状态变化的时间
+ALTER TABLE Reservation
+    ADD COLUMN status     VARCHAR(10) NOT NULL DEFAULT 'confirmed',
+    ADD COLUMN hold_until DATETIME    NULL,
+    ADD COLUMN status_at  DATETIME    NULL,
+    ADD INDEX idx_reservation_hold (status, hold_until);
+
+-- 取消或过期时归还余量，和删除预定时的触发器一样按类型更新(租车按日期共享，不需要)
+DELIMITER //
+CREATE TRIGGER reservation_released
+    AFTER UPDATE
+    ON Reservation
+    FOR EACH ROW
+BEGIN
+    IF OLD.status IN ('held', 'confirmed', 'completed')
+        AND NEW.status IN ('cancelled', 'expired') THEN
+        CASE OLD.res_type
+            WHEN 1 THEN UPDATE Flights SET num_available = num_available + 1 WHERE flight_num = OLD.res_id;
+            WHEN 2 THEN UPDATE Hotels SET num_available = num_available + 1 WHERE hotel_num = OLD.res_id;
+            WHEN 3 THEN UPDATE Bus SET num_available = num_available + 1 WHERE bus_num = OLD.res_id;
+            WHEN 4 THEN UPDATE Trains SET num_available = num_available + 1 WHERE train_num = OLD.res_id;
+            ELSE BEGIN
+            END;
+        END CASE;
+    END IF;
+END //
+DELIMITER ;
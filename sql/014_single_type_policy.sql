@@ -0,0 +1,13 @@
+-- MySQL 的 UNIQUE 索引允许多个 NULL，同一类型可能存在多条类型级取消政策。
+-- 用生成列把 NULL 换成空字符串，保证每个类型只有一条类型级政策。
+
+-- 已有的重复类型级政策只保留最新的一条
+DELETE a
+FROM CancellationPolicy a
+         JOIN CancellationPolicy b
+              ON a.res_type = b.res_type AND a.res_id IS NULL AND b.res_id IS NULL AND a.id < b.id;
+
+ALTER TABLE CancellationPolicy
+    ADD COLUMN policy_key VARCHAR(20) AS (IFNULL(res_id, '')) STORED,
+    DROP INDEX uk_policy,
+    ADD UNIQUE INDEX uk_policy (res_type, policy_key);
@@ -0,0 +1,6 @@
+-- 取消或过期的套餐不再删除，和其中的各项预定一样保留为历史记录，
+-- 退款和积分记录仍能通过套餐号找到对应的套餐。
+-- 状态和其中的各项预定相同：held 待付款，confirmed 已确认，cancelled 已取消，expired 已过期
+ALTER TABLE PackageBooking
+    ADD COLUMN status    VARCHAR(10) NOT NULL DEFAULT 'confirmed',
+    ADD COLUMN status_at DATETIME    NULL;
@@ -0,0 +1,3 @@
+-- 取消或过期时归还余量改由程序按目录(Category)的表和主键更新，
+-- 新增的目录不必再改触发器；预定时扣减余量的触发器不变
+DROP TRIGGER IF EXISTS reservation_released;
@@ -0,0 +1,112 @@
+//! What the storage layer and the screens need to know about a catalog.
+//!
+//! Every kind of thing a customer can book (flights, hotels, buses, ...)
+//! implements [`Bookable`] and describes its table in a [`Category`]. Booking,
+//! cancelling, querying and the consistency check are written once against the
+//! category, so a new kind only needs its own module and an entry in
+//! [`CATEGORIES`].
+
+use crate::bus::Bus;
+use crate::car_rental::CarRental;
+use crate::flight::Flight;
+use crate::hotels::Hotels;
+use crate::i18n::tr;
+use crate::train::Train;
+use crate::ToRow;
+use prettytable::{Attr, Cell, Row};
+
+/// the cities an item is tied to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Place {
+    /// goes from the first city column to the second one, e.g. a flight
+    Route(&'static str, &'static str),
+    /// stays in one city, e.g. a hotel
+    Location(&'static str),
+    /// moves between two cities that must already be on the route, e.g. a
+    /// rental car picked up in one city and dropped off in another
+    OnRoute(&'static str, &'static str),
+}
+
+impl Place {
+    pub fn columns(&self) -> Vec<&'static str> {
+        match self {
+            Place::Route(from, to) | Place::OnRoute(from, to) => vec![from, to],
+            Place::Location(location) => vec![location],
+        }
+    }
+}
+
+/// the table behind a catalog.
+///
+/// Each table has a `price` column and a column counting what is still
+/// available called `num_available`, the others are named here. The price of a
+/// dated category is charged per day of the reservation.
+pub struct Category {
+    /// number stored in `Reservation.res_type`
+    pub(crate) res_type: u32,
+    pub(crate) table: &'static str,
+    pub(crate) key_column: &'static str,
+    /// total seats/rooms, reservations may never outnumber it
+    pub(crate) capacity_column: &'static str,
+    /// when the trip starts, cancellation fees count the hours before it
+    pub(crate) time_column: &'static str,
+    /// when the trip is over, e.g. the arrival or check-out, may be NULL
+    pub(crate) end_column: &'static str,
+    /// reservations cover a [`Period`](crate::period::Period), the capacity is
+    /// shared by the reservations whose periods overlap
+    pub(crate) dated: bool,
+    /// columns read by [`Bookable::from_row`], the key first
+    pub(crate) columns: &'static str,
+    pub(crate) place: Place,
+    /// message key of the name, e.g. `type.flight`
+    pub(crate) name: &'static str,
+    /// message keys of the table header, one per column
+    pub(crate) header: &'static [&'static str],
+    pub(crate) to_row: fn(mysql::Row) -> Row,
+}
+
+impl Category {
+    pub fn res_type(&self) -> u32 {
+        self.res_type
+    }
+    pub fn dated(&self) -> bool {
+        self.dated
+    }
+    pub fn place(&self) -> Place {
+        self.place
+    }
+    pub fn name(&self) -> &'static str {
+        tr(self.name)
+    }
+
+    pub fn header(&self) -> Row {
+        Row::new(
+            self.header
+                .iter()
+                .map(|x| Cell::new(tr(x)).with_style(Attr::Bold))
+                .collect(),
+        )
+    }
+}
+
+/// something a customer can book, read from the table of its [`Category`]
+pub trait Bookable: ToRow + Sized {
+    const CATEGORY: Category;
+
+    /// build the item from the columns of [`Category::columns`]
+    fn from_row(row: mysql::Row) -> Self;
+}
+
+/// every catalog, in the order of the menus
+pub const CATEGORIES: [&Category; 5] = [
+    &Flight::CATEGORY,
+    &Bus::CATEGORY,
+    &Hotels::CATEGORY,
+    &Train::CATEGORY,
+    &CarRental::CATEGORY,
+];
+
+/// the category of a `Reservation.res_type`
+pub fn category(res_type: u32) -> Option<&'static Category> {
+    CATEGORIES.iter().copied().find(|x| x.res_type == res_type)
+}
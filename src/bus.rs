@@ -1,3 +1,4 @@
+use crate::bookable::{Bookable, Category, Place};
 use crate::ToRow;
 use prettytable::{Cell, Row};
 
@@ -46,3 +47,25 @@ impl ToRow for Bus {
         ])
     }
 }
+
+impl Bookable for Bus {
+    const CATEGORY: Category = Category {
+        res_type: 3,
+        table: "Bus",
+        key_column: "bus_num",
+        capacity_column: "num_bus",
+        time_column: "depart_time",
+        end_column: "arrive_time",
+        dated: false,
+        columns: "bus_num, location, price",
+        place: Place::Location("location"),
+        name: "type.bus",
+        header: &["header.bus_num", "header.location", "header.price"],
+        to_row: |row| Bus::from_row(row).to_row(),
+    };
+
+    fn from_row(row: mysql::Row) -> Self {
+        let (bus_num, location, price) = mysql::from_row(row);
+        Bus::new(bus_num, location, price)
+    }
+}
@@ -0,0 +1,93 @@
+/// cancellation policy of a catalog type, or of a single flight/hotel/bus.
+///
+/// Cancelling is free until `free_hours` before departure/check-in, after that
+/// `fee_percent` of the price is kept. Non-refundable fares never refund anything.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CancellationPolicy {
+    free_hours: u32,
+    fee_percent: u32,
+    refundable: bool,
+}
+
+impl CancellationPolicy {
+    pub fn new(free_hours: u32, fee_percent: u32, refundable: bool) -> Self {
+        CancellationPolicy {
+            free_hours,
+            fee_percent: fee_percent.min(100),
+            refundable,
+        }
+    }
+
+    /// policy used when nothing is configured: cancel for free at any time
+    pub fn free() -> Self {
+        CancellationPolicy::new(0, 0, true)
+    }
+
+    pub fn free_hours(&self) -> u32 {
+        self.free_hours
+    }
+    pub fn fee_percent(&self) -> u32 {
+        self.fee_percent
+    }
+    pub fn refundable(&self) -> bool {
+        self.refundable
+    }
+
+    /// work out the fee and the refund of cancelling something that costs `price`.
+    ///
+    /// `hours_before` is the number of hours left until departure/check-in,
+    /// `None` when the item has no scheduled time.
+    pub fn quote(&self, price: u32, hours_before: Option<i64>) -> CancelQuote {
+        let fee = if !self.refundable {
+            price
+        } else {
+            match hours_before {
+                // 费用不超过价格，按 u64 计算不会溢出
+                Some(hours) if hours < self.free_hours as i64 => {
+                    (u64::from(price) * u64::from(self.fee_percent) / 100) as u32
+                }
+                _ => 0,
+            }
+        };
+        CancelQuote {
+            price,
+            fee,
+            refund: price - fee,
+            hours_before,
+        }
+    }
+}
+
+/// what the customer gets back when cancelling a reservation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CancelQuote {
+    price: u32,
+    fee: u32,
+    refund: u32,
+    hours_before: Option<i64>,
+}
+
+impl CancelQuote {
+    pub fn price(&self) -> u32 {
+        self.price
+    }
+    pub fn fee(&self) -> u32 {
+        self.fee
+    }
+    pub fn refund(&self) -> u32 {
+        self.refund
+    }
+    pub fn hours_before(&self) -> Option<i64> {
+        self.hours_before
+    }
+
+    /// the quote of a booking that was never paid, nothing is kept and nothing
+    /// comes back
+    pub fn unpaid(self) -> Self {
+        CancelQuote {
+            fee: 0,
+            refund: 0,
+            ..self
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use crate::bookable::{Bookable, Category, Place};
+use crate::ToRow;
+use prettytable::{Cell, Row};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct CarRental {
+    car_num: String,
+    vehicle_class: String,
+    pickup_city: String,
+    dropoff_city: String,
+    daily_rate: u32,
+    fleet_size: u32,
+}
+
+impl CarRental {
+    pub fn new(
+        car_num: String,
+        vehicle_class: String,
+        pickup_city: String,
+        dropoff_city: String,
+        daily_rate: u32,
+        fleet_size: u32,
+    ) -> Self {
+        CarRental {
+            car_num,
+            vehicle_class,
+            pickup_city,
+            dropoff_city,
+            daily_rate,
+            fleet_size,
+        }
+    }
+
+    pub fn car_num(&self) -> &str {
+        &self.car_num
+    }
+    pub fn vehicle_class(&self) -> &str {
+        &self.vehicle_class
+    }
+    pub fn pickup_city(&self) -> &str {
+        &self.pickup_city
+    }
+    pub fn dropoff_city(&self) -> &str {
+        &self.dropoff_city
+    }
+    pub fn daily_rate(&self) -> u32 {
+        self.daily_rate
+    }
+    pub fn fleet_size(&self) -> u32 {
+        self.fleet_size
+    }
+
+    pub fn set_car_num(&mut self, car_num: String) {
+        self.car_num = car_num;
+    }
+    pub fn set_vehicle_class(&mut self, vehicle_class: String) {
+        self.vehicle_class = vehicle_class;
+    }
+    pub fn set_pickup_city(&mut self, pickup_city: String) {
+        self.pickup_city = pickup_city;
+    }
+    pub fn set_dropoff_city(&mut self, dropoff_city: String) {
+        self.dropoff_city = dropoff_city;
+    }
+    pub fn set_daily_rate(&mut self, daily_rate: u32) {
+        self.daily_rate = daily_rate;
+    }
+    pub fn set_fleet_size(&mut self, fleet_size: u32) {
+        self.fleet_size = fleet_size;
+    }
+}
+
+impl ToRow for CarRental {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(self.car_num()),
+            Cell::new(self.vehicle_class()),
+            Cell::new(self.pickup_city()),
+            Cell::new(self.dropoff_city()),
+            Cell::new(&self.daily_rate().to_string()),
+            Cell::new(&self.fleet_size().to_string()),
+        ])
+    }
+}
+
+impl Bookable for CarRental {
+    const CATEGORY: Category = Category {
+        res_type: 5,
+        table: "CarRentals",
+        key_column: "car_num",
+        capacity_column: "num_cars",
+        // 取车当天零点起算
+        time_column: "TIMESTAMP(Reservation.start_date)",
+        end_column: "TIMESTAMP(Reservation.end_date)",
+        dated: true,
+        columns: "car_num, vehicle_class, pickup_city, dropoff_city, price, num_cars",
+        place: Place::OnRoute("pickup_city", "dropoff_city"),
+        name: "type.car",
+        header: &[
+            "header.car_num",
+            "header.vehicle_class",
+            "header.pickup_city",
+            "header.dropoff_city",
+            "header.daily_rate",
+            "header.fleet_size",
+        ],
+        to_row: |row| CarRental::from_row(row).to_row(),
+    };
+
+    fn from_row(row: mysql::Row) -> Self {
+        let (car_num, vehicle_class, pickup_city, dropoff_city, daily_rate, fleet_size) =
+            mysql::from_row(row);
+        CarRental::new(
+            car_num,
+            vehicle_class,
+            pickup_city,
+            dropoff_city,
+            daily_rate,
+            fleet_size,
+        )
+    }
+}
@@ -0,0 +1,216 @@
+use crate::bookable::{Category, Place};
+use crate::query::Query;
+
+/// how the catalog query screens order their rows
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    #[default]
+    Default,
+    PriceAsc,
+    PriceDesc,
+    City,
+}
+
+impl SortOrder {
+    /// the `ORDER BY` columns of `category`, ties are broken by its key
+    pub fn order_by(&self, category: &Category) -> String {
+        let city_columns = category.place.columns().join(", ");
+        let key_column = category.key_column;
+        match self {
+            SortOrder::Default => key_column.to_string(),
+            SortOrder::PriceAsc => format!("price ASC, {}", key_column),
+            SortOrder::PriceDesc => format!("price DESC, {}", key_column),
+            SortOrder::City => format!("{}, {}", city_columns, key_column),
+        }
+    }
+}
+
+/// conditions used to search flights, hotels and buses.
+///
+/// `from_city`/`arrive_city` only apply to flights and `location` only applies to
+/// hotels and buses, `None` means "don't filter on it".
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CatalogFilter {
+    from_city: Option<String>,
+    arrive_city: Option<String>,
+    location: Option<String>,
+    min_price: Option<u32>,
+    max_price: Option<u32>,
+    available_only: bool,
+    sort: SortOrder,
+}
+
+impl CatalogFilter {
+    pub fn new() -> Self {
+        CatalogFilter::default()
+    }
+
+    pub fn from_city(&self) -> Option<&str> {
+        self.from_city.as_deref()
+    }
+    pub fn arrive_city(&self) -> Option<&str> {
+        self.arrive_city.as_deref()
+    }
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+    pub fn min_price(&self) -> Option<u32> {
+        self.min_price
+    }
+    pub fn max_price(&self) -> Option<u32> {
+        self.max_price
+    }
+    pub fn available_only(&self) -> bool {
+        self.available_only
+    }
+    pub fn sort(&self) -> SortOrder {
+        self.sort
+    }
+
+    pub fn set_from_city(&mut self, from_city: Option<String>) {
+        self.from_city = from_city;
+    }
+    pub fn set_arrive_city(&mut self, arrive_city: Option<String>) {
+        self.arrive_city = arrive_city;
+    }
+    pub fn set_location(&mut self, location: Option<String>) {
+        self.location = location;
+    }
+    pub fn set_min_price(&mut self, min_price: Option<u32>) {
+        self.min_price = min_price;
+    }
+    pub fn set_max_price(&mut self, max_price: Option<u32>) {
+        self.max_price = max_price;
+    }
+    pub fn set_available_only(&mut self, available_only: bool) {
+        self.available_only = available_only;
+    }
+    pub fn set_sort(&mut self, sort: SortOrder) {
+        self.sort = sort;
+    }
+
+    /// append the `WHERE` condition of a search in `category` to `query`.
+    ///
+    /// The city columns of the category are matched against the departure/arrival
+    /// or the location, the values are only ever passed as bound parameters.
+    pub fn condition(&self, query: Query, category: &Category) -> Query {
+        match category.place {
+            Place::Route(from, to) | Place::OnRoute(from, to) => {
+                self.city_condition(query, &[(from, self.from_city()), (to, self.arrive_city())])
+            }
+            Place::Location(location) => self.city_condition(query, &[(location, self.location())]),
+        }
+    }
+
+    fn city_condition(&self, mut query: Query, cities: &[(&str, Option<&str>)]) -> Query {
+        query = query.push(" WHERE 1 = 1");
+        for (column, city) in cities {
+            if let Some(city) = city {
+                query = query.push(&format!(" AND {} = ?", column)).bind(*city);
+            }
+        }
+        if let Some(min_price) = self.min_price {
+            query = query.push(" AND price >= ?").bind(min_price);
+        }
+        if let Some(max_price) = self.max_price {
+            query = query.push(" AND price <= ?").bind(max_price);
+        }
+        if self.available_only {
+            query = query.push(" AND num_available > 0");
+        }
+        query
+    }
+}
+
+/// default number of rows on one page of the catalog screens
+pub const DEFAULT_PAGE_SIZE: u32 = 20;
+
+/// one page of a catalog listing, `number` starts from 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Page {
+    number: u32,
+    size: u32,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Page::new(1, DEFAULT_PAGE_SIZE)
+    }
+}
+
+impl Page {
+    pub fn new(number: u32, size: u32) -> Self {
+        Page {
+            number: number.max(1),
+            size: size.max(1),
+        }
+    }
+
+    pub fn number(&self) -> u32 {
+        self.number
+    }
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+    pub fn offset(&self) -> u64 {
+        (self.number as u64 - 1) * self.size as u64
+    }
+
+    /// number of pages needed to show `total` rows, at least one
+    pub fn total_pages(&self, total: u32) -> u32 {
+        total.div_ceil(self.size).max(1)
+    }
+
+    pub fn set_number(&mut self, number: u32) {
+        self.number = number.max(1);
+    }
+    pub fn set_size(&mut self, size: u32) {
+        self.size = size.max(1);
+        self.number = 1;
+    }
+
+    /// move to where `command` points, never past the last of `total_pages`
+    pub fn apply(&mut self, command: PageCommand, total_pages: u32) {
+        match command {
+            PageCommand::Next => self.set_number((self.number + 1).min(total_pages)),
+            PageCommand::Previous => self.set_number(self.number - 1),
+            PageCommand::Jump(number) => self.set_number(number.min(total_pages)),
+            PageCommand::Size(size) => self.set_size(size),
+            PageCommand::Back => (),
+        }
+    }
+}
+
+/// paging commands start with this, so they never look like an item number
+pub const PAGE_COMMAND_PREFIX: char = ':';
+
+/// what the user can type while a catalog is listed page by page
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageCommand {
+    Next,
+    Previous,
+    Jump(u32),
+    Size(u32),
+    Back,
+}
+
+impl PageCommand {
+    /// `:n`, `:p`, `:g<page>`, `:s<size>` or `:q`, `None` for anything else
+    pub fn parse(input: &str) -> Option<Self> {
+        let command = input.trim().strip_prefix(PAGE_COMMAND_PREFIX)?;
+        match command {
+            "n" => Some(PageCommand::Next),
+            "p" => Some(PageCommand::Previous),
+            "q" => Some(PageCommand::Back),
+            _ => {
+                if let Some(number) = command.strip_prefix('g') {
+                    number.parse().ok().map(PageCommand::Jump)
+                } else if let Some(size) = command.strip_prefix('s') {
+                    size.parse().ok().map(PageCommand::Size)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
@@ -1,312 +1,1167 @@
-use crate::bus::Bus;
-use crate::customer::Customer;
-use crate::flight::Flight;
-use crate::hotels::Hotels;
-use crate::reservation::Reservation;
+use crate::bookable::{category, Category, Place, CATEGORIES};
+use crate::cancellation::CancelQuote;
+use crate::catalog_filter::{CatalogFilter, Page, PageCommand, SortOrder, PAGE_COMMAND_PREFIX};
+use crate::customer::{Customer, ProfileField};
+use crate::ical::{self, Event};
+use crate::itinerary::{self, ItineraryItem};
+use crate::loyalty::{LoyaltyAccount, PointsEntry, POINTS_PER_DISCOUNT};
+use crate::notification::{self, Template, Transport};
+use crate::payment::{Gateway, Payment};
+use crate::period::{Date, Period};
+use crate::prompt::{Interrupt, Output, Prompt, PromptResult};
+use crate::report::{Report, ReportKind};
+use crate::reservation::{normalize_booking_ref, Reservation};
+use crate::suggestion::{numbered_header, route_cities, Suggestion, SUGGESTIONS_PER_CITY};
+use crate::tui;
 use crate::view::View;
-use crate::{read, MySQLConnection, Status};
-use lazy_static::lazy_static;
-use mysql::PooledConn;
+use crate::{t, MySQLConnection, Status};
 use prettytable::Row;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
-use std::io::{stdin, stdout, Read, Stdin, Write};
+use std::fmt::Display;
+use std::fs;
+use std::io::{stdout, BufRead, Write};
 use std::iter::FromIterator;
 use std::process::exit;
+use std::rc::Rc;
 use std::thread::sleep;
 use std::time;
 
-lazy_static! {
-    static ref FLIGHT_HINT: Row = row![b->"航班号", b->"价格", b->"出发城市", b->"到达城市"];
-    static ref HOTEL_HINT: Row = row![b->"大巴号", b->"地点", b->"价格"];
-    static ref BUS_HINT: Row = row![b->"酒店号", b->"地点", b->"价格"];
+// 表头跟随当前语言，所以每次使用时再生成
+pub(crate) fn reservation_hint() -> Row {
+    row![
+        b->t!("header.booking_ref"),
+        b->t!("header.type"),
+        b->t!("header.item_num"),
+        b->t!("header.period"),
+        b->t!("header.status")
+    ]
+}
+
+pub(crate) fn customer_hint() -> Row {
+    row![
+        b->t!("header.customer_id"),
+        b->t!("profile.name"),
+        b->t!("profile.email"),
+        b->t!("profile.phone")
+    ]
+}
+
+pub(crate) fn package_hint() -> Row {
+    row![
+        b->t!("header.package_id"),
+        b->t!("header.package_name"),
+        b->t!("header.price"),
+        b->t!("header.package_content")
+    ]
+}
+
+pub(crate) fn pricing_hint() -> Row {
+    row![
+        b->t!("header.type"),
+        b->t!("header.item_num"),
+        b->t!("pricing.base"),
+        b->t!("report.load"),
+        b->t!("pricing.days_before"),
+        b->t!("pricing.weekday_header"),
+        b->t!("pricing.price")
+    ]
+}
+
+pub(crate) fn package_booking_hint() -> Row {
+    row![
+        b->t!("header.booking_ref"),
+        b->t!("header.package_id"),
+        b->t!("header.package_name"),
+        b->t!("header.price"),
+        b->t!("header.status")
+    ]
+}
+
+pub(crate) fn payment_hint() -> Row {
+    row![
+        b->t!("payment.date"),
+        b->t!("payment.kind"),
+        b->t!("payment.amount"),
+        b->t!("payment.status"),
+        b->t!("payment.transaction_id")
+    ]
+}
+
+pub(crate) fn points_hint() -> Row {
+    row![
+        b->t!("loyalty.date"),
+        b->t!("loyalty.kind"),
+        b->t!("header.booking_ref"),
+        b->t!("loyalty.points")
+    ]
 }
 
 pub struct Controller {
-    flights: Vec<Flight>,
-    hotels: Vec<Hotels>,
-    bus: Vec<Bus>,
-    customers: Vec<Customer>,
     reservations: Vec<Reservation>,
     connection: MySQLConnection,
+    prompt: Prompt,
+    view: View,
+    /// the stream shared by the prompt, the view and the connection
+    out: Output,
     current_user: u32,
+    /// where confirmation emails go, `None` sends nothing
+    transport: Option<Box<dyn Transport>>,
+    gateway: Option<Box<dyn Gateway>>,
+    /// page the HTML itinerary is rendered from
+    itinerary_template: String,
 }
 
 impl Controller {
     pub fn new(sql: MySQLConnection) -> Controller {
+        let out: Output = Rc::new(RefCell::new(Box::new(stdout())));
+        Controller::with_parts(
+            sql,
+            Prompt::stdin(out.clone()),
+            View::stdout(out.clone()),
+            out,
+        )
+    }
+
+    /// read the prompt mode from `reader` and write everything it shows into `writer`,
+    /// e.g. to drive a whole session from a script
+    pub fn with_io(
+        sql: MySQLConnection,
+        reader: Box<dyn BufRead>,
+        writer: Box<dyn Write>,
+    ) -> Controller {
+        let out: Output = Rc::new(RefCell::new(writer));
+        Controller::with_parts(
+            sql,
+            Prompt::new(reader, out.clone()),
+            View::new(out.clone()),
+            out,
+        )
+    }
+
+    // 数据库层的错误也写到同一个输出
+    fn with_parts(mut sql: MySQLConnection, prompt: Prompt, view: View, out: Output) -> Controller {
+        sql.set_output(out.clone());
         Controller {
-            flights: vec![],
-            hotels: vec![],
-            bus: vec![],
-            customers: vec![],
             reservations: vec![],
             connection: sql,
+            prompt,
+            view,
+            out,
             current_user: 0,
+            transport: None,
+            gateway: None,
+            itinerary_template: itinerary::DEFAULT_TEMPLATE.to_string(),
         }
     }
 
-    pub fn connection_mut(&mut self) -> &mut MySQLConnection {
-        &mut self.connection
+    /// send confirmation emails of bookings and cancellations through `transport`
+    pub fn set_transport(&mut self, transport: Box<dyn Transport>) {
+        self.transport = Some(transport);
     }
 
-    pub fn book_hotel(&mut self, res_id: String) -> bool {
-        self.connection.book_hotel(self.current_user, res_id)
+    /// charge new bookings and refund cancellations through `gateway`
+    pub fn set_gateway(&mut self, gateway: Box<dyn Gateway>) {
+        self.gateway = Some(gateway);
     }
 
-    pub fn book_flight(&mut self, res_id: String) -> bool {
-        self.connection.book_flight(self.current_user, res_id)
+    /// try every refund that got no answer from the gateway again, return how
+    /// many went through
+    pub fn settle_refunds(&mut self) -> usize {
+        let gateway = match self.gateway.as_mut() {
+            Some(gateway) => gateway,
+            None => return 0,
+        };
+        let mut settled = 0;
+        for (id, transaction_id, amount) in self.connection.pending_refunds(gateway.name()) {
+            let result = gateway.refund(&transaction_id, amount);
+            self.connection.payment_finished(id, &result);
+            if result.is_ok() {
+                settled += 1;
+            }
+        }
+        settled
     }
 
-    pub fn book_bus(&mut self, res_id: String) -> bool {
-        self.connection.book_bus(self.current_user, res_id)
+    /// what `booking_ref` of the current user costs, a reservation or a tour package
+    fn booking_price(&mut self, booking_ref: &str) -> Option<u32> {
+        if let Some(booking) = self
+            .connection
+            .query_package_by_ref(self.current_user, booking_ref)
+        {
+            return Some(booking.price());
+        }
+        let reservation = self
+            .connection
+            .query_reservation_by_ref(self.current_user, booking_ref)?;
+        self.cancel_quote(reservation.id()).map(|x| x.price())
     }
 
-    pub fn query_hotel(&mut self) {
-        self.hotels = self.connection.query_hotel(self.current_user);
+    /// charge a new booking and confirm its hold, the booking is let go when
+    /// the payment does not go through
+    fn pay(&mut self, booking_ref: &str) -> bool {
+        // 全部用积分抵扣时不需要付款
+        let amount = match self.gateway {
+            Some(_) => self.booking_price(booking_ref).unwrap_or_default(),
+            None => 0,
+        };
+        if let Some(gateway) = self.gateway.as_mut().filter(|_| amount > 0) {
+            let id = self.connection.charge_started(
+                self.current_user,
+                booking_ref,
+                amount,
+                gateway.name(),
+            );
+            let result = gateway.charge(booking_ref, amount);
+            self.connection.payment_finished(id, &result);
+            if let Err(e) = result {
+                self.view.error(t!("error.payment_failed", e));
+                self.connection
+                    .expire_booking(self.current_user, booking_ref);
+                return false;
+            }
+            self.view.payment_approved(amount);
+        }
+        // 付款期间保留时间已到，预定已被释放
+        if !self
+            .connection
+            .confirm_booking(self.current_user, booking_ref)
+        {
+            self.view.error(t!("error.hold_expired"));
+            self.refund(booking_ref, amount);
+            return false;
+        }
+        true
     }
 
-    pub fn query_flight(&mut self) {
-        self.flights = self.connection.query_flight(self.current_user);
+    /// hold new bookings for `minutes` until they are paid
+    pub fn set_hold_minutes(&mut self, minutes: u32) {
+        self.connection.set_hold_minutes(Some(minutes));
     }
 
-    pub fn query_bus(&mut self) {
-        self.bus = self.connection.query_bus(self.current_user);
+    /// give back `amount` of what `booking_ref` was paid, a refund without an
+    /// answer stays pending for [`Controller::settle_refunds`]
+    fn refund(&mut self, booking_ref: &str, amount: u32) {
+        if amount == 0 {
+            return;
+        }
+        let (charge_id, transaction_id) = match self
+            .connection
+            .approved_charge(self.current_user, booking_ref)
+        {
+            Some(charge) => charge,
+            None => return,
+        };
+        let id = self.connection.refund_started(charge_id, amount);
+        let gateway = match self.gateway.as_mut() {
+            Some(gateway) => gateway,
+            None => return,
+        };
+        let result = gateway.refund(&transaction_id, amount);
+        self.connection.payment_finished(id, &result);
+        match result {
+            Ok(_) => self.view.refund_approved(amount),
+            Err(e) => self.view.error(t!("error.refund_failed", amount, e)),
+        }
     }
 
-    pub fn query_all_flight(&mut self) {
-        self.flights = self.connection.query_all_flight();
+    /// every charge and refund of `booking_ref` of the current user
+    pub fn payments(&mut self, booking_ref: &str) -> Vec<Payment> {
+        self.connection.payments(self.current_user, booking_ref)
     }
 
-    pub fn query_all_hotel(&mut self) {
-        self.hotels = self.connection.query_all_hotel();
+    /// try to send every queued email again, return how many went out
+    pub fn deliver_notifications(&mut self) -> usize {
+        match self.transport.as_mut() {
+            Some(transport) => notification::deliver(&mut self.connection, transport.as_mut()),
+            None => 0,
+        }
     }
 
-    pub fn query_all_bus(&mut self) {
-        self.bus = self.connection.query_all_bus();
+    /// queue an email to the current user if they have an address, then deliver the queue
+    fn notify(&mut self, template: Template, vars: &[(&str, &dyn Display)]) {
+        if self.transport.is_none() {
+            return;
+        }
+        let customer = match self.profile() {
+            Some(customer) => customer,
+            None => return,
+        };
+        let email = match customer.email() {
+            Some(email) => email,
+            None => return,
+        };
+        let name = customer.name();
+        let mut vars = vars.to_vec();
+        vars.push(("name", &name));
+        let message = template.render(email, &vars);
+        self.connection
+            .enqueue_notification(self.current_user, &message);
+        self.deliver_notifications();
+    }
+
+    /// confirm a new reservation by email
+    fn notify_booked(&mut self, booking_ref: &str) {
+        let reservation = match self
+            .connection
+            .query_reservation_by_ref(self.current_user, booking_ref)
+        {
+            Some(reservation) => reservation,
+            None => return,
+        };
+        let price = self
+            .cancel_quote(reservation.id())
+            .map(|x| x.price())
+            .unwrap_or_default();
+        let item = reservation_item(&reservation);
+        self.notify(
+            Template::BookingConfirmed,
+            &[
+                ("booking_ref", &booking_ref),
+                ("item", &item),
+                ("price", &price),
+            ],
+        );
+    }
+
+    pub fn book(&mut self, category: &Category, res_id: String) -> Option<String> {
+        let booking_ref = self.connection.book(category, self.current_user, res_id)?;
+        if !self.pay(&booking_ref) {
+            return None;
+        }
+        self.notify_booked(&booking_ref);
+        Some(booking_ref)
+    }
+
+    /// book an item of a dated category for the days of `period`
+    pub fn book_period(
+        &mut self,
+        category: &Category,
+        res_id: String,
+        period: Period,
+    ) -> Option<String> {
+        let booking_ref =
+            self.connection
+                .book_period(category, self.current_user, res_id, period)?;
+        if !self.pay(&booking_ref) {
+            return None;
+        }
+        self.notify_booked(&booking_ref);
+        Some(booking_ref)
+    }
+
+    /// book an item, with the days of `period` for dated categories, paying as
+    /// much as possible with loyalty points
+    pub fn book_with_points(
+        &mut self,
+        category: &Category,
+        res_id: String,
+        period: Option<Period>,
+    ) -> Option<String> {
+        let booking_ref =
+            self.connection
+                .book_with_points(category, self.current_user, res_id, period)?;
+        if !self.pay(&booking_ref) {
+            return None;
+        }
+        self.notify_booked(&booking_ref);
+        Some(booking_ref)
+    }
+
+    /// book a whole tour package, nothing is booked if any of its items is sold out
+    pub fn book_package(&mut self, package_id: &str) -> Option<String> {
+        let booking_ref = self
+            .connection
+            .book_package(self.current_user, package_id)?;
+        if !self.pay(&booking_ref) {
+            return None;
+        }
+        self.notify_package_booked(&booking_ref);
+        Some(booking_ref)
+    }
+
+    /// like [`Controller::book_package`], paying as much as possible with loyalty points
+    pub fn book_package_with_points(&mut self, package_id: &str) -> Option<String> {
+        let booking_ref = self
+            .connection
+            .book_package_with_points(self.current_user, package_id)?;
+        if !self.pay(&booking_ref) {
+            return None;
+        }
+        self.notify_package_booked(&booking_ref);
+        Some(booking_ref)
+    }
+
+    fn notify_package_booked(&mut self, booking_ref: &str) {
+        if let Some(booking) = self
+            .connection
+            .query_package_by_ref(self.current_user, booking_ref)
+        {
+            self.notify(
+                Template::BookingConfirmed,
+                &[
+                    ("booking_ref", &booking_ref),
+                    ("item", &booking.name()),
+                    ("price", &booking.price()),
+                ],
+            );
+        }
+    }
+
+    pub fn cancel_package(&mut self, booking_ref: &str) -> bool {
+        let quoted = self
+            .connection
+            .package_quote(self.current_user, booking_ref);
+        if !self
+            .connection
+            .cancel_package(self.current_user, booking_ref)
+        {
+            return false;
+        }
+        if let Some((booking, quote)) = quoted {
+            self.refund(booking_ref, quote.refund());
+            self.notify(
+                Template::BookingCancelled,
+                &[
+                    ("booking_ref", &booking_ref),
+                    ("item", &booking.name()),
+                    ("fee", &quote.fee()),
+                    ("refund", &quote.refund()),
+                ],
+            );
+        }
+        true
+    }
+
+    /// the cheapest available hotels, buses and everything else that stays in one
+    /// city, in each city of the flights and trains booked by the current user
+    pub fn suggest(&mut self) -> Vec<Suggestion> {
+        let mut routes: Vec<Vec<String>> = Vec::new();
+        for category in CATEGORIES.iter() {
+            if let Place::Route(..) = category.place() {
+                routes.extend(self.connection.booked_places(category, self.current_user));
+            }
+        }
+        let cities = route_cities(&routes);
+
+        let mut suggestions = Vec::new();
+        let page = Page::new(1, SUGGESTIONS_PER_CITY);
+        for category in CATEGORIES.iter() {
+            if let Place::Location(_) = category.place() {
+                for city in cities.iter() {
+                    let mut filter = CatalogFilter::new();
+                    filter.set_location(Some(city.clone()));
+                    filter.set_available_only(true);
+                    filter.set_sort(SortOrder::PriceAsc);
+                    suggestions.extend(
+                        self.search(category, &filter, &page)
+                            .into_iter()
+                            .map(|row| Suggestion::new(category, city.clone(), row)),
+                    );
+                }
+            }
+        }
+        suggestions
+    }
+
+    pub fn profile(&mut self) -> Option<Customer> {
+        self.connection.query_customer(self.current_user)
+    }
+
+    pub fn loyalty_account(&mut self) -> LoyaltyAccount {
+        self.connection.loyalty_account(self.current_user)
+    }
+
+    /// every change of the loyalty points of the current user, the latest first
+    pub fn points_statement(&mut self) -> Vec<PointsEntry> {
+        self.connection.points_statement(self.current_user)
+    }
+
+    /// validate `value` and store it into the profile of the current user,
+    /// the error is the message explaining what is wrong
+    pub fn update_profile(&mut self, field: ProfileField, value: &str) -> Result<(), &'static str> {
+        let value = field.validate(value)?;
+        self.connection
+            .update_profile(self.current_user, field, value);
+        Ok(())
+    }
+
+    pub fn is_admin(&mut self) -> bool {
+        self.connection.is_admin(self.current_user)
+    }
+
+    /// customers whose name, email or phone contains `keyword`, only for admins
+    pub fn search_customers(&mut self, keyword: &str) -> Vec<Customer> {
+        if !self.is_admin() {
+            return vec![];
+        }
+        self.connection.search_customers(keyword)
+    }
+
+    /// one of the sales reports, only for admins
+    pub fn report(&mut self, kind: ReportKind) -> Option<Report> {
+        if !self.is_admin() {
+            return None;
+        }
+        Some(self.connection.report(kind))
+    }
+
+    /// print what the pricing rules would charge for every item today, for the
+    /// admin menu and the `--simulate-pricing` command
+    pub fn show_pricing_simulation(&mut self) {
+        let quotes = self.connection.pricing_simulation();
+        self.view.querying(&pricing_hint(), &quotes);
+    }
+
+    /// what the trip of the current user costs
+    pub fn trip_total(&mut self) -> u64 {
+        self.connection.trip_total(self.current_user)
+    }
+
+    /// the items of `category` booked by the current user
+    pub fn query_booked(&mut self, category: &Category) -> Vec<Row> {
+        self.connection.query_rows(category, self.current_user)
+    }
+
+    pub fn query_reservations(&mut self) {
+        self.reservations = self.connection.query_reservations(self.current_user);
+    }
+
+    pub fn search(&mut self, category: &Category, filter: &CatalogFilter, page: &Page) -> Vec<Row> {
+        self.connection.search_rows(category, filter, page)
+    }
+
+    /// number of items of `category` that match `filter`
+    pub fn count_catalog(&mut self, category: &Category, filter: &CatalogFilter) -> u32 {
+        self.connection.count(category, filter)
+    }
+
+    pub fn cancel_quote(&mut self, id: u32) -> Option<CancelQuote> {
+        self.connection
+            .cancel_quote(self.current_user, id)
+            .map(|(_, quote)| quote)
+    }
+
+    pub fn cancel_reservation(&mut self, id: u32) -> bool {
+        let quoted = self.connection.cancel_quote(self.current_user, id);
+        if !self.connection.cancel_reservation(self.current_user, id) {
+            return false;
+        }
+        if let Some((reservation, quote)) = quoted {
+            self.refund(reservation.booking_ref(), quote.refund());
+            let item = reservation_item(&reservation);
+            self.notify(
+                Template::BookingCancelled,
+                &[
+                    ("booking_ref", &reservation.booking_ref()),
+                    ("item", &item),
+                    ("fee", &quote.fee()),
+                    ("refund", &quote.refund()),
+                ],
+            );
+        }
+        true
     }
 
     /// check consistency as soon as the system is opened or closed.
     /// error code: -2 stands for DBCC error
     pub fn check_consistency(&mut self) {
-        View::init_check();
+        self.view.init_check();
         let time = time::Duration::from_secs(1);
         sleep(time);
-        if self.connection.check_consistency() == false {
-            eprintln!("Fatal Error: Database Consistency Check(DBCC) failed");
+        if !self.connection.check_consistency() {
+            self.view.error(t!("app.dbcc_failed"));
             exit(-2);
         }
-        View::success_hint();
+        self.view.success_hint();
     }
 
-    pub fn login_view(&mut self) {
-        View::login_menu();
-        let mut login_cnt = 1;
-        while self.login() == false && login_cnt < 3 {
-            login_cnt += 1;
-            View::login_menu();
-        }
-        if login_cnt == 3 {
-            println!("三次输入账号错误, 程序自动退出...");
-            exit(-1);
+    /// log in or sign up, `Ok(false)` after three failed attempts
+    pub fn login_view(&mut self) -> PromptResult<bool> {
+        for _ in 0..3 {
+            self.view.login_menu();
+            if self.login()? {
+                return Ok(true);
+            }
         }
+        self.view.text(t!("login.too_many"));
+        Ok(false)
     }
 
-    fn sign_up(&mut self, id: u32) -> bool {
-        View::sign_up(id);
-        read!(comfirm as char);
-        let mut cnt = 0;
-        loop {
-            if comfirm == 'y' || comfirm == 'Y' {
-                self.add_user(id);
-                self.current_user = id;
-                return true;
-            } else if comfirm == 'n' || comfirm == 'N' {
-                return false;
-            } else {
-                println!("请输入正确的指令");
-                cnt += 1;
-                if cnt == 3 {
-                    println!("三次输入错误，程序自动退出...");
-                    break;
-                }
-            }
+    fn sign_up(&mut self, id: u32) -> PromptResult<bool> {
+        self.view.sign_up(id);
+        if !self.prompt.confirm(None)? {
+            return Ok(false);
         }
-        return false;
+        self.add_user(id)?;
+        self.current_user = id;
+        Ok(true)
     }
 
-    fn login(&mut self) -> bool {
-        read!(user_id as u32);
-        return if self.connection.user_exist(user_id) {
+    fn login(&mut self) -> PromptResult<bool> {
+        let user_id = self.prompt.read::<u32>()?;
+        if self.connection.user_exist(user_id) {
             self.current_user = user_id;
-            println!("您好，{}!", self.connection.query_user_name(user_id));
-            true
+            let name = self.connection.query_user_name(user_id);
+            self.view.text(&t!("login.welcome", name));
+            Ok(true)
         } else {
             self.sign_up(user_id)
-        };
+        }
     }
-    fn add_user(&mut self, id: u32) {
-        View::new_name();
-        read!(name as String);
+    fn add_user(&mut self, id: u32) -> PromptResult<()> {
+        self.view.new_name();
+        let name = self.prompt.text()?;
         self.connection.add_user(id, name);
+        Ok(())
     }
 
     fn user_menu(&mut self) -> Status {
-        View::user_menu();
-        read!(num as u32);
-        match num {
-            1 => self.booking(),
-            2 => self.cancel_book(),
-            3 => self.querying(),
-            4 => self.travel_path(),
-            5 => View::check_completeness(self.check_completeness()),
-            0 => return Status::Login,
-            _ => return Status::Quit,
+        let admin = self.is_admin();
+        self.view.user_menu(admin);
+        let res = match self.prompt.read::<u32>() {
+            Ok(1) => self.booking(),
+            Ok(2) => self.cancel_book(),
+            Ok(3) => self.querying(),
+            Ok(4) => {
+                self.travel_path();
+                Ok(())
+            }
+            Ok(5) => {
+                let complete = self.check_completeness();
+                self.view.check_completeness(complete);
+                Ok(())
+            }
+            Ok(6) => self.suggesting(),
+            Ok(7) => self.profile_menu(),
+            Ok(8) if admin => self.admin_menu(),
+            Ok(0) => return Status::Login,
+            Ok(9) => return Status::Quit,
+            Ok(_) => {
+                self.view.wrong_command();
+                Ok(())
+            }
+            Err(e) => Err(e),
         };
-        return Status::Continue;
+        match res {
+            // 返回上一级即回到主菜单
+            Ok(()) | Err(Interrupt::Back) => Status::Continue,
+            Err(Interrupt::Eof) => Status::Quit,
+        }
     }
 
-    fn booking(&mut self) -> bool {
-        View::booking_menu();
-        read!(num as u32);
-        match num {
-            1 => {
-                self.query_all_flight();
-                View::booking(&FLIGHT_HINT, self.flights());
-                read!(res_id as String);
-                let success = self.book_flight(res_id);
-                if success {
-                    View::success_hint();
-                }
-            }
-            2 => {
-                self.query_all_bus();
-                View::booking(&BUS_HINT, self.bus());
-                read!(res_id as String);
-                let success = self.book_bus(res_id);
-                if success {
-                    View::success_hint();
-                }
+    fn booking(&mut self) -> PromptResult<()> {
+        self.view.booking_menu();
+        let choice = self.prompt.read::<usize>()?;
+        let category = match menu_category(choice) {
+            Some(category) => category,
+            None if choice == CATEGORIES.len() + 1 => return self.package_booking(),
+            None => return Ok(()),
+        };
+        let res_id = match self.browse(category, &CatalogFilter::new(), true)? {
+            Some(res_id) => res_id,
+            None => return Ok(()),
+        };
+        let period = if category.dated() {
+            Some(self.read_period()?)
+        } else {
+            None
+        };
+        let booking_ref = match (self.ask_redeem()?, period) {
+            (true, period) => self.book_with_points(category, res_id, period),
+            (false, Some(period)) => self.book_period(category, res_id, period),
+            (false, None) => self.book(category, res_id),
+        };
+        if let Some(booking_ref) = booking_ref {
+            self.view.booking_ref(&booking_ref);
+        }
+        Ok(())
+    }
+
+    /// ask whether to pay with loyalty points, only when there are enough for a discount
+    fn ask_redeem(&mut self) -> PromptResult<bool> {
+        let account = self.loyalty_account();
+        if account.balance() < i64::from(POINTS_PER_DISCOUNT) {
+            return Ok(false);
+        }
+        self.view.redeem_hint(&account);
+        self.prompt.confirm(Some(false))
+    }
+
+    /// show the profile and edit the field the user picks, until they go back
+    fn profile_menu(&mut self) -> PromptResult<()> {
+        loop {
+            let customer = match self.profile() {
+                Some(customer) => customer,
+                None => return Ok(()),
+            };
+            let account = self.loyalty_account();
+            self.view.profile(&customer, &account);
+            let choice = self.prompt.read::<usize>()?;
+            if choice == ProfileField::ALL.len() + 1 {
+                let statement = self.points_statement();
+                self.view.querying(&points_hint(), &statement);
+                continue;
             }
-            3 => {
-                self.query_all_hotel();
-                View::booking(&HOTEL_HINT, self.hotels());
-                read!(res_id as String);
-                let success = self.book_hotel(res_id);
-                if success {
-                    View::success_hint();
+            let field = match choice.checked_sub(1).and_then(|x| ProfileField::ALL.get(x)) {
+                Some(field) => *field,
+                None => return Ok(()),
+            };
+            loop {
+                self.view.profile_value_hint(field);
+                let value = self.prompt.line()?;
+                match self.update_profile(field, &value) {
+                    Ok(()) => break,
+                    Err(message) => self.view.text(message),
                 }
             }
-            4 => {}
-            _ => {
-                return true;
+            self.view.success_hint();
+        }
+    }
+
+    fn admin_menu(&mut self) -> PromptResult<()> {
+        self.view.admin_menu();
+        match self.prompt.read::<u32>()? {
+            1 => {
+                self.view.customer_search_hint();
+                let keyword = self.prompt.text()?;
+                let customers = self.search_customers(&keyword);
+                self.view.querying(&customer_hint(), &customers);
             }
+            2 => self.report_menu()?,
+            3 => self.show_pricing_simulation(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// show the report the admin picks and export it as CSV if asked to
+    fn report_menu(&mut self) -> PromptResult<()> {
+        let kinds = ReportKind::all();
+        self.view.report_menu(&kinds);
+        let kind = match self
+            .prompt
+            .read::<usize>()?
+            .checked_sub(1)
+            .and_then(|x| kinds.get(x))
+        {
+            Some(kind) => *kind,
+            None => return Ok(()),
+        };
+        let report = match self.report(kind) {
+            Some(report) => report,
+            None => return Ok(()),
         };
-        return false;
+        self.view.report_title(&kind.title());
+        self.view.querying(&report.header(), &report.table_rows());
+        self.view.report_export_hint();
+        if self.prompt.confirm(Some(false))? {
+            let path = format!("{}.csv", kind.file_stem());
+            match fs::write(&path, report.to_csv()) {
+                Ok(()) => self.view.export_success(&path),
+                Err(e) => self.view.error(t!("booking.export_failed", e)),
+            }
+        }
+        Ok(())
     }
 
-    fn cancel_book(&mut self) -> bool {
-        View::cancel_menu();
-        read!(num as u32);
-        match num {
-            1 => {
-                self.query_flight();
-                View::querying(&FLIGHT_HINT, self.flights());
-                View::cancel_hint();
-                read!(res_id as String);
-                let success = self.cancel_flight(res_id);
-                if success {
-                    View::success_hint();
-                }
+    /// show the suggestions numbered per catalog and book the one the user picks
+    fn suggesting(&mut self) -> PromptResult<()> {
+        let suggestions = self.suggest();
+        if suggestions.is_empty() {
+            self.view.no_suggestions();
+            return Ok(());
+        }
+        for category in CATEGORIES.iter() {
+            let rows: Vec<Row> = suggestions
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| x.category().res_type() == category.res_type())
+                .map(|(i, x)| x.numbered_row(i + 1))
+                .collect();
+            if !rows.is_empty() {
+                self.view.suggestion_category(category.name());
+                self.view.querying(&numbered_header(category), &rows);
             }
-            2 => {
-                self.query_bus();
-                View::querying(&BUS_HINT, self.bus());
-                View::cancel_hint();
-                read!(res_id as String);
-                let success = self.cancel_bus(res_id);
-                if success {
-                    View::success_hint();
+        }
+
+        self.view.suggestion_prompt();
+        let suggestion = match self.prompt.optional::<usize>()? {
+            Some(number) => number.checked_sub(1).and_then(|x| suggestions.get(x)),
+            None => return Ok(()),
+        };
+        match suggestion {
+            Some(suggestion) => {
+                if let Some(booking_ref) = self.book(suggestion.category(), suggestion.res_id()) {
+                    self.view.booking_ref(&booking_ref);
                 }
             }
-            3 => {
-                self.query_hotel();
-                View::querying(&HOTEL_HINT, self.hotels());
-                View::cancel_hint();
-                read!(res_id as String);
-                let success = self.cancel_hotel(res_id);
-                if success {
-                    View::success_hint();
-                }
+            None => self.view.wrong_command(),
+        }
+        Ok(())
+    }
+
+    /// list the tour packages and book the one the user picks
+    fn package_booking(&mut self) -> PromptResult<()> {
+        let packages = self.connection.packages();
+        self.view.querying(&package_hint(), &packages);
+        if packages.is_empty() {
+            return Ok(());
+        }
+        self.view.package_hint();
+        let package_id = self.prompt.text()?;
+        let booking_ref = if self.ask_redeem()? {
+            self.book_package_with_points(&package_id)
+        } else {
+            self.book_package(&package_id)
+        };
+        if let Some(booking_ref) = booking_ref {
+            self.view.booking_ref(&booking_ref);
+        }
+        Ok(())
+    }
+
+    /// ask the first and the last day until the last one is not before the first
+    fn read_period(&mut self) -> PromptResult<Period> {
+        loop {
+            self.view.start_date_hint();
+            let start = self.prompt.read::<Date>()?;
+            self.view.end_date_hint();
+            let end = self.prompt.read::<Date>()?;
+            match Period::new(start, end) {
+                Some(period) => return Ok(period),
+                None => self.view.invalid_period(),
+            }
+        }
+    }
+
+    fn cancel_book(&mut self) -> PromptResult<()> {
+        self.view.cancel_menu();
+        let choice = self.prompt.read::<usize>()?;
+        let id = if let Some(category) = menu_category(choice) {
+            let booked = self.query_booked(category);
+            self.view.querying(&category.header(), &booked);
+            self.view.cancel_hint();
+            let res_id = self.prompt.text()?;
+            self.reservation_id(category.res_type(), &res_id)
+        } else if choice == CATEGORIES.len() + 1 {
+            self.query_reservations();
+            self.view.querying(&reservation_hint(), &self.reservations);
+            self.view.booking_ref_hint();
+            let booking_ref = self.prompt.text()?;
+            self.connection
+                .query_reservation_by_ref(self.current_user, &normalize_booking_ref(&booking_ref))
+                .map(|reservation| reservation.id())
+        } else if choice == CATEGORIES.len() + 2 {
+            return self.cancel_package_menu();
+        } else {
+            return Ok(());
+        };
+
+        match id {
+            Some(id) => self.confirm_cancel(id),
+            None => {
+                self.view.reservation_not_found();
+                Ok(())
+            }
+        }
+    }
+
+    /// show the refund of the cancellation policy and cancel after the user agrees
+    fn confirm_cancel(&mut self, id: u32) -> PromptResult<()> {
+        let quote = match self.cancel_quote(id) {
+            Some(quote) => quote,
+            None => {
+                self.view.reservation_not_found();
+                return Ok(());
             }
-            _ => (),
         };
+        self.view.cancel_quote(&quote);
+        if self.prompt.confirm(None)? && self.cancel_reservation(id) {
+            self.view.success_hint();
+        }
+        Ok(())
+    }
 
-        return false;
+    /// list the booked tour packages and cancel the one the user picks as a whole
+    fn cancel_package_menu(&mut self) -> PromptResult<()> {
+        let packages = self.connection.query_packages(self.current_user);
+        self.view.querying(&package_booking_hint(), &packages);
+        if packages.is_empty() {
+            return Ok(());
+        }
+        self.view.booking_ref_hint();
+        let booking_ref = normalize_booking_ref(&self.prompt.text()?);
+        let quote = match self
+            .connection
+            .package_quote(self.current_user, &booking_ref)
+        {
+            Some((_, quote)) => quote,
+            None => {
+                self.view.booking_ref_not_found(&booking_ref);
+                return Ok(());
+            }
+        };
+        self.view.cancel_quote(&quote);
+        if self.prompt.confirm(None)? && self.cancel_package(&booking_ref) {
+            self.view.success_hint();
+        }
+        Ok(())
     }
 
-    fn querying(&mut self) -> bool {
-        View::query_menu();
-        read!(num as u32);
-        match num {
-            1 => {
-                self.query_all_flight();
-                View::querying(&FLIGHT_HINT, self.flights())
+    fn querying(&mut self) -> PromptResult<()> {
+        self.view.query_menu();
+        let choice = self.prompt.read::<usize>()?;
+        if let Some(category) = menu_category(choice) {
+            let filter = self.read_filter(category)?;
+            self.browse(category, &filter, false)?;
+            return Ok(());
+        }
+        // 目录之后依次是：我的预订、按预订号查询、按预订号导出、导出行程日历、导出行程单
+        match choice.checked_sub(CATEGORIES.len()) {
+            Some(1) => {
+                self.query_reservations();
+                self.view.querying(&reservation_hint(), &self.reservations)
             }
-            2 => {
-                self.query_all_bus();
-                View::querying(&BUS_HINT, self.bus())
+            Some(2) => {
+                self.view.booking_ref_hint();
+                let booking_ref = self.prompt.text()?;
+                self.lookup_by_ref(&normalize_booking_ref(&booking_ref));
             }
-            3 => {
-                self.query_all_hotel();
-                View::querying(&HOTEL_HINT, self.hotels())
+            Some(3) => {
+                self.view.booking_ref_hint();
+                let booking_ref = self.prompt.text()?;
+                self.export_by_ref(&normalize_booking_ref(&booking_ref));
             }
+            Some(4) => self.export_calendar(),
+            Some(5) => self.export_html(),
             _ => (),
         }
-        return false;
+        Ok(())
     }
 
-    fn travel_path(&mut self) -> bool {
-        self.query_flight();
-        self.query_bus();
-        self.query_hotel();
+    /// list the catalog page by page, only one page is loaded from the database at a time.
+    ///
+    /// When `booking` is set, anything that does not start with
+    /// [`PAGE_COMMAND_PREFIX`] is returned as the number of the item the user wants to book.
+    fn browse(
+        &mut self,
+        category: &Category,
+        filter: &CatalogFilter,
+        booking: bool,
+    ) -> PromptResult<Option<String>> {
+        let total = self.count_catalog(category, filter);
+        let mut page = Page::default();
+        loop {
+            let total_pages = page.total_pages(total);
+            let rows = self.search(category, filter, &page);
+            self.view
+                .page(&category.header(), &rows, &page, total_pages);
+            self.view.page_prompt(booking);
+            // 列表中的 :q 和直接回车一样只回到上一步
+            let command = match self.prompt.line() {
+                Err(Interrupt::Back) => return Ok(None),
+                command => command?,
+            };
+            match PageCommand::parse(&command) {
+                Some(PageCommand::Back) => return Ok(None),
+                Some(command) => page.apply(command, total_pages),
+                None if command.is_empty() => return Ok(None),
+                // 分页命令都有前缀，其余输入都是要预定的号码
+                None if booking && !command.starts_with(PAGE_COMMAND_PREFIX) => {
+                    return Ok(Some(command))
+                }
+                None => self.view.wrong_command(),
+            }
+        }
+    }
+
+    /// ask the search conditions of a catalog query, empty input skips a condition
+    fn read_filter(&mut self, category: &Category) -> PromptResult<CatalogFilter> {
+        let mut filter = CatalogFilter::new();
+        if let Place::Route(..) | Place::OnRoute(..) = category.place() {
+            self.view.filter_hint(t!("filter.from_city"));
+            filter.set_from_city(self.prompt.optional()?);
+            self.view.filter_hint(t!("filter.arrive_city"));
+            filter.set_arrive_city(self.prompt.optional()?);
+        } else {
+            self.view.filter_hint(t!("filter.location"));
+            filter.set_location(self.prompt.optional()?);
+        }
+        self.view.filter_hint(t!("filter.min_price"));
+        filter.set_min_price(self.prompt.optional()?);
+        self.view.filter_hint(t!("filter.max_price"));
+        filter.set_max_price(self.prompt.optional()?);
+        self.view.filter_hint(t!("filter.available"));
+        filter.set_available_only(self.prompt.confirm(Some(false))?);
+
+        self.view.sort_menu();
+        filter.set_sort(match self.prompt.optional::<u32>()? {
+            Some(1) => SortOrder::PriceAsc,
+            Some(2) => SortOrder::PriceDesc,
+            Some(3) => SortOrder::City,
+            _ => SortOrder::Default,
+        });
+        Ok(filter)
+    }
+
+    /// print the reservation and the flight/bus/hotel it refers to
+    fn lookup_by_ref(&mut self, booking_ref: &str) {
+        match self.reservation_detail(booking_ref) {
+            Some(detail) => self.view.text(&detail),
+            None => self.view.booking_ref_not_found(booking_ref),
+        }
+    }
+
+    /// write the reservation detail into `booking_<REF>.txt`
+    fn export_by_ref(&mut self, booking_ref: &str) {
+        let detail = match self.reservation_detail(booking_ref) {
+            Some(detail) => detail,
+            None => {
+                self.view.booking_ref_not_found(booking_ref);
+                return;
+            }
+        };
+        let path = format!("booking_{}.txt", booking_ref);
+        match fs::write(&path, detail) {
+            Ok(()) => self.view.export_success(&path),
+            Err(e) => self.view.error(t!("booking.export_failed", e)),
+        }
+    }
+
+    /// write every booked item that has a time into `itinerary_<user>.ics`
+    fn export_calendar(&mut self) {
+        let events: Vec<Event> = self
+            .itinerary()
+            .iter()
+            .filter_map(ItineraryItem::event)
+            .collect();
+        if events.is_empty() {
+            self.view.itinerary_empty();
+            return;
+        }
+        let path = format!("itinerary_{}.ics", self.current_user);
+        match fs::write(&path, ical::calendar(&events)) {
+            Ok(()) => self.view.export_success(&path),
+            Err(e) => self.view.error(t!("booking.export_failed", e)),
+        }
+    }
+
+    /// write the printable itinerary into `itinerary_<user>.html`
+    fn export_html(&mut self) {
+        let page = match self.itinerary_html() {
+            Some(page) => page,
+            None => return,
+        };
+        let path = format!("itinerary_{}.html", self.current_user);
+        match fs::write(&path, page) {
+            Ok(()) => self.view.export_success(&path),
+            Err(e) => self.view.error(t!("booking.export_failed", e)),
+        }
+    }
+
+    /// everything booked, in the order of the trip; same items as [`Controller::travel_path`] lists
+    pub fn itinerary(&mut self) -> Vec<ItineraryItem> {
+        let mut items = Vec::new();
+        for category in CATEGORIES.iter() {
+            items.extend(self.connection.itinerary(category, self.current_user));
+        }
+        itinerary::sort(&mut items);
+        items
+    }
 
-        View::travel_flight();
-        View::querying(&FLIGHT_HINT, self.flights());
+    /// the itinerary of the current user rendered from the itinerary template
+    pub fn itinerary_html(&mut self) -> Option<String> {
+        let customer = self.profile()?;
+        let items = self.itinerary();
+        let packages = self.connection.query_packages(self.current_user);
+        let total = self.trip_total();
+        Some(itinerary::html(
+            &self.itinerary_template,
+            &customer,
+            &items,
+            &packages,
+            total,
+        ))
+    }
 
-        View::travel_bus();
-        View::querying(&BUS_HINT, self.bus());
+    /// render [`Controller::itinerary_html`] from `template` instead of the built-in one
+    pub fn set_itinerary_template(&mut self, template: String) {
+        self.itinerary_template = template;
+    }
 
-        View::travel_hotel();
-        View::querying(&HOTEL_HINT, self.hotels());
-        return true;
+    fn reservation_detail(&mut self, booking_ref: &str) -> Option<String> {
+        let reservation = self
+            .connection
+            .query_reservation_by_ref(self.current_user, booking_ref)?;
+        let item = category(reservation.res_type()).and_then(|category| {
+            self.connection
+                .find_row(category, reservation.res_id())
+                .map(|row| View::render(&category.header(), &[row]))
+        });
+        let payments = self
+            .connection
+            .payments(self.current_user, reservation.booking_ref());
+        let mut detail = View::render(&reservation_hint(), &[reservation]);
+        if let Some(item) = item {
+            detail.push_str(&item);
+        }
+        if !payments.is_empty() {
+            detail.push_str(&View::render(&payment_hint(), &payments));
+        }
+        Some(detail)
+    }
+
+    fn travel_path(&mut self) -> bool {
+        for category in CATEGORIES.iter() {
+            let booked = self.query_booked(category);
+            self.view.travel_category(category.name());
+            self.view.querying(&category.header(), &booked);
+        }
+
+        self.query_reservations();
+        self.view.travel_reservation();
+        self.view.querying(&reservation_hint(), &self.reservations);
+        let packages = self.connection.query_packages(self.current_user);
+        self.view.travel_packages();
+        self.view.querying(&package_booking_hint(), &packages);
+        let total = self.trip_total();
+        self.view.trip_total(total);
+        true
     }
 
     /// # check the completeness:
-    /// 1. hotel can't be outside of the region of all flights that user is booked.
-    /// 2. all destination can be arrived, which means all flights can shape a graph
+    /// 1. hotels, buses and everything else that stays in one city can't be outside of
+    ///    the region of all flights and other routes that user is booked, rental cars
+    ///    must be picked up and dropped off in cities of that region as well.
+    /// 2. all destination can be arrived, which means all routes can shape a graph
     ///    that calls euler path.
     ///
     /// In the function, we erect a `Map` to indicate relation **place <-> in/out**.
     ///
-    /// If a flight go from a city, this city has one point deducted, and if a flight go to a city,
+    /// If a route go from a city, this city has one point deducted, and if a route go to a city,
     /// this city has one point added.
     ///
     /// In the end, if all city is zero, or just one city is 1 while one city is -1, we have a euler
     /// graph. Therefore, completeness is ensured, otherwise, it is broken.
-    fn check_completeness(&mut self) -> bool {
-        self.query_flight();
-        let mut all_city: Vec<String> = Vec::new();
-        for flight in &self.flights {
-            all_city.push(flight.from_city().to_string());
-            all_city.push(flight.arrive_city().to_string());
+    pub fn check_completeness(&mut self) -> bool {
+        let mut routes: Vec<Vec<String>> = Vec::new();
+        let mut stays: Vec<Vec<String>> = Vec::new();
+        for category in CATEGORIES.iter() {
+            let places = self.connection.booked_places(category, self.current_user);
+            match category.place() {
+                Place::Route(..) => routes.extend(places),
+                Place::Location(_) | Place::OnRoute(..) => stays.extend(places),
+            }
         }
 
-        let city: HashSet<String> = HashSet::from_iter(all_city.into_iter());
+        let city: HashSet<String> = HashSet::from_iter(routes.iter().flatten().cloned());
         let mut euler_graph: HashMap<String, i32> =
             HashMap::from_iter(city.iter().map(|x| (x.clone(), 0)));
-        self.flights.iter().for_each(|x| {
-            let from = euler_graph.get_mut(x.from_city()).unwrap();
+        routes.iter().for_each(|x| {
+            let from = euler_graph.get_mut(&x[0]).unwrap();
             *from -= 1;
-            let arrive = euler_graph.get_mut(x.arrive_city()).unwrap();
+            let arrive = euler_graph.get_mut(&x[1]).unwrap();
             *arrive += 1;
         });
 
@@ -320,33 +1175,34 @@ impl Controller {
             }
         });
 
-        if positive > 1 || negative > 1 {
-            return false;
-        } else if positive == 1 && negative != 1 {
-            return false;
-        } else if positive != 1 && negative == 1 {
+        if positive > 1 || negative > 1 || (positive == 1) != (negative == 1) {
             return false;
         }
 
-        self.query_hotel();
-        for hotel in &self.hotels {
-            if !city.contains(hotel.location()) {
-                return false;
-            }
-        }
+        stays.iter().flatten().all(|x| city.contains(x))
+    }
 
-        self.query_bus();
-        for bus in &self.bus {
-            if !city.contains(bus.location()) {
-                return false;
-            }
-        }
+    /// send everything the session shows to `writer` and return the writer used so far
+    pub(crate) fn swap_output(&mut self, writer: Box<dyn Write>) -> Box<dyn Write> {
+        std::mem::replace(&mut *self.out.borrow_mut(), writer)
+    }
 
-        return true;
+    pub(crate) fn show_error(&mut self, message: &str) {
+        self.view.error(message);
+    }
+
+    /// same as [`Controller::run`], but with the full-screen interface after login
+    pub fn run_tui(&mut self) -> Status {
+        match self.login_view() {
+            Ok(true) => tui::run(self),
+            _ => Status::Quit,
+        }
     }
 
     pub fn run(&mut self) -> Status {
-        self.login_view();
+        if self.login_view() != Ok(true) {
+            return Status::Quit;
+        }
         loop {
             let ret = self.user_menu();
             if !(ret == Status::Continue) {
@@ -355,25 +1211,29 @@ impl Controller {
         }
     }
 
-    pub fn flights(&self) -> &Vec<Flight> {
-        &self.flights
-    }
-    pub fn hotels(&self) -> &Vec<Hotels> {
-        &self.hotels
-    }
-    pub fn bus(&self) -> &Vec<Bus> {
-        &self.bus
+    pub fn reservations(&self) -> &Vec<Reservation> {
+        &self.reservations
     }
 
-    fn cancel_flight(&mut self, res_id: String) -> bool {
-        self.connection.cancel_flight(self.current_user, res_id)
+    fn reservation_id(&mut self, res_type: u32, res_id: &str) -> Option<u32> {
+        self.connection
+            .reservation_id(self.current_user, res_type, res_id)
     }
+}
 
-    fn cancel_bus(&mut self, res_id: String) -> bool {
-        self.connection.cancel_bus(self.current_user, res_id)
+/// what a reservation is for, e.g. `航班 MU5101` or `租车 C1 2024-05-01 ~ 2024-05-03`
+fn reservation_item(reservation: &Reservation) -> String {
+    let item = format!("{} {}", reservation.type_name(), reservation.res_id());
+    match reservation.period() {
+        Some(period) => format!("{} {}", item, period),
+        None => item,
     }
+}
 
-    fn cancel_hotel(&mut self, res_id: String) -> bool {
-        self.connection.cancel_hotel(self.current_user, res_id)
-    }
+/// the catalog chosen in a menu that lists [`CATEGORIES`] first, numbered from 1
+fn menu_category(choice: usize) -> Option<&'static Category> {
+    choice
+        .checked_sub(1)
+        .and_then(|x| CATEGORIES.get(x))
+        .copied()
 }
@@ -1,10 +1,31 @@
+use crate::period::Date;
+use crate::{t, ToRow};
+use prettytable::{Cell, Row};
+
 pub struct Customer {
     name: String,
     id: u32,
+    email: Option<String>,
+    phone: Option<String>,
+    birth_date: Option<Date>,
+    /// ID card or passport number
+    id_number: Option<String>,
+    nationality: Option<String>,
+    emergency_contact: Option<String>,
 }
+
 impl Customer {
     pub fn new(name: String, id: u32) -> Self {
-        Customer { name, id }
+        Customer {
+            name,
+            id,
+            email: None,
+            phone: None,
+            birth_date: None,
+            id_number: None,
+            nationality: None,
+            emergency_contact: None,
+        }
     }
 
     pub fn name(&self) -> &str {
@@ -13,6 +34,24 @@ impl Customer {
     pub fn id(&self) -> u32 {
         self.id
     }
+    pub fn email(&self) -> Option<&str> {
+        self.email.as_deref()
+    }
+    pub fn phone(&self) -> Option<&str> {
+        self.phone.as_deref()
+    }
+    pub fn birth_date(&self) -> Option<Date> {
+        self.birth_date
+    }
+    pub fn id_number(&self) -> Option<&str> {
+        self.id_number.as_deref()
+    }
+    pub fn nationality(&self) -> Option<&str> {
+        self.nationality.as_deref()
+    }
+    pub fn emergency_contact(&self) -> Option<&str> {
+        self.emergency_contact.as_deref()
+    }
 
     pub fn set_name(&mut self, name: String) {
         self.name = name;
@@ -20,4 +59,171 @@ impl Customer {
     pub fn set_id(&mut self, id: u32) {
         self.id = id;
     }
+    pub fn set_email(&mut self, email: Option<String>) {
+        self.email = email;
+    }
+    pub fn set_phone(&mut self, phone: Option<String>) {
+        self.phone = phone;
+    }
+    pub fn set_birth_date(&mut self, birth_date: Option<Date>) {
+        self.birth_date = birth_date;
+    }
+    pub fn set_id_number(&mut self, id_number: Option<String>) {
+        self.id_number = id_number;
+    }
+    pub fn set_nationality(&mut self, nationality: Option<String>) {
+        self.nationality = nationality;
+    }
+    pub fn set_emergency_contact(&mut self, emergency_contact: Option<String>) {
+        self.emergency_contact = emergency_contact;
+    }
+
+    /// current value of `field` as it is shown and stored
+    pub fn get(&self, field: ProfileField) -> Option<String> {
+        match field {
+            ProfileField::Name => Some(self.name.clone()),
+            ProfileField::Email => self.email.clone(),
+            ProfileField::Phone => self.phone.clone(),
+            ProfileField::BirthDate => self.birth_date.map(|x| x.to_string()),
+            ProfileField::IdNumber => self.id_number.clone(),
+            ProfileField::Nationality => self.nationality.clone(),
+            ProfileField::EmergencyContact => self.emergency_contact.clone(),
+        }
+    }
+}
+
+// 管理员查询客户时的一行
+impl ToRow for Customer {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(&self.id().to_string()),
+            Cell::new(self.name()),
+            Cell::new(self.email().unwrap_or_default()),
+            Cell::new(self.phone().unwrap_or_default()),
+        ])
+    }
+}
+
+/// a field of the customer profile that can be edited
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileField {
+    Name,
+    Email,
+    Phone,
+    BirthDate,
+    IdNumber,
+    Nationality,
+    EmergencyContact,
+}
+
+impl ProfileField {
+    /// every field, in the order of the profile screen
+    pub const ALL: [ProfileField; 7] = [
+        ProfileField::Name,
+        ProfileField::Email,
+        ProfileField::Phone,
+        ProfileField::BirthDate,
+        ProfileField::IdNumber,
+        ProfileField::Nationality,
+        ProfileField::EmergencyContact,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProfileField::Name => t!("profile.name"),
+            ProfileField::Email => t!("profile.email"),
+            ProfileField::Phone => t!("profile.phone"),
+            ProfileField::BirthDate => t!("profile.birth_date"),
+            ProfileField::IdNumber => t!("profile.id_number"),
+            ProfileField::Nationality => t!("profile.nationality"),
+            ProfileField::EmergencyContact => t!("profile.emergency_contact"),
+        }
+    }
+
+    /// column of the field in `Customer`
+    pub(crate) fn column(&self) -> &'static str {
+        match self {
+            ProfileField::Name => "name",
+            ProfileField::Email => "email",
+            ProfileField::Phone => "phone",
+            ProfileField::BirthDate => "birth_date",
+            ProfileField::IdNumber => "id_number",
+            ProfileField::Nationality => "nationality",
+            ProfileField::EmergencyContact => "emergency_contact",
+        }
+    }
+
+    /// check what the user typed and normalize it for storage.
+    ///
+    /// An empty value clears the field, except the name which is required. The
+    /// error is the message explaining what is wrong.
+    pub fn validate(&self, value: &str) -> Result<Option<String>, &'static str> {
+        let value = value.trim();
+        if value.is_empty() && *self != ProfileField::Name {
+            return Ok(None);
+        }
+        let text = Some(value.to_string());
+        let normalized = match self {
+            ProfileField::Name => {
+                text.filter(|x| !x.is_empty() && x.chars().count() <= MAX_NAME_LEN)
+            }
+            ProfileField::Email => text.filter(|x| valid_email(x)),
+            ProfileField::Phone => text.filter(|x| valid_phone(x)),
+            ProfileField::BirthDate => value
+                .parse::<Date>()
+                .ok()
+                .filter(|x| x.year() >= 1900 && *x <= Date::today())
+                .map(|x| x.to_string()),
+            ProfileField::IdNumber => Some(value.to_uppercase()).filter(|x| {
+                (5..=20).contains(&x.len()) && x.chars().all(|c| c.is_ascii_alphanumeric())
+            }),
+            ProfileField::Nationality => text.filter(|x| {
+                x.chars().count() <= MAX_NAME_LEN
+                    && x.chars().all(|c| c.is_alphabetic() || c == ' ' || c == '-')
+            }),
+            ProfileField::EmergencyContact => text.filter(|x| x.chars().count() <= MAX_TEXT_LEN),
+        };
+        normalized.map(Some).ok_or_else(|| self.invalid_message())
+    }
+
+    fn invalid_message(&self) -> &'static str {
+        match self {
+            ProfileField::Name => t!("profile.invalid_name"),
+            ProfileField::Email => t!("profile.invalid_email"),
+            ProfileField::Phone => t!("profile.invalid_phone"),
+            ProfileField::BirthDate => t!("profile.invalid_birth_date"),
+            ProfileField::IdNumber => t!("profile.invalid_id_number"),
+            ProfileField::Nationality => t!("profile.invalid_nationality"),
+            ProfileField::EmergencyContact => t!("profile.invalid_emergency_contact"),
+        }
+    }
+}
+
+/// longest name or nationality, in characters
+const MAX_NAME_LEN: usize = 50;
+/// longest email or emergency contact, in characters
+const MAX_TEXT_LEN: usize = 100;
+
+// 只做基本的格式检查：local@domain.tld，不含空白
+fn valid_email(value: &str) -> bool {
+    let (local, domain) = match value.split_once('@') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    value.chars().count() <= MAX_TEXT_LEN
+        && !local.is_empty()
+        && !value.chars().any(char::is_whitespace)
+        && !domain.contains('@')
+        && domain.contains('.')
+        && domain.split('.').all(|x| !x.is_empty())
+}
+
+// 可以有开头的 +，数字之间允许空格和 -，共 6 到 15 位数字
+fn valid_phone(value: &str) -> bool {
+    let rest = value.strip_prefix('+').unwrap_or(value);
+    let digits = rest.chars().filter(char::is_ascii_digit).count();
+    rest.chars()
+        .all(|x| x.is_ascii_digit() || x == ' ' || x == '-')
+        && rest.starts_with(|x: char| x.is_ascii_digit())
+        && (6..=15).contains(&digits)
 }
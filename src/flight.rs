@@ -1,5 +1,5 @@
+use crate::bookable::{Bookable, Category, Place};
 use crate::ToRow;
-use mysql::{params, PooledConn};
 use prettytable::{Cell, Row};
 
 #[derive(Clone)]
@@ -58,6 +58,33 @@ impl ToRow for Flight {
     }
 }
 
+impl Bookable for Flight {
+    const CATEGORY: Category = Category {
+        res_type: 1,
+        table: "Flights",
+        key_column: "flight_num",
+        capacity_column: "num_seat",
+        time_column: "depart_time",
+        end_column: "arrive_time",
+        dated: false,
+        columns: "flight_num, price, from_city, arrive_city",
+        place: Place::Route("from_city", "arrive_city"),
+        name: "type.flight",
+        header: &[
+            "header.flight_num",
+            "header.price",
+            "header.from_city",
+            "header.arrive_city",
+        ],
+        to_row: |row| Flight::from_row(row).to_row(),
+    };
+
+    fn from_row(row: mysql::Row) -> Self {
+        let (flight_num, price, from_city, arrive_city) = mysql::from_row(row);
+        Flight::new(flight_num, price, from_city, arrive_city)
+    }
+}
+
 impl PartialEq<Self> for Flight {
     fn eq(&self, other: &Self) -> bool {
         self.flight_num == other.flight_num
@@ -1,3 +1,4 @@
+use crate::bookable::{Bookable, Category, Place};
 use crate::ToRow;
 use prettytable::{Cell, Row};
 
@@ -46,3 +47,25 @@ impl ToRow for Hotels {
         ])
     }
 }
+
+impl Bookable for Hotels {
+    const CATEGORY: Category = Category {
+        res_type: 2,
+        table: "Hotels",
+        key_column: "hotel_num",
+        capacity_column: "num_rooms",
+        time_column: "check_in_time",
+        end_column: "check_out_time",
+        dated: false,
+        columns: "hotel_num, location, price",
+        place: Place::Location("location"),
+        name: "type.hotel",
+        header: &["header.hotel_num", "header.location", "header.price"],
+        to_row: |row| Hotels::from_row(row).to_row(),
+    };
+
+    fn from_row(row: mysql::Row) -> Self {
+        let (hotel_num, location, price) = mysql::from_row(row);
+        Hotels::new(hotel_num, location, price)
+    }
+}
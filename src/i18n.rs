@@ -0,0 +1,105 @@
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// languages that have a message catalog in `locales/`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[default]
+    ZhCn = 0,
+    En = 1,
+}
+
+impl Locale {
+    /// accept the usual spellings, e.g. `zh`, `zh-CN`, `zh_CN.UTF-8`, `en`, `en_US`
+    pub fn parse(name: &str) -> Option<Locale> {
+        let name = name.trim().to_lowercase();
+        if name.starts_with("zh") {
+            Some(Locale::ZhCn)
+        } else if name.starts_with("en") {
+            Some(Locale::En)
+        } else {
+            None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Locale::ZhCn => "zh-CN",
+            Locale::En => "en",
+        }
+    }
+}
+
+lazy_static! {
+    static ref CATALOGS: HashMap<Locale, HashMap<&'static str, &'static str>> = {
+        let mut catalogs = HashMap::new();
+        catalogs.insert(
+            Locale::ZhCn,
+            parse_catalog(include_str!("../locales/zh-CN.txt")),
+        );
+        catalogs.insert(Locale::En, parse_catalog(include_str!("../locales/en.txt")));
+        catalogs
+    };
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(Locale::ZhCn as u8);
+
+pub fn set_locale(locale: Locale) {
+    CURRENT.store(locale as u8, Ordering::Relaxed);
+}
+
+pub fn locale() -> Locale {
+    match CURRENT.load(Ordering::Relaxed) {
+        1 => Locale::En,
+        _ => Locale::ZhCn,
+    }
+}
+
+/// look up `key` in the catalog of the current locale.
+///
+/// Missing keys fall back to `zh-CN` and then to the key itself, so a typo shows
+/// up on screen instead of crashing the program.
+pub fn tr(key: &'static str) -> &'static str {
+    CATALOGS[&locale()]
+        .get(key)
+        .or_else(|| CATALOGS[&Locale::ZhCn].get(key))
+        .copied()
+        .unwrap_or(key)
+}
+
+/// like [`tr`], with every `{}` replaced by the next argument
+pub fn trf(key: &'static str, args: &[&dyn Display]) -> String {
+    let mut args = args.iter();
+    let mut parts = tr(key).split("{}");
+    let mut message = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            message.push_str(&arg.to_string());
+        }
+        message.push_str(part);
+    }
+    message
+}
+
+fn parse_catalog(source: &'static str) -> HashMap<&'static str, &'static str> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+
+/// translate a message key of the current locale, see `locales/`
+#[macro_export]
+macro_rules! t {
+    ($key:expr) => {
+        $crate::i18n::tr($key)
+    };
+    ($key:expr, $($arg:expr),+ $(,)?) => {
+        $crate::i18n::trf($key, &[$(&$arg as &dyn std::fmt::Display),+])
+    };
+}
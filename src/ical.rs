@@ -0,0 +1,238 @@
+//! Itineraries as iCalendar files (RFC 5545).
+//!
+//! Every booked item that has a time becomes one `VEVENT`. Times are written
+//! as local ("floating") times, the way they are stored in the tables; dated
+//! items such as rental cars become all-day events.
+
+use crate::period::Date;
+use crate::t;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// the longest content line allowed, in octets, longer lines are folded
+const MAX_LINE_LEN: usize = 75;
+
+/// when an event starts or ends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventTime {
+    /// a whole day
+    Date(Date),
+    /// a local time of day, typed as `YYYY-MM-DD HH:MM`
+    DateTime(Date, u32, u32),
+}
+
+impl EventTime {
+    // 全天事件按当天 0 点排序
+    fn key(&self) -> (Date, u32, u32) {
+        match *self {
+            EventTime::Date(date) => (date, 0, 0),
+            EventTime::DateTime(date, hour, minute) => (date, hour, minute),
+        }
+    }
+}
+
+impl Ord for EventTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl PartialOrd for EventTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for EventTime {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (date, time) = match s.split_once(' ') {
+            Some(parts) => parts,
+            None => return Ok(EventTime::Date(s.parse()?)),
+        };
+        let mut parts = time.splitn(3, ':').map(|x| x.parse::<u32>());
+        match (parts.next(), parts.next()) {
+            (Some(Ok(hour)), Some(Ok(minute))) if hour < 24 && minute < 60 => {
+                Ok(EventTime::DateTime(date.parse()?, hour, minute))
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for EventTime {
+    /// the value of `DTSTART`/`DTEND`, e.g. `20240501T073000`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventTime::Date(date) => write_date(f, date),
+            EventTime::DateTime(date, hour, minute) => {
+                write_date(f, date)?;
+                write!(f, "T{:02}{:02}00", hour, minute)
+            }
+        }
+    }
+}
+
+fn write_date(f: &mut fmt::Formatter<'_>, date: &Date) -> fmt::Result {
+    write!(f, "{:04}{:02}{:02}", date.year(), date.month(), date.day())
+}
+
+/// one booked item on the calendar
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    booking_ref: String,
+    summary: String,
+    location: String,
+    start: EventTime,
+    end: Option<EventTime>,
+}
+
+impl Event {
+    pub fn new(
+        booking_ref: String,
+        summary: String,
+        location: String,
+        start: EventTime,
+        end: Option<EventTime>,
+    ) -> Self {
+        Event {
+            booking_ref,
+            summary,
+            location,
+            start,
+            end,
+        }
+    }
+
+    pub fn booking_ref(&self) -> &str {
+        &self.booking_ref
+    }
+    pub fn summary(&self) -> &str {
+        &self.summary
+    }
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+    pub fn start(&self) -> EventTime {
+        self.start
+    }
+    pub fn end(&self) -> Option<EventTime> {
+        self.end
+    }
+
+    fn write(&self, calendar: &mut String, stamp: &str) {
+        // 全天事件的 DTEND 是结束那天的下一天
+        let (start, end) = match (self.start, self.end) {
+            (EventTime::Date(start), end) => {
+                let end = match end {
+                    Some(EventTime::Date(end)) => end.max(start),
+                    _ => start,
+                };
+                (
+                    format!("DTSTART;VALUE=DATE:{}", self.start),
+                    Some(format!(
+                        "DTEND;VALUE=DATE:{}",
+                        EventTime::Date(end.add_days(1))
+                    )),
+                )
+            }
+            (_, end) => (
+                format!("DTSTART:{}", self.start),
+                end.filter(|x| *x > self.start)
+                    .map(|x| format!("DTEND:{}", x)),
+            ),
+        };
+        push_line(calendar, "BEGIN:VEVENT");
+        push_line(
+            calendar,
+            &format!("UID:{}@tour-booking-system", self.booking_ref),
+        );
+        push_line(calendar, &format!("DTSTAMP:{}", stamp));
+        push_line(calendar, &start);
+        if let Some(end) = end {
+            push_line(calendar, &end);
+        }
+        push_line(calendar, &format!("SUMMARY:{}", escape(&self.summary)));
+        if !self.location.is_empty() {
+            push_line(calendar, &format!("LOCATION:{}", escape(&self.location)));
+        }
+        push_line(
+            calendar,
+            &format!(
+                "DESCRIPTION:{}",
+                escape(&t!("ical.booking_ref", self.booking_ref))
+            ),
+        );
+        push_line(calendar, "END:VEVENT");
+    }
+}
+
+/// a whole `.ics` file with one event per item, in the order of their start
+pub fn calendar(events: &[Event]) -> String {
+    let mut events: Vec<&Event> = events.iter().collect();
+    events.sort_by_key(|x| x.start);
+    let stamp = utc_now();
+
+    let mut calendar = String::new();
+    push_line(&mut calendar, "BEGIN:VCALENDAR");
+    push_line(&mut calendar, "VERSION:2.0");
+    push_line(&mut calendar, "PRODID:-//TourBookingSystem//Itinerary//EN");
+    push_line(&mut calendar, "CALSCALE:GREGORIAN");
+    for event in events {
+        event.write(&mut calendar, &stamp);
+    }
+    push_line(&mut calendar, "END:VCALENDAR");
+    calendar
+}
+
+// 超过 75 字节的行要折行，续行以空格开头，不能把一个 UTF-8 字符拆开
+fn push_line(calendar: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_LEN {
+            calendar.push_str("\r\n ");
+            len = 1;
+        }
+        calendar.push(c);
+        len += c.len_utf8();
+    }
+    calendar.push_str("\r\n");
+}
+
+/// escape a TEXT value
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | ';' | ',' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            '\r' => (),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// the current time in UTC for `DTSTAMP`, e.g. `20240501T073000Z`
+fn utc_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let epoch = Date::new(1970, 1, 1).expect("1970-01-01 is a valid date");
+    let time = secs % 86_400;
+    format!(
+        "{}T{:02}{:02}{:02}Z",
+        EventTime::Date(epoch.add_days((secs / 86_400) as i64)),
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
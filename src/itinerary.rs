@@ -0,0 +1,302 @@
+//! Everything a customer booked, in the order of the trip.
+//!
+//! The items are exported as an iCalendar file (see [`crate::ical`]) or as a
+//! printable HTML page rendered from a template, `templates/itinerary.html` by
+//! default or the file named by `itinerary_template` in the config file.
+
+use crate::bookable::Category;
+use crate::config::Config;
+use crate::customer::Customer;
+use crate::ical::{Event, EventTime};
+use crate::package::PackageBooking;
+use crate::period::Date;
+use crate::t;
+use std::fs;
+
+/// the template used when `itinerary_template` is not set
+pub const DEFAULT_TEMPLATE: &str = include_str!("../templates/itinerary.html");
+
+/// one booked flight, hotel, bus, ...
+pub struct ItineraryItem {
+    booking_ref: String,
+    category: &'static Category,
+    res_id: String,
+    /// departure, pick-up or the only city of the item
+    from: String,
+    /// arrival or drop-off city
+    to: Option<String>,
+    start: Option<EventTime>,
+    end: Option<EventTime>,
+    /// what the reservation costs, dated items are charged per day
+    price: u64,
+    /// the tour package the item was booked in, paid by the package price
+    package_ref: Option<String>,
+}
+
+impl ItineraryItem {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        booking_ref: String,
+        category: &'static Category,
+        res_id: String,
+        from: String,
+        to: Option<String>,
+        start: Option<EventTime>,
+        end: Option<EventTime>,
+        price: u64,
+        package_ref: Option<String>,
+    ) -> Self {
+        ItineraryItem {
+            booking_ref,
+            category,
+            res_id,
+            from,
+            to,
+            start,
+            end,
+            price,
+            package_ref,
+        }
+    }
+
+    pub fn booking_ref(&self) -> &str {
+        &self.booking_ref
+    }
+    pub fn category(&self) -> &'static Category {
+        self.category
+    }
+    pub fn res_id(&self) -> &str {
+        &self.res_id
+    }
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+    pub fn to(&self) -> Option<&str> {
+        self.to.as_deref()
+    }
+    pub fn start(&self) -> Option<EventTime> {
+        self.start
+    }
+    pub fn end(&self) -> Option<EventTime> {
+        self.end
+    }
+    pub fn price(&self) -> u64 {
+        self.price
+    }
+    pub fn package_ref(&self) -> Option<&str> {
+        self.package_ref.as_deref()
+    }
+
+    /// e.g. `航班 MU5101 北京 → 上海`
+    pub fn summary(&self) -> String {
+        match &self.to {
+            Some(to) => t!(
+                "itinerary.summary_route",
+                self.category.name(),
+                self.res_id,
+                self.from,
+                to
+            ),
+            None => t!("itinerary.summary", self.category.name(), self.res_id),
+        }
+    }
+
+    /// the calendar event of the item, `None` without a start time
+    pub fn event(&self) -> Option<Event> {
+        Some(Event::new(
+            self.booking_ref.clone(),
+            self.summary(),
+            self.from.clone(),
+            self.start?,
+            self.end,
+        ))
+    }
+}
+
+/// order the items as they happen, the ones without a time come last
+pub fn sort(items: &mut [ItineraryItem]) {
+    items.sort_by(|a, b| {
+        (a.start.is_none(), a.start, &a.booking_ref).cmp(&(
+            b.start.is_none(),
+            b.start,
+            &b.booking_ref,
+        ))
+    });
+}
+
+/// the template chosen by `itinerary_template` in the config file
+pub fn template(config: &Config) -> String {
+    let path = match config.get("itinerary_template") {
+        Some(path) => path,
+        None => return DEFAULT_TEMPLATE.to_string(),
+    };
+    fs::read_to_string(path).unwrap_or_else(|e| {
+        eprintln!("{}", t!("error.itinerary_template", path, e));
+        DEFAULT_TEMPLATE.to_string()
+    })
+}
+
+/// the itinerary as a self-contained HTML page.
+///
+/// `{lang}`, `{title}`, `{customer}`, `{customer_id}`, `{generated}`,
+/// `{items}`, `{packages}`, `{total}` and the labels `{customer_label}`,
+/// `{generated_label}`, `{total_label}` in `template` are replaced. `{items}`
+/// and `{packages}` become whole sections with their tables.
+pub fn html(
+    template: &str,
+    customer: &Customer,
+    items: &[ItineraryItem],
+    packages: &[PackageBooking],
+    total: u64,
+) -> String {
+    let vars = [
+        ("lang", t!("itinerary.lang").to_string()),
+        ("title", escape(t!("itinerary.title"))),
+        ("customer_label", escape(t!("itinerary.customer"))),
+        ("generated_label", escape(t!("itinerary.generated"))),
+        ("total_label", escape(t!("itinerary.total"))),
+        ("customer", escape(customer.name())),
+        ("customer_id", customer.id().to_string()),
+        ("generated", Date::today().to_string()),
+        ("items", items_section(items)),
+        ("packages", packages_section(packages)),
+        ("total", total.to_string()),
+    ];
+    // 一次扫描替换，填进去的内容里再出现 {key} 也不会被替换
+    let mut page = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        page.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = rest.find('}').and_then(|close| {
+            let key = &rest[1..close];
+            vars.iter()
+                .find(|(name, _)| *name == key)
+                .map(|(_, value)| (value, close))
+        });
+        match value {
+            Some((value, close)) => {
+                page.push_str(value);
+                rest = &rest[close + 1..];
+            }
+            None => {
+                page.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    page.push_str(rest);
+    page
+}
+
+fn items_section(items: &[ItineraryItem]) -> String {
+    let mut section = format!(
+        "<section class=\"items\">\n<h2>{}</h2>\n",
+        escape(t!("itinerary.items"))
+    );
+    if items.is_empty() {
+        section.push_str(&format!(
+            "<p class=\"empty\">{}</p>\n</section>",
+            escape(t!("itinerary.no_items"))
+        ));
+        return section;
+    }
+    section.push_str("<table>\n");
+    section.push_str(&header(&[
+        t!("itinerary.when"),
+        t!("itinerary.item"),
+        t!("itinerary.place"),
+        t!("header.booking_ref"),
+        t!("itinerary.price"),
+    ]));
+    for item in items {
+        let when = match (item.start, item.end) {
+            (Some(start), Some(end)) if end != start => {
+                format!("{} ~ {}", time_text(start), time_text(end))
+            }
+            (Some(start), _) => time_text(start),
+            (None, _) => String::new(),
+        };
+        let place = match item.to() {
+            Some(to) => format!("{} → {}", item.from, to),
+            None => item.from.clone(),
+        };
+        let price = match item.package_ref() {
+            Some(package_ref) => t!("itinerary.in_package", package_ref),
+            None => item.price.to_string(),
+        };
+        section.push_str(&row(&[
+            &when,
+            &format!("{} {}", item.category.name(), item.res_id),
+            &place,
+            &item.booking_ref,
+            &price,
+        ]));
+    }
+    section.push_str("</table>\n</section>");
+    section
+}
+
+fn packages_section(packages: &[PackageBooking]) -> String {
+    if packages.is_empty() {
+        return String::new();
+    }
+    let mut section = format!(
+        "<section class=\"packages\">\n<h2>{}</h2>\n<table>\n",
+        escape(t!("itinerary.packages"))
+    );
+    section.push_str(&header(&[
+        t!("header.booking_ref"),
+        t!("itinerary.package"),
+        t!("itinerary.price"),
+    ]));
+    for package in packages {
+        section.push_str(&row(&[
+            package.booking_ref(),
+            package.name(),
+            &package.price().to_string(),
+        ]));
+    }
+    section.push_str("</table>\n</section>");
+    section
+}
+
+// 日历里用 20240501T073000，页面上用 2024-05-01 07:30
+fn time_text(time: EventTime) -> String {
+    match time {
+        EventTime::Date(date) => date.to_string(),
+        EventTime::DateTime(date, hour, minute) => format!("{} {:02}:{:02}", date, hour, minute),
+    }
+}
+
+fn header(cells: &[&str]) -> String {
+    let cells: String = cells
+        .iter()
+        .map(|x| format!("<th>{}</th>", escape(x)))
+        .collect();
+    format!("<tr>{}</tr>\n", cells)
+}
+
+fn row(cells: &[&str]) -> String {
+    let cells: String = cells
+        .iter()
+        .map(|x| format!("<td>{}</td>", escape(x)))
+        .collect();
+    format!("<tr>{}</tr>\n", cells)
+}
+
+/// escape text for HTML content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
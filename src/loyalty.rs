@@ -0,0 +1,206 @@
+//! Loyalty points.
+//!
+//! Every change of a customer's points is a row of the `LoyaltyLedger`, the
+//! balance is their sum. Points are earned on what a booking costs once it
+//! is paid, used points lower the price of a booking, and cancelling a booking
+//! takes back the points it earned and returns the points it used.
+
+use crate::t;
+use crate::ToRow;
+use prettytable::{Cell, Row};
+
+/// points earned per unit of money spent, before the tier bonus
+pub const POINTS_PER_UNIT: u32 = 1;
+/// points needed for one unit of discount
+pub const POINTS_PER_DISCOUNT: u32 = 100;
+
+/// level of a customer, from the points earned so far
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Tier {
+    Basic,
+    Silver,
+    Gold,
+    Platinum,
+}
+
+impl Tier {
+    /// every tier, from the lowest
+    pub const ALL: [Tier; 4] = [Tier::Basic, Tier::Silver, Tier::Gold, Tier::Platinum];
+
+    /// the tier reached with `earned` points, points used on discounts still count
+    pub fn from_points(earned: i64) -> Self {
+        Tier::ALL
+            .iter()
+            .rev()
+            .copied()
+            .find(|x| earned >= x.threshold())
+            .unwrap_or(Tier::Basic)
+    }
+
+    /// points earned needed to reach the tier
+    pub fn threshold(&self) -> i64 {
+        match self {
+            Tier::Basic => 0,
+            Tier::Silver => 5_000,
+            Tier::Gold => 20_000,
+            Tier::Platinum => 50_000,
+        }
+    }
+
+    /// extra points earned on top of [`POINTS_PER_UNIT`], in percent
+    pub fn bonus_percent(&self) -> u32 {
+        match self {
+            Tier::Basic => 0,
+            Tier::Silver => 25,
+            Tier::Gold => 50,
+            Tier::Platinum => 100,
+        }
+    }
+
+    pub fn next(&self) -> Option<Tier> {
+        Tier::ALL.iter().copied().find(|x| x > self)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Tier::Basic => t!("loyalty.basic"),
+            Tier::Silver => t!("loyalty.silver"),
+            Tier::Gold => t!("loyalty.gold"),
+            Tier::Platinum => t!("loyalty.platinum"),
+        }
+    }
+
+    /// points earned by paying `paid`
+    pub fn earn(&self, paid: u32) -> u64 {
+        u64::from(paid) * u64::from(POINTS_PER_UNIT) * u64::from(100 + self.bonus_percent()) / 100
+    }
+}
+
+/// the points of a customer
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoyaltyAccount {
+    balance: i64,
+    /// points earned and not taken back by cancellations
+    earned: i64,
+}
+
+impl LoyaltyAccount {
+    pub fn new(balance: i64, earned: i64) -> Self {
+        LoyaltyAccount { balance, earned }
+    }
+
+    pub fn balance(&self) -> i64 {
+        self.balance
+    }
+    pub fn earned(&self) -> i64 {
+        self.earned
+    }
+    pub fn tier(&self) -> Tier {
+        Tier::from_points(self.earned)
+    }
+
+    /// points still to earn for the next tier, `None` at the top
+    pub fn to_next_tier(&self) -> Option<i64> {
+        self.tier().next().map(|x| x.threshold() - self.earned)
+    }
+
+    /// the discount on `price` when paying with as many points as possible,
+    /// and the points it takes
+    pub fn redemption(&self, price: u32) -> (u32, u32) {
+        let available = (self.balance.max(0) / i64::from(POINTS_PER_DISCOUNT)) as u64;
+        let discount = available.min(u64::from(price)) as u32;
+        (discount, discount * POINTS_PER_DISCOUNT)
+    }
+}
+
+/// why the points changed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointsKind {
+    /// earned on a booking
+    Earn,
+    /// used as a discount on a booking
+    Redeem,
+    /// earned points taken back when the booking is cancelled
+    Reverse,
+    /// used points given back when the booking is cancelled
+    Restore,
+}
+
+impl PointsKind {
+    /// value of `LoyaltyLedger.kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PointsKind::Earn => "earn",
+            PointsKind::Redeem => "redeem",
+            PointsKind::Reverse => "reverse",
+            PointsKind::Restore => "restore",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "earn" => Some(PointsKind::Earn),
+            "redeem" => Some(PointsKind::Redeem),
+            "reverse" => Some(PointsKind::Reverse),
+            "restore" => Some(PointsKind::Restore),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PointsKind::Earn => t!("loyalty.earn"),
+            PointsKind::Redeem => t!("loyalty.redeem"),
+            PointsKind::Reverse => t!("loyalty.reverse"),
+            PointsKind::Restore => t!("loyalty.restore"),
+        }
+    }
+}
+
+/// a row of the points statement
+pub struct PointsEntry {
+    kind: PointsKind,
+    points: i64,
+    booking_ref: Option<String>,
+    created_at: String,
+}
+
+impl PointsEntry {
+    pub fn new(
+        kind: PointsKind,
+        points: i64,
+        booking_ref: Option<String>,
+        created_at: String,
+    ) -> Self {
+        PointsEntry {
+            kind,
+            points,
+            booking_ref,
+            created_at,
+        }
+    }
+
+    pub fn kind(&self) -> PointsKind {
+        self.kind
+    }
+    pub fn points(&self) -> i64 {
+        self.points
+    }
+    pub fn booking_ref(&self) -> Option<&str> {
+        self.booking_ref.as_deref()
+    }
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+}
+
+impl ToRow for PointsEntry {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(&self.created_at),
+            Cell::new(self.kind.label()),
+            Cell::new(self.booking_ref().unwrap_or_default()),
+            Cell::new(&format!("{:+}", self.points)),
+        ])
+    }
+}
@@ -1,4 +1,4 @@
-use TourBookingSystem::run;
+use tour_booking_system::run;
 
 fn main() {
     run();
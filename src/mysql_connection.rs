@@ -1,31 +1,121 @@
-use crate::bus::Bus;
-use crate::flight::Flight;
-use crate::hotels::Hotels;
-use crate::reservation::Reservation;
+use crate::bookable::{category, Bookable, Category, Place, CATEGORIES};
+use crate::cancellation::{CancelQuote, CancellationPolicy};
+use crate::catalog_filter::{CatalogFilter, Page};
+use crate::customer::{Customer, ProfileField};
+use crate::itinerary::ItineraryItem;
+use crate::loyalty::{LoyaltyAccount, PointsEntry, PointsKind};
+use crate::notification::{Message, NOTIFY_RETRY};
+use crate::package::{Package, PackageBooking, PackageItem, PACKAGE_RES_TYPE};
+use crate::payment::{Payment, PaymentError, PaymentKind, PaymentStatus};
+use crate::period::{Date, Period};
+use crate::pricing::{self, Demand, PriceQuote, PricingRule};
+use crate::prompt::Output;
+use crate::query::Query;
+use crate::report::{load_factor, Report, ReportKind, REPORT_LIMIT};
+use crate::reservation::{generate_booking_ref, Reservation, ReservationStatus};
+use crate::t;
 use mysql::prelude::Queryable;
-use mysql::{params, Error, Pool, PooledConn, Transaction};
-use std::borrow::BorrowMut;
-use std::collections::HashMap;
-use std::task::Poll;
+use mysql::{params, Error, IsolationLevel, Pool, PooledConn, Transaction, TxOpts};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// MySQL error code `ER_DUP_ENTRY`
+const DUPLICATE_ENTRY: u16 = 1062;
+/// MySQL error code `ER_LOCK_WAIT_TIMEOUT`
+const LOCK_WAIT_TIMEOUT: u16 = 1205;
+/// MySQL error code `ER_LOCK_DEADLOCK`
+const DEADLOCK: u16 = 1213;
+/// how many times we try to find an unused booking reference
+const BOOKING_REF_RETRY: u32 = 5;
+/// how many times a transaction is run when it keeps losing deadlocks
+const DEADLOCK_RETRY: u32 = 5;
+
+/// what a reservation costs: the price quoted when it was booked, or else the
+/// catalog price, dated reservations pay it once per day
+const RESERVATION_PRICE: &str = "COALESCE(Reservation.quoted_price, \
+    price * COALESCE(GREATEST(DATEDIFF(Reservation.end_date, Reservation.start_date), 1), 1))";
+
+/// columns read into a [`PricingRule`]
+const PRICING_RULE_COLUMNS: &str =
+    "res_type, min_load, max_load, min_days, max_days, weekday, adjust_percent";
+/// columns read into a [`Reservation`]
+const RESERVATION_COLUMNS: &str = "customer_id, res_type, res_id, id, booking_ref, \
+    DATE_FORMAT(start_date, '%Y-%m-%d'), DATE_FORMAT(end_date, '%Y-%m-%d'), status";
+/// reservations that take up their seat/room or were travelled, cancelled and
+/// expired ones are only kept as history
+const ACTIVE_RESERVATION: &str = "Reservation.status IN ('held', 'confirmed', 'completed')";
+/// reservations that were paid for, held ones may still expire
+const PAID_RESERVATION: &str = "Reservation.status IN ('confirmed', 'completed')";
+/// the first day a dated reservation no longer takes up, see [`Period::returned`]
+const RETURNED: &str = "GREATEST(Reservation.end_date, Reservation.start_date + INTERVAL 1 DAY)";
+/// tour packages that were not cancelled and did not expire
+const ACTIVE_PACKAGE: &str = "PackageBooking.status IN ('held', 'confirmed')";
+/// tour packages that were paid for, held ones may still expire
+const PAID_PACKAGE: &str = "PackageBooking.status = 'confirmed'";
+
+/// columns read into a [`Customer`]
+const CUSTOMER_COLUMNS: &str = "name, id, email, phone, DATE_FORMAT(birth_date, '%Y-%m-%d'), \
+    id_number, nationality, emergency_contact";
+
+/// outcome of the booking transaction
+enum Booking {
+    Booked(String),
+    NotFound,
+    SoldOut,
+    RefExhausted,
+    /// the price of the booking does not fit in the price column
+    TooExpensive,
+    /// an item of a tour package could not be booked
+    Unavailable(PackageItem),
+}
 
 pub struct MySQLConnection {
-    url: String,
-    pool: Pool,
     conn: PooledConn,
+    /// minutes a new booking is held before it must be confirmed, `None`
+    /// confirms bookings right away
+    hold_minutes: Option<u32>,
+    /// where failures are reported, stderr until [`MySQLConnection::set_output`]
+    out: Option<Output>,
 }
 
 impl MySQLConnection {
     pub fn new(url: &str) -> Self {
-        let url = url;
         let pool = Pool::new(url).unwrap();
+        MySQLConnection::from_pool(&pool)
+    }
+
+    /// take a connection of a shared pool, e.g. one per thread
+    pub fn from_pool(pool: &Pool) -> Self {
         let conn = pool.get_conn().unwrap();
         MySQLConnection {
-            url: url.to_string(),
-            pool,
             conn,
+            hold_minutes: None,
+            out: None,
         }
     }
 
+    /// report failures to `out`, e.g. the stream the session is written to
+    pub fn set_output(&mut self, out: Output) {
+        self.out = Some(out);
+    }
+
+    fn error(&self, message: impl AsRef<str>) {
+        match &self.out {
+            Some(out) => {
+                let mut out = out.borrow_mut();
+                writeln!(out, "{}", message.as_ref())
+                    .and_then(|_| out.flush())
+                    .expect("Error: write output failed");
+            }
+            None => eprintln!("{}", message.as_ref()),
+        }
+    }
+
+    /// hold new bookings for `minutes` until [`MySQLConnection::confirm_booking`]
+    pub fn set_hold_minutes(&mut self, minutes: Option<u32>) {
+        self.hold_minutes = minutes;
+    }
+
     // 安全检查:
     // 1. 检查操作时用户名是否存在
     // 2. 检查操作时的剩余数量和总数量之间的关系
@@ -40,370 +130,2046 @@ impl MySQLConnection {
             )
             .map(|row| row.map(|num_of_user: u32| num_of_user));
 
-        return if res.unwrap().unwrap() == 0 {
-            // println!("Error: user doesn't exist");
-            false
-        } else {
-            true
-        };
+        // println!("Error: user doesn't exist");
+        res.unwrap().unwrap() != 0
     }
 
     // 预定操作
-    pub fn book_hotel(&mut self, user_id: u32, res_id: String) -> bool {
-        self.user_exist(user_id);
-
-        let res = self.conn.exec_first(
-            "SELECT count(*)
-             FROM Hotels
-             WHERE hotel_num = :res_id",
-            params! {
-                "res_id" => &res_id
-            },
-        );
-
-        if res.unwrap() == Some(0) {
-            eprintln!("错误：未找到想要预约的酒店");
-            return false;
-        }
+    /// book an item of `category` for `user_id` and return the booking reference.
+    ///
+    /// The item row is locked with `SELECT ... FOR UPDATE` while the
+    /// reservations on it are counted, so two customers racing for the last seat
+    /// are serialized and only one of them gets it. A new reference is drawn if
+    /// the generated one is already taken, the whole transaction is retried on
+    /// deadlock.
+    pub fn book(&mut self, category: &Category, user_id: u32, res_id: String) -> Option<String> {
+        self.reserve(category, user_id, res_id, None, false)
+    }
 
-        self.conn.exec_drop(
-            "INSERT INTO Reservation \
-            VALUES (:customer_id, 2, :res_id, null);",
-            params! {
-                "customer_id" => user_id,
-                "res_id" => res_id,
-            },
-        );
+    /// book an item of a dated category, e.g. a rental car, for the days of `period`.
+    ///
+    /// Only the reservations whose periods overlap `period` count against the
+    /// capacity of the item.
+    pub fn book_period(
+        &mut self,
+        category: &Category,
+        user_id: u32,
+        res_id: String,
+        period: Period,
+    ) -> Option<String> {
+        self.reserve(category, user_id, res_id, Some(period), false)
+    }
 
-        return true;
+    /// like [`MySQLConnection::book`] and [`MySQLConnection::book_period`], paying
+    /// as much of the price as possible with the loyalty points of `user_id`
+    pub fn book_with_points(
+        &mut self,
+        category: &Category,
+        user_id: u32,
+        res_id: String,
+        period: Option<Period>,
+    ) -> Option<String> {
+        self.reserve(category, user_id, res_id, period, true)
     }
 
-    pub fn book_bus(&mut self, user_id: u32, res_id: String) -> bool {
+    fn reserve(
+        &mut self,
+        category: &Category,
+        user_id: u32,
+        res_id: String,
+        period: Option<Period>,
+        redeem: bool,
+    ) -> Option<String> {
         self.user_exist(user_id);
+        if category.dated && period.is_none() {
+            self.error(t!("error.period_required", category.name()));
+            return None;
+        }
 
-        let res = self.conn.exec_first(
-            "SELECT count(*)
-             FROM Bus
-             WHERE bus_num = :res_id",
-            params! {
-                "res_id" => &res_id
-            },
-        );
+        let not_found = t!("error.item_not_found", category.name());
+        self.book_in_transaction(user_id, &not_found, |tx| {
+            reserve_item(tx, category, user_id, &res_id, period, None, redeem)
+        })
+    }
 
-        if res.unwrap() == Some(0) {
-            eprintln!("错误：未找到想要预约的大巴");
-            return false;
-        }
+    /// book every item of a tour package for `user_id` and return the booking
+    /// reference of the package.
+    ///
+    /// The items are booked in one transaction the same way
+    /// [`MySQLConnection::book`] books them one by one, if any of them is sold
+    /// out nothing is booked. The items are locked in a fixed order so that two
+    /// packages sharing items do not deadlock each other.
+    pub fn book_package(&mut self, user_id: u32, package_id: &str) -> Option<String> {
+        self.reserve_package(user_id, package_id, false)
+    }
 
-        self.conn.exec_drop(
-            "INSERT INTO Reservation \
-            VALUES (:customer_id, 3, :res_id, null);",
-            params! {
-                "customer_id" => user_id,
-                "res_id" => res_id,
-            },
-        );
-        return true;
+    /// like [`MySQLConnection::book_package`], paying as much of the price as
+    /// possible with the loyalty points of `user_id`
+    pub fn book_package_with_points(&mut self, user_id: u32, package_id: &str) -> Option<String> {
+        self.reserve_package(user_id, package_id, true)
     }
 
-    pub fn book_flight(&mut self, user_id: u32, res_id: String) -> bool {
+    fn reserve_package(&mut self, user_id: u32, package_id: &str, redeem: bool) -> Option<String> {
         self.user_exist(user_id);
+        let package = match self.find_package(package_id) {
+            Some(package) => package,
+            None => {
+                self.error(t!("error.package_not_found"));
+                return None;
+            }
+        };
+        let mut items = package.items().to_vec();
+        items.sort_by(|a, b| (a.res_type(), a.res_id()).cmp(&(b.res_type(), b.res_id())));
 
-        let res = self.conn.exec_first(
-            "SELECT count(*) FROM Flights WHERE flight_num = :res_id",
-            params! {
-                "res_id" => &res_id
-            },
-        );
+        self.book_in_transaction(user_id, t!("error.package_not_found"), |tx| {
+            // 套餐价格以预定时为准
+            let price: u32 = match Query::new(
+                "SELECT price FROM Package WHERE package_id = ? FOR UPDATE",
+            )
+            .bind(package.package_id())
+            .first(tx)?
+            {
+                Some(price) => price,
+                None => return Ok(Booking::NotFound),
+            };
+            let (price, used) = loyalty_discount(tx, user_id, price, redeem)?;
+            let package_ref = match insert_with_booking_ref(tx, |booking_ref| {
+                Query::new(
+                    "INSERT INTO PackageBooking (booking_ref, customer_id, package_id, price, booked_at) \
+                     VALUES (?, ?, ?, ?, NOW())",
+                )
+                .bind(booking_ref)
+                .bind(user_id)
+                .bind(package.package_id())
+                .bind(price)
+            })? {
+                Some(package_ref) => package_ref,
+                None => return Ok(Booking::RefExhausted),
+            };
+            redeem_points(tx, user_id, &package_ref, used)?;
 
-        if res.unwrap() == Some(0) {
-            eprintln!("错误：未找到想要预约的航班");
-            return false;
+            // 任何一项失败都直接返回，事务未提交即回滚
+            for item in items.iter() {
+                let category = match category(item.res_type()) {
+                    Some(category) if !category.dated => category,
+                    _ => return Ok(Booking::Unavailable(item.clone())),
+                };
+                match reserve_item(
+                    tx,
+                    category,
+                    user_id,
+                    item.res_id(),
+                    None,
+                    Some(&package_ref),
+                    false,
+                )? {
+                    Booking::Booked(_) => {}
+                    Booking::RefExhausted => return Ok(Booking::RefExhausted),
+                    _ => return Ok(Booking::Unavailable(item.clone())),
+                }
+            }
+            Ok(Booking::Booked(package_ref))
+        })
+    }
+
+    /// run the booking transaction `body`, again if it was picked as a deadlock
+    /// victim, then hold and commit what it booked.
+    ///
+    /// Anything but [`Booking::Booked`] leaves the transaction uncommitted so it
+    /// rolls back, and why nothing was booked is reported, `not_found` when the
+    /// item or package does not exist.
+    fn book_in_transaction(
+        &mut self,
+        user_id: u32,
+        not_found: &str,
+        mut body: impl FnMut(&mut Transaction) -> Result<Booking, Error>,
+    ) -> Option<String> {
+        let hold_minutes = self.hold_minutes;
+        let res = self.retry_on_deadlock(|conn| {
+            let mut tx = conn.start_transaction(
+                TxOpts::default().set_isolation_level(Some(IsolationLevel::ReadCommitted)),
+            )?;
+            match body(&mut tx)? {
+                Booking::Booked(booking_ref) => {
+                    hold(&mut tx, user_id, &booking_ref, hold_minutes)?;
+                    tx.commit().map(|_| Booking::Booked(booking_ref))
+                }
+                booking => Ok(booking),
+            }
+        });
+
+        match res {
+            Ok(Booking::Booked(booking_ref)) => Some(booking_ref),
+            Ok(Booking::NotFound) => {
+                self.error(not_found);
+                None
+            }
+            Ok(Booking::SoldOut) => {
+                self.error(t!("error.sold_out"));
+                None
+            }
+            Ok(Booking::RefExhausted) => {
+                self.error(t!("error.booking_ref_exhausted"));
+                None
+            }
+            Ok(Booking::TooExpensive) => {
+                self.error(t!("error.price_overflow"));
+                None
+            }
+            Ok(Booking::Unavailable(item)) => {
+                self.error(t!(
+                    "error.package_unavailable",
+                    item.type_name(),
+                    item.res_id()
+                ));
+                None
+            }
+            Err(e) => {
+                self.error(t!("error.booking_failed", e));
+                None
+            }
         }
+    }
 
-        self.conn.exec_drop(
-            r"INSERT INTO Reservation
-            VALUES (:customer_id, 1, :res_id, null);",
-            params! {
-                "customer_id" => user_id,
-                "res_id" => res_id,
-            },
-        );
-        return true;
+    /// run `f` again when the server picked its transaction as a deadlock victim
+    fn retry_on_deadlock<T>(
+        &mut self,
+        mut f: impl FnMut(&mut PooledConn) -> Result<T, Error>,
+    ) -> Result<T, Error> {
+        let mut attempt = 1;
+        loop {
+            match f(&mut self.conn) {
+                Err(ref e) if is_deadlock(e) && attempt < DEADLOCK_RETRY => attempt += 1,
+                res => return res,
+            }
+        }
     }
 
     // 查询操作
-    pub fn query_flight(&mut self, user_id: u32) -> Vec<Flight> {
-        self.user_exist(user_id);
+    /// the items of `T` booked by `user_id`
+    pub fn query<T: Bookable>(&mut self, user_id: u32) -> Vec<T> {
+        self.booked(&T::CATEGORY, user_id)
+            .into_iter()
+            .map(T::from_row)
+            .collect()
+    }
 
-        self.conn
-            .query_map(
-                format!(
-                    "SELECT flight_num, price, from_city, arrive_city
-                     FROM Flights, Reservation
-                     WHERE customer_id = {}
-                     AND res_type = 1
-                     AND Flights.flight_num = Reservation.res_id",
-                    user_id
-                ),
-                |(flight_num, price, from_city, arrive_city)| {
-                    Flight::new(flight_num, price, from_city, arrive_city)
-                },
-            )
-            .expect("Error: query flight failed")
+    /// like [`MySQLConnection::query`], as table rows
+    pub fn query_rows(&mut self, category: &Category, user_id: u32) -> Vec<prettytable::Row> {
+        self.booked(category, user_id)
+            .into_iter()
+            .map(category.to_row)
+            .collect()
     }
 
-    pub fn query_hotel(&mut self, user_id: u32) -> Vec<Hotels> {
+    fn booked(&mut self, category: &Category, user_id: u32) -> Vec<mysql::Row> {
         self.user_exist(user_id);
 
-        self.conn
-            .query_map(
-                format!(
-                    "SELECT hotel_num, location, price
-                     FROM Hotels, Reservation
-                     WHERE customer_id = {}
-                     AND res_type = 2
-                     AND Hotels.hotel_num = Reservation.res_id",
-                    user_id
-                ),
-                |(hotel_num, location, price)| Hotels::new(hotel_num, location, price),
-            )
-            .expect("Error: query hotel failed")
+        Query::new(format!(
+            "SELECT {columns}
+             FROM {table}, Reservation
+             WHERE customer_id = ?
+             AND res_type = ?
+             AND {table}.{key} = Reservation.res_id
+             AND {active}",
+            columns = category.columns,
+            table = category.table,
+            key = category.key_column,
+            active = ACTIVE_RESERVATION
+        ))
+        .bind(user_id)
+        .bind(category.res_type)
+        .map(&mut self.conn, |row: mysql::Row| row)
+        .expect("Error: query reservation failed")
     }
 
-    pub fn query_bus(&mut self, user_id: u32) -> Vec<Bus> {
+    /// cities of every item of `category` booked by `user_id`,
+    /// in the order of [`Place::columns`]
+    pub fn booked_places(&mut self, category: &Category, user_id: u32) -> Vec<Vec<String>> {
         self.user_exist(user_id);
 
-        self.conn
-            .query_map(
-                format!(
-                    "SELECT bus_num, location, price
-                     FROM Bus, Reservation
-                     WHERE customer_id = {}
-                     AND res_type = 3
-                     AND Bus.bus_num = Reservation.res_id",
-                    user_id
-                ),
-                |(bus_num, location, price)| Bus::new(bus_num, location, price),
-            )
-            .expect("Error: query bus failed")
+        Query::new(format!(
+            "SELECT {columns}
+             FROM {table}, Reservation
+             WHERE customer_id = ?
+             AND res_type = ?
+             AND {table}.{key} = Reservation.res_id
+             AND {active}",
+            columns = category.place.columns().join(", "),
+            table = category.table,
+            key = category.key_column,
+            active = ACTIVE_RESERVATION
+        ))
+        .bind(user_id)
+        .bind(category.res_type)
+        .map(&mut self.conn, |row: mysql::Row| {
+            row.unwrap().into_iter().map(mysql::from_value).collect()
+        })
+        .expect("Error: query reservation failed")
     }
 
-    pub fn query_all_flight(&mut self) -> Vec<Flight> {
-        self.conn
-            .query_map(
-                "SELECT flight_num, price, from_city, arrive_city
-                FROM Flights",
-                |(flight_num, price, from_city, arrive_city)| {
-                    Flight::new(flight_num, price, from_city, arrive_city)
-                },
-            )
-            .expect("Error: query flight failed")
+    // 条件查询：筛选条件全部以参数绑定，排序列只会是 Category 中写死的列名
+    pub fn search<T: Bookable>(&mut self, filter: &CatalogFilter, page: &Page) -> Vec<T> {
+        self.select(&T::CATEGORY, filter, page)
+            .into_iter()
+            .map(T::from_row)
+            .collect()
     }
 
-    pub fn query_all_hotel(&mut self) -> Vec<Hotels> {
-        self.conn
-            .query_map(
-                "SELECT hotel_num, location, price FROM Hotels",
-                |(hotel_num, location, price)| Hotels::new(hotel_num, location, price),
+    /// like [`MySQLConnection::search`], as table rows
+    pub fn search_rows(
+        &mut self,
+        category: &Category,
+        filter: &CatalogFilter,
+        page: &Page,
+    ) -> Vec<prettytable::Row> {
+        self.select(category, filter, page)
+            .into_iter()
+            .map(category.to_row)
+            .collect()
+    }
+
+    fn select(
+        &mut self,
+        category: &Category,
+        filter: &CatalogFilter,
+        page: &Page,
+    ) -> Vec<mysql::Row> {
+        filter
+            .condition(
+                Query::new(format!(
+                    "SELECT {} FROM {}",
+                    category.columns, category.table
+                )),
+                category,
             )
-            .expect("Error: query hotel failed")
+            .push(&format!(
+                " ORDER BY {} LIMIT ? OFFSET ?",
+                filter.sort().order_by(category)
+            ))
+            .bind(page.size())
+            .bind(page.offset())
+            .map(&mut self.conn, |row: mysql::Row| row)
+            .expect("Error: query catalog failed")
     }
 
-    pub fn query_all_bus(&mut self) -> Vec<Bus> {
-        self.conn
-            .query_map(
-                "SELECT bus_num, location, price FROM Bus",
-                |(bus_num, location, price)| Bus::new(bus_num, location, price),
+    // 条件查询的总行数，用于分页
+    pub fn count(&mut self, category: &Category, filter: &CatalogFilter) -> u32 {
+        filter
+            .condition(
+                Query::new(format!("SELECT count(*) FROM {}", category.table)),
+                category,
             )
-            .expect("Error: query bus failed")
+            .first(&mut self.conn)
+            .expect("Error: count rows failed")
+            .unwrap_or(0)
+    }
+
+    pub fn find<T: Bookable>(&mut self, key: &str) -> Option<T> {
+        self.find_raw(&T::CATEGORY, key).map(T::from_row)
+    }
+
+    /// like [`MySQLConnection::find`], as a table row
+    pub fn find_row(&mut self, category: &Category, key: &str) -> Option<prettytable::Row> {
+        self.find_raw(category, key).map(category.to_row)
+    }
+
+    fn find_raw(&mut self, category: &Category, key: &str) -> Option<mysql::Row> {
+        Query::new(format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            category.columns, category.table, category.key_column
+        ))
+        .bind(key)
+        .first(&mut self.conn)
+        .expect("Error: query catalog failed")
+    }
+
+    /// all reservations of a user that are not cancelled or expired, together
+    /// with their booking references
+    pub fn query_reservations(&mut self, user_id: u32) -> Vec<Reservation> {
+        self.user_exist(user_id);
+
+        Query::new(format!(
+            "SELECT {} FROM Reservation WHERE customer_id = ? AND {} ORDER BY res_type, id",
+            RESERVATION_COLUMNS, ACTIVE_RESERVATION
+        ))
+        .bind(user_id)
+        .map(&mut self.conn, reservation)
+        .expect("Error: query reservation failed")
+    }
+
+    /// what `user_id` booked in `category`, with the cities, times and prices
+    pub fn itinerary(&mut self, category: &'static Category, user_id: u32) -> Vec<ItineraryItem> {
+        self.user_exist(user_id);
+
+        // 按天预订的只保留日期，导出为全天事件
+        let format = if category.dated {
+            "%Y-%m-%d"
+        } else {
+            "%Y-%m-%d %H:%i"
+        };
+        Query::new(format!(
+            "SELECT booking_ref, res_id, CAST({price} AS UNSIGNED), package_ref,
+                    DATE_FORMAT({time}, '{format}'), DATE_FORMAT({end}, '{format}'), {places}
+             FROM {table}, Reservation
+             WHERE customer_id = ?
+             AND res_type = ?
+             AND {table}.{key} = Reservation.res_id
+             AND {active}",
+            price = RESERVATION_PRICE,
+            time = category.time_column,
+            end = category.end_column,
+            format = format,
+            places = category.place.columns().join(", "),
+            table = category.table,
+            key = category.key_column,
+            active = ACTIVE_RESERVATION
+        ))
+        .bind(user_id)
+        .bind(category.res_type)
+        .map(&mut self.conn, |row: mysql::Row| {
+            itinerary_item(category, row)
+        })
+        .expect("Error: query reservation failed")
+    }
+
+    /// look up a reservation by its booking reference,
+    /// only reservations that belong to `user_id` are visible.
+    pub fn query_reservation_by_ref(
+        &mut self,
+        user_id: u32,
+        booking_ref: &str,
+    ) -> Option<Reservation> {
+        Query::new(format!(
+            "SELECT {} FROM Reservation WHERE customer_id = ? AND booking_ref = ?",
+            RESERVATION_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(booking_ref)
+        .first(&mut self.conn)
+        .expect("Error: query reservation failed")
+        .map(reservation)
+    }
+
+    pub fn query_reservation_by_id(&mut self, user_id: u32, id: u32) -> Option<Reservation> {
+        Query::new(format!(
+            "SELECT {} FROM Reservation WHERE customer_id = ? AND id = ?",
+            RESERVATION_COLUMNS
+        ))
+        .bind(user_id)
+        .bind(id)
+        .first(&mut self.conn)
+        .expect("Error: query reservation failed")
+        .map(reservation)
     }
 
     pub fn query_user_name(&mut self, user_id: u32) -> String {
         // 已经确定用户存在，所以直接解包
-        self.conn
-            .query_first(format!(
-                "SELECT name \
-                FROM customer \
-                WHERE id = {}",
-                user_id
-            ))
-            .unwrap()
+        Query::new("SELECT name FROM Customer WHERE id = ?")
+            .bind(user_id)
+            .first(&mut self.conn)
+            .expect("Error: query user name failed")
             .unwrap()
     }
 
-    pub fn check_consistency(&mut self) -> bool {
-        let res = self
-            .conn
-            .query_map(
-                "SELECT res_type, res_id, count(*) FROM Reservation GROUP BY res_id, res_type",
-                |(res_type, res_id, num): (u32, String, u32)| (res_type, res_id, num),
+    pub fn query_customer(&mut self, user_id: u32) -> Option<Customer> {
+        Query::new(format!(
+            "SELECT {} FROM Customer WHERE id = ?",
+            CUSTOMER_COLUMNS
+        ))
+        .bind(user_id)
+        .first(&mut self.conn)
+        .expect("Error: query customer failed")
+        .map(customer)
+    }
+
+    /// store a value checked by [`ProfileField::validate`], `None` clears the field
+    pub fn update_profile(&mut self, user_id: u32, field: ProfileField, value: Option<String>) {
+        Query::new(format!(
+            "UPDATE Customer SET {} = ? WHERE id = ?",
+            field.column()
+        ))
+        .bind(value)
+        .bind(user_id)
+        .execute(&mut self.conn)
+        .expect("Error: update profile failed");
+    }
+
+    pub fn is_admin(&mut self, user_id: u32) -> bool {
+        Query::new("SELECT is_admin FROM Customer WHERE id = ?")
+            .bind(user_id)
+            .first(&mut self.conn)
+            .expect("Error: query customer failed")
+            .unwrap_or(false)
+    }
+
+    /// customers whose name, email or phone contains `keyword`
+    pub fn search_customers(&mut self, keyword: &str) -> Vec<Customer> {
+        let pattern = format!("%{}%", escape_like(keyword.trim()));
+        Query::new(format!(
+            "SELECT {} FROM Customer \
+             WHERE name LIKE ? OR email LIKE ? OR phone LIKE ? \
+             ORDER BY id",
+            CUSTOMER_COLUMNS
+        ))
+        .bind(&pattern)
+        .bind(&pattern)
+        .bind(&pattern)
+        .map(&mut self.conn, customer)
+        .expect("Error: search customer failed")
+    }
+
+    /// compute one of the sales reports
+    pub fn report(&mut self, kind: ReportKind) -> Report {
+        let rows =
+            match kind {
+                ReportKind::Occupancy(category) => self.occupancy(category),
+                ReportKind::RevenueByCategory => {
+                    let mut rows = Vec::new();
+                    for category in CATEGORIES.iter() {
+                        let (bookings, revenue) = self
+                            .revenue_by(category, "res_type")
+                            .into_iter()
+                            .fold((0, 0), |(a, b), (_, x, y)| (a + x, b + y));
+                        rows.push(revenue_row(category.name().to_string(), bookings, revenue));
+                    }
+                    let (bookings, revenue) = self
+                        .package_revenue_by("'packages'")
+                        .into_iter()
+                        .fold((0, 0), |(a, b), (_, x, y)| (a + x, b + y));
+                    rows.push(revenue_row(
+                        t!("report.packages").to_string(),
+                        bookings,
+                        revenue,
+                    ));
+                    rows
+                }
+                ReportKind::RevenueByCity => {
+                    let mut groups = Vec::new();
+                    for category in CATEGORIES.iter() {
+                        // 路线算到达城市，其余算所在(取车)城市
+                        let city = match category.place {
+                            Place::Route(_, to) => to,
+                            Place::Location(city) | Place::OnRoute(city, _) => city,
+                        };
+                        groups.extend(self.revenue_by(category, city));
+                    }
+                    let mut groups = merge_groups(groups);
+                    groups.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+                    groups
+                        .into_iter()
+                        .map(|(city, bookings, revenue)| revenue_row(city, bookings, revenue))
+                        .collect()
+                }
+                ReportKind::RevenueByMonth => {
+                    let month = "DATE_FORMAT(booked_at, '%Y-%m')";
+                    let mut groups = Vec::new();
+                    for category in CATEGORIES.iter() {
+                        groups.extend(self.revenue_by(category, month));
+                    }
+                    groups.extend(self.package_revenue_by(month));
+                    merge_groups(groups)
+                        .into_iter()
+                        .map(|(month, bookings, revenue)| revenue_row(month, bookings, revenue))
+                        .collect()
+                }
+                ReportKind::TopCustomers => {
+                    let mut groups = Vec::new();
+                    for category in CATEGORIES.iter() {
+                        groups.extend(self.revenue_by(category, "customer_id"));
+                    }
+                    groups.extend(self.package_revenue_by("customer_id"));
+                    let mut groups = merge_groups(groups);
+                    groups.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| b.1.cmp(&a.1)));
+                    groups.truncate(REPORT_LIMIT);
+                    groups
+                        .into_iter()
+                        .map(|(id, bookings, revenue)| {
+                            let name = id
+                                .parse()
+                                .ok()
+                                .and_then(|id| self.query_customer(id))
+                                .map(|x| x.name().to_string())
+                                .unwrap_or_default();
+                            vec![id, name, bookings.to_string(), revenue.to_string()]
+                        })
+                        .collect()
+                }
+                ReportKind::PopularRoutes => {
+                    let mut groups = Vec::new();
+                    for category in CATEGORIES.iter() {
+                        if let Place::Route(from, to) = category.place {
+                            groups.extend(self.revenue_by(
+                                category,
+                                &format!("CONCAT_WS(' → ', {}, {})", from, to),
+                            ));
+                        }
+                    }
+                    let mut groups = merge_groups(groups);
+                    groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.2.cmp(&a.2)));
+                    groups.truncate(REPORT_LIMIT);
+                    groups
+                        .into_iter()
+                        .map(|(route, bookings, revenue)| revenue_row(route, bookings, revenue))
+                        .collect()
+                }
+            };
+        Report::new(kind, rows)
+    }
+
+    /// booked seats/rooms of every item of `category`, the fullest first
+    fn occupancy(&mut self, category: &Category) -> Vec<Vec<String>> {
+        Query::new(format!(
+            "SELECT {table}.{key}, CONCAT_WS(' → ', {places}), CAST({capacity} AS UNSIGNED),
+                    COUNT(Reservation.id)
+             FROM {table} LEFT JOIN Reservation
+             ON Reservation.res_type = ? AND Reservation.res_id = {table}.{key} AND {active}
+             GROUP BY {table}.{key}
+             ORDER BY COUNT(Reservation.id) / {capacity} DESC, {table}.{key}",
+            places = category.place.columns().join(", "),
+            capacity = category.capacity_column,
+            table = category.table,
+            key = category.key_column,
+            active = ACTIVE_RESERVATION
+        ))
+        .bind(category.res_type)
+        .map(
+            &mut self.conn,
+            |(item, place, capacity, booked): (String, String, u64, u64)| {
+                vec![
+                    item,
+                    place,
+                    capacity.to_string(),
+                    booked.to_string(),
+                    load_factor(booked, capacity),
+                ]
+            },
+        )
+        .expect("Error: query report failed")
+    }
+
+    /// bookings and revenue of `category` grouped by the expression `group`,
+    /// items of packages are paid by the package and unpaid holds are left out
+    fn revenue_by(&mut self, category: &Category, group: &str) -> Vec<(String, u64, u64)> {
+        Query::new(format!(
+            "SELECT COALESCE(CAST({group} AS CHAR), ''), COUNT(*), CAST(SUM({price}) AS UNSIGNED)
+             FROM {table}, Reservation
+             WHERE res_type = ?
+             AND package_ref IS NULL
+             AND {table}.{key} = Reservation.res_id
+             AND {active}
+             GROUP BY 1",
+            group = group,
+            price = RESERVATION_PRICE,
+            table = category.table,
+            key = category.key_column,
+            active = PAID_RESERVATION
+        ))
+        .bind(category.res_type)
+        .map(&mut self.conn, |x: (String, u64, u64)| x)
+        .expect("Error: query report failed")
+    }
+
+    /// like [`MySQLConnection::revenue_by`], for the booked tour packages
+    fn package_revenue_by(&mut self, group: &str) -> Vec<(String, u64, u64)> {
+        Query::new(format!(
+            "SELECT COALESCE(CAST({} AS CHAR), ''), COUNT(*), CAST(SUM(price) AS UNSIGNED)
+             FROM PackageBooking
+             WHERE {}
+             GROUP BY 1",
+            group, PAID_PACKAGE
+        ))
+        .map(&mut self.conn, |x: (String, u64, u64)| x)
+        .expect("Error: query report failed")
+    }
+
+    /// the loyalty points of `user_id`
+    pub fn loyalty_account(&mut self, user_id: u32) -> LoyaltyAccount {
+        loyalty_account(&mut self.conn, user_id).expect("Error: query loyalty points failed")
+    }
+
+    /// every change of the points of `user_id`, the latest first
+    pub fn points_statement(&mut self, user_id: u32) -> Vec<PointsEntry> {
+        Query::new(
+            "SELECT kind, points, booking_ref, DATE_FORMAT(created_at, '%Y-%m-%d %H:%i')
+             FROM LoyaltyLedger
+             WHERE customer_id = ?
+             ORDER BY id DESC",
+        )
+        .bind(user_id)
+        .map(
+            &mut self.conn,
+            |(kind, points, booking_ref, created_at): (String, i64, Option<String>, String)| {
+                (PointsKind::parse(&kind), points, booking_ref, created_at)
+            },
+        )
+        .expect("Error: query loyalty points failed")
+        .into_iter()
+        .filter_map(|(kind, points, booking_ref, created_at)| {
+            Some(PointsEntry::new(kind?, points, booking_ref, created_at))
+        })
+        .collect()
+    }
+
+    /// what every item of the catalogs would cost if it were booked today,
+    /// dated items are priced for a rental starting today
+    pub fn pricing_simulation(&mut self) -> Vec<PriceQuote> {
+        let today = Date::today();
+        let mut quotes = Vec::new();
+        for category in CATEGORIES.iter().copied() {
+            let rules = pricing_rules(&mut self.conn, category.res_type)
+                .expect("Error: query pricing failed");
+            let overlapping = if category.dated {
+                format!(" AND start_date <= CURDATE() AND {} > CURDATE()", RETURNED)
+            } else {
+                String::new()
+            };
+            let items = Query::new(format!(
+                "SELECT {key}, price, {capacity},
+                        (SELECT COUNT(*) FROM Reservation
+                         WHERE res_type = ? AND res_id = {table}.{key} AND {active}{overlapping}),
+                        DATE_FORMAT({time}, '%Y-%m-%d')
+                 FROM {table}
+                 ORDER BY {key}",
+                capacity = category.capacity_column,
+                overlapping = overlapping,
+                active = ACTIVE_RESERVATION,
+                time = if category.dated {
+                    "CURDATE()"
+                } else {
+                    category.time_column
+                },
+                table = category.table,
+                key = category.key_column
+            ))
+            .bind(category.res_type)
+            .map(
+                &mut self.conn,
+                |x: (String, u32, u32, u32, Option<String>)| x,
             )
-            .expect("user_id should be u32");
+            .expect("Error: query pricing failed");
+            for (res_id, base, capacity, booked, departure) in items {
+                let departure = departure.and_then(|x| x.parse().ok());
+                let demand = Demand::new(booked, capacity, departure, today);
+                let price = pricing::quote(base, &rules, category.res_type, &demand);
+                quotes.push(PriceQuote::new(category, res_id, base, demand, price));
+            }
+        }
+        quotes
+    }
+
+    pub fn check_consistency(&mut self) -> bool {
+        let res = Query::new(format!(
+            "SELECT res_type, res_id, count(*) FROM Reservation WHERE {} GROUP BY res_id, res_type",
+            ACTIVE_RESERVATION
+        ))
+        .map(
+            &mut self.conn,
+            |(res_type, res_id, num): (u32, String, u32)| (res_type, res_id, num),
+        )
+        .expect("user_id should be u32");
 
         for (res_type, res_id, num) in res.iter() {
-            if match res_type {
-                1 => self.flight_consistency(res_id, num),
-                2 => self.hotel_consistency(res_id, num),
-                3 => self.bus_consistency(res_id, num),
-                _ => false,
-            } == false
-            {
+            let consistent = match category(*res_type) {
+                Some(category) => self.item_consistency(category, res_id, *num),
+                None => false,
+            };
+            if !consistent {
                 return false;
             }
         }
 
-        return true;
+        true
     }
 
-    fn flight_consistency(&mut self, res_id: &String, res_num: &u32) -> bool {
-        let res: Option<(u32, u32)> = self
-            .conn
-            .query_first(format!(
-                "SELECT num_seat, num_available \
-                FROM Flights \
-                WHERE flight_num = '{}'",
-                res_id
-            ))
-            .unwrap();
-        return match res {
+    /// the seats/rooms taken on an item match its reservations
+    fn item_consistency(&mut self, category: &Category, res_id: &str, res_num: u32) -> bool {
+        if category.dated {
+            return self.period_consistency(category, res_id);
+        }
+        let res: Option<(u32, u32)> = Query::new(format!(
+            "SELECT {}, num_available FROM {} WHERE {} = ?",
+            category.capacity_column, category.table, category.key_column
+        ))
+        .bind(res_id)
+        .first(&mut self.conn)
+        .expect("Error: query consistency failed");
+        match res {
             None => false,
-            Some((sum, avail)) => sum - avail == res_num.clone(),
-        };
+            Some((sum, avail)) => sum.checked_sub(avail) == Some(res_num),
+        }
     }
 
-    fn bus_consistency(&mut self, res_id: &String, res_num: &u32) -> bool {
-        let res: Option<(u32, u32)> = self
-            .conn
-            .query_first(format!(
-                "SELECT num_bus, num_available \
-                FROM Bus \
-                WHERE bus_num = '{}'",
-                res_id
-            ))
-            .unwrap();
-        return match res {
+    /// the reservations of a dated item never outnumber its capacity on any day.
+    ///
+    /// The most reservations overlap on the first day of one of them, so it is
+    /// enough to count the overlaps at every start date.
+    fn period_consistency(&mut self, category: &Category, res_id: &str) -> bool {
+        let capacity: Option<u32> = Query::new(format!(
+            "SELECT {} FROM {} WHERE {} = ?",
+            category.capacity_column, category.table, category.key_column
+        ))
+        .bind(res_id)
+        .first(&mut self.conn)
+        .expect("Error: query consistency failed");
+        // 同时占用的预定最多的时刻总在某个预定开始的那天
+        let overlap: Option<u32> = Query::new(format!(
+            "SELECT count(*) FROM Reservation a, Reservation b \
+             WHERE a.res_type = ? AND a.res_id = ? \
+               AND b.res_type = a.res_type AND b.res_id = a.res_id \
+               AND b.start_date <= a.start_date AND {returned} > a.start_date \
+               AND {active_a} AND {active_b} \
+             GROUP BY a.id \
+             ORDER BY count(*) DESC \
+             LIMIT 1",
+            returned = aliased(RETURNED, "b"),
+            active_a = aliased(ACTIVE_RESERVATION, "a"),
+            active_b = aliased(ACTIVE_RESERVATION, "b")
+        ))
+        .bind(category.res_type)
+        .bind(res_id)
+        .first(&mut self.conn)
+        .expect("Error: query consistency failed");
+        match capacity {
             None => false,
-            Some((sum, avail)) => sum - avail == res_num.clone(),
-        };
+            Some(capacity) => overlap.unwrap_or(0) <= capacity,
+        }
     }
 
-    fn hotel_consistency(&mut self, res_id: &String, res_num: &u32) -> bool {
-        let res: Option<(u32, u32)> = self
-            .conn
-            .query_first(format!(
-                "SELECT num_rooms, num_available \
-                FROM Hotels \
-                WHERE hotel_num = '{}'",
-                res_id
+    /// what the trip of `user_id` costs, every reservation at its current price
+    pub fn trip_total(&mut self, user_id: u32) -> u64 {
+        self.user_exist(user_id);
+
+        let mut total = 0;
+        for category in CATEGORIES.iter() {
+            // 套餐中的各项按套餐价格计算
+            let sum: Option<u64> = Query::new(format!(
+                "SELECT CAST(SUM({price}) AS UNSIGNED)
+                 FROM {table}, Reservation
+                 WHERE customer_id = ?
+                 AND res_type = ?
+                 AND package_ref IS NULL
+                 AND {table}.{key} = Reservation.res_id
+                 AND {active}",
+                price = RESERVATION_PRICE,
+                table = category.table,
+                key = category.key_column,
+                active = ACTIVE_RESERVATION
             ))
-            .unwrap();
-        return match res {
-            None => false,
-            Some((sum, avail)) => sum - avail == res_num.clone(),
-        };
+            .bind(user_id)
+            .bind(category.res_type)
+            .first(&mut self.conn)
+            .expect("Error: query trip total failed")
+            .flatten();
+            total += sum.unwrap_or(0);
+        }
+        let packages: Option<u64> = Query::new(format!(
+            "SELECT CAST(SUM(price) AS UNSIGNED) FROM PackageBooking WHERE customer_id = ? AND {}",
+            PAID_PACKAGE
+        ))
+        .bind(user_id)
+        .first(&mut self.conn)
+        .expect("Error: query trip total failed")
+        .flatten();
+        total + packages.unwrap_or(0)
     }
 
     pub fn add_user(&mut self, id: u32, name: String) {
-        self.conn.exec_drop(
-            "INSERT INTO Customer VALUES (:name, :id);",
-            params! {
-                "name" => name,
-                "id" => id
-            },
-        );
+        self.conn
+            .exec_drop(
+                "INSERT INTO Customer (name, id) VALUES (:name, :id);",
+                params! {
+                    "name" => name,
+                    "id" => id
+                },
+            )
+            .expect("Error: add user failed");
     }
 
-    pub fn cancel_flight(&mut self, user_id: u32, res_id: String) -> bool {
-        self.user_exist(user_id);
+    /// cancel one reservation of `user_id` on the given item
+    pub fn cancel(&mut self, category: &Category, user_id: u32, res_id: String) -> bool {
+        match self.reservation_id(user_id, category.res_type, &res_id) {
+            Some(id) => self.cancel_reservation(user_id, id),
+            None => {
+                self.error(t!("error.item_not_booked", category.name()));
+                false
+            }
+        }
+    }
 
-        let res = self.conn.exec_first(
-            "SELECT count(*) \
-                FROM Reservation \
-                WHERE customer_id = :user_id \
-                  AND res_type = 1 \
-                  AND res_id = :res_id",
-            params! {
-                "user_id" => user_id,
-                "res_id" => &res_id
-            },
-        );
+    pub fn cancel_by_ref(&mut self, user_id: u32, booking_ref: &str) -> bool {
+        match self.query_reservation_by_ref(user_id, booking_ref) {
+            Some(reservation) => self.cancel_reservation(user_id, reservation.id()),
+            None => {
+                self.error(t!("error.booking_ref_not_found"));
+                false
+            }
+        }
+    }
 
-        if res.unwrap() == Some(0) {
-            eprintln!("未预约对应的航班");
+    /// cancel exactly one reservation, identified by `Reservation.id`.
+    ///
+    /// The row must belong to `user_id`, so other customers who booked the same
+    /// flight/hotel/bus keep their reservations. The refund worked out by the
+    /// cancellation policy is recorded in `Refund` within the same transaction,
+    /// which is retried on deadlock. A held booking was never paid and gets no
+    /// refund.
+    pub fn cancel_reservation(&mut self, user_id: u32, id: u32) -> bool {
+        if let Some(package_ref) = self.package_ref(user_id, id) {
+            self.error(t!("error.part_of_package", package_ref));
             return false;
         }
+        let (reservation, quote) = match self.cancel_quote(user_id, id) {
+            Some(quoted) => quoted,
+            None => {
+                self.error(t!("error.reservation_not_found"));
+                return false;
+            }
+        };
 
-        self.conn.exec_drop(
-            "DELETE FROM BookingSystem.Reservation \
-                WHERE res_id = :res_id",
-            params! {
-                "res_id" => res_id
-            },
-        );
+        let res = self.retry_on_deadlock(|conn| {
+            conn.start_transaction(TxOpts::default()).and_then(|mut tx| {
+                // 先改状态再记录退款, 同时取消同一预定时后到的事务会更新 0 行而不是撞上主键
+                tx.exec_drop(
+                    "UPDATE Reservation \
+                        SET status = 'cancelled', hold_until = NULL, status_at = NOW() \
+                        WHERE id = :id \
+                          AND customer_id = :user_id \
+                          AND package_ref IS NULL \
+                          AND status = :status",
+                    params! {
+                        "id" => id,
+                        "user_id" => user_id,
+                        "status" => reservation.status().as_str()
+                    },
+                )?;
+                // 状态在报价后变了(例如刚付款)时不按旧的报价取消
+                if tx.affected_rows() != 1 {
+                    return tx.rollback().map(|_| false);
+                }
+                give_back(&mut tx, reservation.res_type(), reservation.res_id())?;
+                // 保留中的预定还没有付款，没有可退的
+                if reservation.status() != ReservationStatus::Held {
+                    tx.exec_drop(
+                        "INSERT INTO Refund \
+                            (reservation_id, booking_ref, customer_id, res_type, res_id, price, fee, refund, cancelled_at) \
+                         VALUES \
+                            (:id, :booking_ref, :user_id, :res_type, :res_id, :price, :fee, :refund, NOW())",
+                        params! {
+                            "id" => id,
+                            "booking_ref" => reservation.booking_ref(),
+                            "user_id" => user_id,
+                            "res_type" => reservation.res_type(),
+                            "res_id" => reservation.res_id(),
+                            "price" => quote.price(),
+                            "fee" => quote.fee(),
+                            "refund" => quote.refund(),
+                        },
+                    )?;
+                }
+                reverse_points(&mut tx, user_id, reservation.booking_ref())?;
+                tx.commit().map(|_| true)
+            })
+        });
+
+        match res {
+            Ok(true) => true,
+            Ok(false) => {
+                self.error(t!("error.reservation_not_found"));
+                false
+            }
+            Err(e) => {
+                self.error(t!("error.cancel_failed", e));
+                false
+            }
+        }
+    }
+
+    /// refund the customer would get by cancelling reservation `id` right now
+    pub fn cancel_quote(&mut self, user_id: u32, id: u32) -> Option<(Reservation, CancelQuote)> {
+        let reservation = self
+            .query_reservation_by_id(user_id, id)
+            .filter(|x| x.status().is_cancellable())?;
+        let category = category(reservation.res_type())?;
+        let (price, hours_before): (u32, Option<i64>) = Query::new(format!(
+            "SELECT {price}, TIMESTAMPDIFF(HOUR, NOW(), {time})
+             FROM {table}, Reservation
+             WHERE Reservation.id = ?
+             AND {table}.{key} = Reservation.res_id",
+            price = RESERVATION_PRICE,
+            time = category.time_column,
+            table = category.table,
+            key = category.key_column
+        ))
+        .bind(reservation.id())
+        .first(&mut self.conn)
+        .expect("Error: query price failed")?;
 
-        return true;
+        let policy = self.cancellation_policy(reservation.res_type(), reservation.res_id());
+        let quote = policy.quote(price, hours_before);
+        match reservation.status() {
+            ReservationStatus::Held => Some((reservation, quote.unpaid())),
+            _ => Some((reservation, quote)),
+        }
     }
 
-    pub fn cancel_bus(&mut self, user_id: u32, res_id: String) -> bool {
-        self.user_exist(user_id);
+    /// policy of the item itself if configured, otherwise the one of its catalog type
+    pub fn cancellation_policy(&mut self, res_type: u32, res_id: &str) -> CancellationPolicy {
+        self.conn
+            .exec_first(
+                "SELECT free_hours, fee_percent, refundable \
+                    FROM CancellationPolicy \
+                    WHERE res_type = :res_type \
+                      AND (res_id = :res_id OR res_id IS NULL) \
+                    ORDER BY res_id IS NULL, id DESC \
+                    LIMIT 1",
+                params! {
+                    "res_type" => res_type,
+                    "res_id" => res_id
+                },
+            )
+            .expect("Error: query cancellation policy failed")
+            .map(|(free_hours, fee_percent, refundable)| {
+                CancellationPolicy::new(free_hours, fee_percent, refundable)
+            })
+            .unwrap_or_else(CancellationPolicy::free)
+    }
 
-        let res = self.conn.exec_first(
-            "SELECT count(*) \
-                FROM Reservation \
-                WHERE customer_id = :user_id \
-                  AND res_type = 3 \
-                  AND res_id = :res_id",
-            params! {
-                "user_id" => user_id,
-                "res_id" => &res_id
+    /// total amount refunded to a reservation, `None` if it was never cancelled
+    pub fn query_refund(&mut self, id: u32) -> Option<u32> {
+        self.conn
+            .exec_first(
+                "SELECT refund FROM Refund WHERE reservation_id = :id",
+                params! { "id" => id },
+            )
+            .expect("Error: query refund failed")
+    }
+
+    // 通知队列
+    pub fn enqueue_notification(&mut self, user_id: u32, message: &Message) {
+        Query::new(
+            "INSERT INTO Notification \
+                (customer_id, recipient, subject, body, created_at, next_attempt_at) \
+             VALUES (?, ?, ?, ?, NOW(), NOW())",
+        )
+        .bind(user_id)
+        .bind(message.to())
+        .bind(message.subject())
+        .bind(message.body())
+        .execute(&mut self.conn)
+        .expect("Error: queue notification failed");
+    }
+
+    /// messages not sent yet whose next attempt is due, oldest first
+    pub fn pending_notifications(&mut self) -> Vec<(u32, Message)> {
+        Query::new(
+            "SELECT id, recipient, subject, body FROM Notification \
+             WHERE sent_at IS NULL AND attempts < ? AND next_attempt_at <= NOW() \
+             ORDER BY id",
+        )
+        .bind(NOTIFY_RETRY)
+        .map(
+            &mut self.conn,
+            |(id, to, subject, body): (u32, String, String, String)| {
+                (id, Message::new(to, subject, body))
             },
-        );
+        )
+        .expect("Error: query notification failed")
+    }
 
-        if res.unwrap() == Some(0) {
-            eprintln!("未预约对应的大巴");
-            return false;
+    pub fn notification_sent(&mut self, id: u32) {
+        Query::new("UPDATE Notification SET sent_at = NOW(), attempts = attempts + 1 WHERE id = ?")
+            .bind(id)
+            .execute(&mut self.conn)
+            .expect("Error: update notification failed");
+    }
+
+    /// keep the message for later, every failure waits five minutes longer
+    pub fn notification_failed(&mut self, id: u32, error: &str) {
+        let error: String = error.chars().take(200).collect();
+        Query::new(
+            "UPDATE Notification \
+             SET attempts = attempts + 1, \
+                 last_error = ?, \
+                 next_attempt_at = NOW() + INTERVAL (attempts * 5) MINUTE \
+             WHERE id = ?",
+        )
+        .bind(error)
+        .bind(id)
+        .execute(&mut self.conn)
+        .expect("Error: update notification failed");
+    }
+
+    // 付款记录
+    /// record a charge of `booking_ref` sent to `provider`, return its id
+    pub fn charge_started(
+        &mut self,
+        user_id: u32,
+        booking_ref: &str,
+        amount: u32,
+        provider: &str,
+    ) -> u32 {
+        Query::new(
+            "INSERT INTO Payment (customer_id, booking_ref, kind, amount, status, provider, created_at) \
+             VALUES (?, ?, ?, ?, ?, ?, NOW())",
+        )
+        .bind(user_id)
+        .bind(booking_ref)
+        .bind(PaymentKind::Charge.as_str())
+        .bind(amount)
+        .bind(PaymentStatus::Pending.as_str())
+        .bind(provider)
+        .execute(&mut self.conn)
+        .expect("Error: record payment failed");
+        self.conn.last_insert_id() as u32
+    }
+
+    /// record a refund of `amount` of the charge `charge_id`, return its id
+    pub fn refund_started(&mut self, charge_id: u32, amount: u32) -> u32 {
+        Query::new(
+            "INSERT INTO Payment \
+                (customer_id, booking_ref, kind, amount, status, provider, charge_id, created_at) \
+             SELECT customer_id, booking_ref, ?, ?, ?, provider, id, NOW() \
+             FROM Payment WHERE id = ?",
+        )
+        .bind(PaymentKind::Refund.as_str())
+        .bind(amount)
+        .bind(PaymentStatus::Pending.as_str())
+        .bind(charge_id)
+        .execute(&mut self.conn)
+        .expect("Error: record payment failed");
+        self.conn.last_insert_id() as u32
+    }
+
+    /// record the answer of the provider to the payment `id`
+    pub fn payment_finished(&mut self, id: u32, result: &Result<String, PaymentError>) {
+        let (transaction_id, error) = match result {
+            Ok(transaction_id) => (Some(transaction_id.clone()), None),
+            Err(e) => (
+                None,
+                Some(e.to_string().chars().take(200).collect::<String>()),
+            ),
+        };
+        Query::new(
+            "UPDATE Payment \
+             SET status = ?, transaction_id = COALESCE(?, transaction_id), last_error = ? \
+             WHERE id = ?",
+        )
+        .bind(PaymentStatus::of(result).as_str())
+        .bind(transaction_id)
+        .bind(error)
+        .bind(id)
+        .execute(&mut self.conn)
+        .expect("Error: record payment failed");
+    }
+
+    /// id and transaction id of the approved charge of `booking_ref`
+    pub fn approved_charge(&mut self, user_id: u32, booking_ref: &str) -> Option<(u32, String)> {
+        Query::new(
+            "SELECT id, transaction_id FROM Payment \
+             WHERE customer_id = ? AND booking_ref = ? AND kind = ? AND status = ? \
+             ORDER BY id DESC",
+        )
+        .bind(user_id)
+        .bind(booking_ref)
+        .bind(PaymentKind::Charge.as_str())
+        .bind(PaymentStatus::Approved.as_str())
+        .first(&mut self.conn)
+        .expect("Error: query payment failed")
+    }
+
+    /// refunds through `provider` that have no answer yet or timed out, with the
+    /// transaction id of the charge they refund and the amount, oldest first
+    pub fn pending_refunds(&mut self, provider: &str) -> Vec<(u32, String, u32)> {
+        Query::new(
+            "SELECT refund.id, charge.transaction_id, refund.amount \
+             FROM Payment AS refund, Payment AS charge \
+             WHERE refund.charge_id = charge.id \
+               AND refund.kind = ? \
+               AND refund.status IN (?, ?) \
+               AND refund.provider = ? \
+             ORDER BY refund.id",
+        )
+        .bind(PaymentKind::Refund.as_str())
+        .bind(PaymentStatus::Pending.as_str())
+        .bind(PaymentStatus::Timeout.as_str())
+        .bind(provider)
+        .map(&mut self.conn, |row: (u32, String, u32)| row)
+        .expect("Error: query payment failed")
+    }
+
+    /// every charge and refund of `booking_ref`, oldest first
+    pub fn payments(&mut self, user_id: u32, booking_ref: &str) -> Vec<Payment> {
+        Query::new(
+            "SELECT id, booking_ref, kind, amount, status, transaction_id, \
+                    DATE_FORMAT(created_at, '%Y-%m-%d %H:%i') \
+             FROM Payment \
+             WHERE customer_id = ? AND booking_ref = ? \
+             ORDER BY id",
+        )
+        .bind(user_id)
+        .bind(booking_ref)
+        .map(&mut self.conn, payment)
+        .expect("Error: query payment failed")
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// let a booking that was never paid for go, see [`expire`]
+    pub fn expire_booking(&mut self, user_id: u32, booking_ref: &str) -> bool {
+        let res = self.retry_on_deadlock(|conn| {
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            expire(&mut tx, user_id, booking_ref)?;
+            tx.commit()
+        });
+        match res {
+            Ok(()) => true,
+            Err(e) => {
+                self.error(t!("error.cancel_failed", e));
+                false
+            }
         }
+    }
 
-        self.conn.exec_drop(
-            "DELETE FROM BookingSystem.Reservation \
-                WHERE res_id = :res_id",
-            params! {
-                "res_id" => res_id
+    /// confirm a held booking once it is paid and give the points it earns,
+    /// `false` when the hold already ran out
+    pub fn confirm_booking(&mut self, user_id: u32, booking_ref: &str) -> bool {
+        self.retry_on_deadlock(|conn| {
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            Query::new(
+                "UPDATE Reservation \
+                 SET status = 'confirmed', hold_until = NULL, status_at = NOW() \
+                 WHERE customer_id = ? AND (booking_ref = ? OR package_ref = ?) AND status = 'held'",
+            )
+            .bind(user_id)
+            .bind(booking_ref)
+            .bind(booking_ref)
+            .execute(&mut tx)?;
+            Query::new(
+                "UPDATE PackageBooking SET status = 'confirmed', status_at = NOW() \
+                 WHERE customer_id = ? AND booking_ref = ? AND status = 'held'",
+            )
+            .bind(user_id)
+            .bind(booking_ref)
+            .execute(&mut tx)?;
+            let confirmed: u32 = Query::new(
+                "SELECT count(*) FROM Reservation \
+                 WHERE customer_id = ? AND (booking_ref = ? OR package_ref = ?) \
+                   AND status = 'confirmed'",
+            )
+            .bind(user_id)
+            .bind(booking_ref)
+            .bind(booking_ref)
+            .first(&mut tx)?
+            .unwrap_or(0);
+            if confirmed > 0 {
+                earn_points(&mut tx, user_id, booking_ref)?;
+            }
+            tx.commit().map(|_| confirmed > 0)
+        })
+        .expect("Error: update reservation failed")
+    }
+
+    /// let every hold that ran out go and mark the reservations whose trip is
+    /// over as completed, return how many bookings expired and how many
+    /// reservations completed
+    pub fn sweep(&mut self) -> (usize, usize) {
+        let expired = self.retry_on_deadlock(|conn| {
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            // 套餐中的各项按套餐的预订号一起过期
+            let bookings: Vec<(u32, String)> = Query::new(
+                "SELECT DISTINCT customer_id, COALESCE(package_ref, booking_ref) \
+                 FROM Reservation \
+                 WHERE status = 'held' AND hold_until <= NOW() \
+                 FOR UPDATE",
+            )
+            .map(&mut tx, |x: (u32, String)| x)?;
+            for (user_id, booking_ref) in bookings.iter() {
+                expire(&mut tx, *user_id, booking_ref)?;
+            }
+            tx.commit().map(|_| bookings.len())
+        });
+
+        let mut completed = 0;
+        for category in CATEGORIES.iter() {
+            // 按天预定的在最后一天结束后才算完成
+            let end = if category.dated {
+                format!("{} + INTERVAL 1 DAY", category.end_column)
+            } else {
+                category.end_column.to_string()
+            };
+            Query::new(format!(
+                "UPDATE Reservation, {table} \
+                 SET Reservation.status = 'completed', Reservation.status_at = NOW() \
+                 WHERE Reservation.status = 'confirmed' \
+                   AND Reservation.res_type = ? \
+                   AND {table}.{key} = Reservation.res_id \
+                   AND {end} <= NOW()",
+                table = category.table,
+                key = category.key_column,
+                end = end
+            ))
+            .bind(category.res_type)
+            .execute(&mut self.conn)
+            .expect("Error: update reservation failed");
+            completed += self.conn.affected_rows() as usize;
+        }
+
+        match expired {
+            Ok(expired) => (expired, completed),
+            Err(e) => {
+                self.error(t!("error.cancel_failed", e));
+                (0, completed)
+            }
+        }
+    }
+
+    /// booking reference of the tour package reservation `id` belongs to
+    fn package_ref(&mut self, user_id: u32, id: u32) -> Option<String> {
+        Query::new("SELECT package_ref FROM Reservation WHERE id = ? AND customer_id = ?")
+            .bind(id)
+            .bind(user_id)
+            .first(&mut self.conn)
+            .expect("Error: query reservation failed")
+            .flatten()
+    }
+
+    // 套餐
+    /// every tour package, ordered by id
+    pub fn packages(&mut self) -> Vec<Package> {
+        let items: Vec<(String, PackageItem)> = Query::new(
+            "SELECT package_id, res_type, res_id FROM PackageItem ORDER BY package_id, res_type, res_id",
+        )
+        .map(
+            &mut self.conn,
+            |(package_id, res_type, res_id): (String, u32, String)| {
+                (package_id, PackageItem::new(res_type, res_id))
             },
-        );
+        )
+        .expect("Error: query package failed");
+        Query::new("SELECT package_id, name, price FROM Package ORDER BY package_id")
+            .map(
+                &mut self.conn,
+                |(package_id, name, price): (String, String, u32)| {
+                    Package::new(package_id, name, price, vec![])
+                },
+            )
+            .expect("Error: query package failed")
+            .into_iter()
+            .map(|mut package| {
+                package.set_items(
+                    items
+                        .iter()
+                        .filter(|(id, _)| id == package.package_id())
+                        .map(|(_, item)| item.clone())
+                        .collect(),
+                );
+                package
+            })
+            .collect()
+    }
 
-        return true;
+    pub fn find_package(&mut self, package_id: &str) -> Option<Package> {
+        let (package_id, name, price): (String, String, u32) =
+            Query::new("SELECT package_id, name, price FROM Package WHERE package_id = ?")
+                .bind(package_id)
+                .first(&mut self.conn)
+                .expect("Error: query package failed")?;
+        let items = Query::new(
+            "SELECT res_type, res_id FROM PackageItem WHERE package_id = ? ORDER BY res_type, res_id",
+        )
+        .bind(&package_id)
+        .map(&mut self.conn, |(res_type, res_id)| {
+            PackageItem::new(res_type, res_id)
+        })
+        .expect("Error: query package failed");
+        Some(Package::new(package_id, name, price, items))
     }
 
-    pub fn cancel_hotel(&mut self, user_id: u32, res_id: String) -> bool {
+    /// tour packages booked by `user_id`
+    pub fn query_packages(&mut self, user_id: u32) -> Vec<PackageBooking> {
         self.user_exist(user_id);
 
-        let res = self.conn.exec_first(
-            "SELECT count(*) \
-                FROM Reservation \
-                WHERE customer_id = :user_id \
-                  AND res_type = 2 \
-                  AND res_id = :res_id",
-            params! {
-                "user_id" => user_id,
-                "res_id" => &res_id
-            },
-        );
+        Query::new(format!(
+            "SELECT booking_ref, customer_id, PackageBooking.package_id, name, PackageBooking.price,
+                    PackageBooking.status
+             FROM PackageBooking, Package
+             WHERE customer_id = ?
+             AND PackageBooking.package_id = Package.package_id
+             AND {}
+             ORDER BY booked_at, booking_ref",
+            ACTIVE_PACKAGE
+        ))
+        .bind(user_id)
+        .map(&mut self.conn, package_booking)
+        .expect("Error: query package failed")
+    }
 
-        if res.unwrap() == Some(0) {
-            eprintln!("未预约对应的酒店");
-            return false;
+    pub fn query_package_by_ref(
+        &mut self,
+        user_id: u32,
+        booking_ref: &str,
+    ) -> Option<PackageBooking> {
+        Query::new(format!(
+            "SELECT booking_ref, customer_id, PackageBooking.package_id, name, PackageBooking.price,
+                    PackageBooking.status
+             FROM PackageBooking, Package
+             WHERE customer_id = ?
+             AND booking_ref = ?
+             AND PackageBooking.package_id = Package.package_id
+             AND {}",
+            ACTIVE_PACKAGE
+        ))
+        .bind(user_id)
+        .bind(booking_ref)
+        .first(&mut self.conn)
+        .expect("Error: query package failed")
+        .map(package_booking)
+    }
+
+    /// refund of cancelling a booked tour package right now.
+    ///
+    /// The package price is quoted with the policy configured for the package
+    /// (`res_type` 0), counting the hours before its earliest item.
+    pub fn package_quote(
+        &mut self,
+        user_id: u32,
+        booking_ref: &str,
+    ) -> Option<(PackageBooking, CancelQuote)> {
+        let booking = self.query_package_by_ref(user_id, booking_ref)?;
+        let mut hours_before: Option<i64> = None;
+        for category in CATEGORIES.iter() {
+            let hours: Option<i64> = Query::new(format!(
+                "SELECT MIN(TIMESTAMPDIFF(HOUR, NOW(), {time}))
+                 FROM {table}, Reservation
+                 WHERE package_ref = ?
+                 AND res_type = ?
+                 AND {table}.{key} = Reservation.res_id",
+                time = category.time_column,
+                table = category.table,
+                key = category.key_column
+            ))
+            .bind(booking_ref)
+            .bind(category.res_type)
+            .first(&mut self.conn)
+            .expect("Error: query package failed")
+            .flatten();
+            hours_before = match (hours_before, hours) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
         }
 
-        self.conn.exec_drop(
-            "DELETE FROM BookingSystem.Reservation \
-                WHERE hotel_num = ':res_id'",
-            params! {
-                "res_id" => res_id
-            },
-        );
+        let policy = self.cancellation_policy(PACKAGE_RES_TYPE, booking.package_id());
+        let quote = policy.quote(booking.price(), hours_before);
+        match booking.status() {
+            ReservationStatus::Held => Some((booking, quote.unpaid())),
+            _ => Some((booking, quote)),
+        }
+    }
+
+    /// cancel a booked tour package with all of its items in one transaction,
+    /// the refund is recorded in `PackageRefund` unless the package was never paid
+    pub fn cancel_package(&mut self, user_id: u32, booking_ref: &str) -> bool {
+        let (booking, quote) = match self.package_quote(user_id, booking_ref) {
+            Some(quoted) => quoted,
+            None => {
+                self.error(t!("error.booking_ref_not_found"));
+                return false;
+            }
+        };
 
-        return true;
+        let res = self.retry_on_deadlock(|conn| {
+            let mut tx = conn.start_transaction(TxOpts::default())?;
+            // 套餐保留为历史记录，退款和积分记录仍指向它
+            Query::new(
+                "UPDATE PackageBooking SET status = 'cancelled', status_at = NOW() \
+                 WHERE booking_ref = ? AND customer_id = ? AND status = ?",
+            )
+            .bind(booking_ref)
+            .bind(user_id)
+            .bind(booking.status().as_str())
+            .execute(&mut tx)?;
+            if tx.affected_rows() != 1 {
+                return tx.rollback().map(|_| false);
+            }
+            let released = live_reservations(&mut tx, user_id, booking_ref)?;
+            Query::new(
+                "UPDATE Reservation \
+                 SET status = 'cancelled', hold_until = NULL, status_at = NOW() \
+                 WHERE package_ref = ? AND customer_id = ? AND status IN ('held', 'confirmed')",
+            )
+            .bind(booking_ref)
+            .bind(user_id)
+            .execute(&mut tx)?;
+            for (res_type, res_id) in released.iter() {
+                give_back(&mut tx, *res_type, res_id)?;
+            }
+            if booking.status() != ReservationStatus::Held {
+                Query::new(
+                    "INSERT INTO PackageRefund \
+                        (booking_ref, customer_id, package_id, price, fee, refund, cancelled_at) \
+                     VALUES (?, ?, ?, ?, ?, ?, NOW())",
+                )
+                .bind(booking_ref)
+                .bind(user_id)
+                .bind(booking.package_id())
+                .bind(quote.price())
+                .bind(quote.fee())
+                .bind(quote.refund())
+                .execute(&mut tx)?;
+            }
+            reverse_points(&mut tx, user_id, booking_ref)?;
+            tx.commit().map(|_| true)
+        });
+
+        match res {
+            Ok(true) => true,
+            Ok(false) => {
+                self.error(t!("error.booking_ref_not_found"));
+                false
+            }
+            Err(e) => {
+                self.error(t!("error.cancel_failed", e));
+                false
+            }
+        }
     }
+
+    /// id of one reservation of `user_id` on the given flight/hotel/bus
+    pub fn reservation_id(&mut self, user_id: u32, res_type: u32, res_id: &str) -> Option<u32> {
+        self.user_exist(user_id);
+
+        self.conn
+            .exec_first(
+                "SELECT id \
+                    FROM Reservation \
+                    WHERE customer_id = :user_id \
+                      AND res_type = :res_type \
+                      AND res_id = :res_id \
+                      AND status IN ('held', 'confirmed') \
+                    ORDER BY package_ref IS NOT NULL, id \
+                    LIMIT 1",
+                params! {
+                    "user_id" => user_id,
+                    "res_type" => res_type,
+                    "res_id" => res_id
+                },
+            )
+            .expect("Error: query reservation failed")
+    }
+}
+
+type ReservationRow = (
+    u32,
+    u32,
+    String,
+    u32,
+    String,
+    Option<String>,
+    Option<String>,
+    String,
+);
+
+type PricingRuleRow = (
+    Option<u32>,
+    Option<u32>,
+    Option<u32>,
+    Option<u32>,
+    Option<u32>,
+    Option<u32>,
+    i32,
+);
+
+type CustomerRow = (
+    String,
+    u32,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
+
+fn customer(
+    (name, id, email, phone, birth_date, id_number, nationality, emergency_contact): CustomerRow,
+) -> Customer {
+    let mut customer = Customer::new(name, id);
+    customer.set_email(email);
+    customer.set_phone(phone);
+    customer.set_birth_date(birth_date.and_then(|x| x.parse().ok()));
+    customer.set_id_number(id_number);
+    customer.set_nationality(nationality);
+    customer.set_emergency_contact(emergency_contact);
+    customer
+}
+
+fn revenue_row(name: String, bookings: u64, revenue: u64) -> Vec<String> {
+    vec![name, bookings.to_string(), revenue.to_string()]
+}
+
+/// add up the bookings and revenue of the same group, ordered by the group
+fn merge_groups(groups: Vec<(String, u64, u64)>) -> Vec<(String, u64, u64)> {
+    let mut merged: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for (group, bookings, revenue) in groups {
+        let sum = merged.entry(group).or_default();
+        sum.0 += bookings;
+        sum.1 += revenue;
+    }
+    merged
+        .into_iter()
+        .map(|(group, (bookings, revenue))| (group, bookings, revenue))
+        .collect()
+}
+
+/// `condition` on the `Reservation` table written for the alias `alias`
+fn aliased(condition: &str, alias: &str) -> String {
+    condition.replace("Reservation.", &format!("{}.", alias))
+}
+
+/// match `%` and `_` typed by the user literally in a `LIKE` pattern
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+type PackageBookingRow = (String, u32, String, String, u32, String);
+
+fn package_booking(
+    (booking_ref, customer, package_id, name, price, status): PackageBookingRow,
+) -> PackageBooking {
+    let mut booking = PackageBooking::new(booking_ref, customer, package_id, name, price);
+    if let Some(status) = ReservationStatus::parse(&status) {
+        booking.set_status(status);
+    }
+    booking
+}
+
+fn itinerary_item(category: &'static Category, mut row: mysql::Row) -> ItineraryItem {
+    let time = |x: Option<String>| x.and_then(|x| x.parse().ok());
+    ItineraryItem::new(
+        row.take(0).unwrap_or_default(),
+        category,
+        row.take(1).unwrap_or_default(),
+        row.take(6).unwrap_or_default(),
+        row.take::<Option<String>, _>(7).flatten(),
+        time(row.take(4).flatten()),
+        time(row.take(5).flatten()),
+        row.take(2).unwrap_or_default(),
+        row.take(3).flatten(),
+    )
+}
+
+fn reservation(
+    (customer, res_type, res_id, id, booking_ref, start, end, status): ReservationRow,
+) -> Reservation {
+    let mut reservation = Reservation::new(
+        customer,
+        res_type,
+        res_id,
+        id,
+        booking_ref,
+        Period::from_columns(start, end),
+    );
+    if let Some(status) = ReservationStatus::parse(&status) {
+        reservation.set_status(status);
+    }
+    reservation
+}
+
+/// keep a new booking held for `minutes`, it stays confirmed and earns its
+/// points right away when `minutes` is `None`
+fn hold(
+    tx: &mut Transaction,
+    user_id: u32,
+    booking_ref: &str,
+    minutes: Option<u32>,
+) -> Result<(), Error> {
+    let minutes = match minutes {
+        Some(minutes) => minutes,
+        None => return earn_points(tx, user_id, booking_ref),
+    };
+    Query::new(
+        "UPDATE Reservation \
+         SET status = 'held', hold_until = NOW() + INTERVAL ? MINUTE, status_at = NOW() \
+         WHERE booking_ref = ? OR package_ref = ?",
+    )
+    .bind(minutes)
+    .bind(booking_ref)
+    .bind(booking_ref)
+    .execute(tx)?;
+    Query::new(
+        "UPDATE PackageBooking SET status = 'held', status_at = NOW() \
+         WHERE customer_id = ? AND booking_ref = ?",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .execute(tx)
+}
+
+/// let a booking that was never paid for go, the seats/rooms are given back, a
+/// package booking is marked expired and the points it earned or used are
+/// reversed in the ledger like a cancellation
+fn expire(tx: &mut Transaction, user_id: u32, booking_ref: &str) -> Result<(), Error> {
+    let released = live_reservations(tx, user_id, booking_ref)?;
+    Query::new(
+        "UPDATE Reservation \
+         SET status = 'expired', hold_until = NULL, status_at = NOW() \
+         WHERE customer_id = ? AND (booking_ref = ? OR package_ref = ?) \
+           AND status IN ('held', 'confirmed')",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .bind(booking_ref)
+    .execute(tx)?;
+    Query::new(
+        "UPDATE PackageBooking SET status = 'expired', status_at = NOW() \
+         WHERE customer_id = ? AND booking_ref = ? AND status IN ('held', 'confirmed')",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .execute(tx)?;
+    for (res_type, res_id) in released.iter() {
+        give_back(tx, *res_type, res_id)?;
+    }
+    reverse_points(tx, user_id, booking_ref)
+}
+
+/// `(res_type, res_id)` of the held or confirmed reservations of a booking or
+/// of the items of a package booking, locked until `tx` ends
+fn live_reservations(
+    tx: &mut Transaction,
+    user_id: u32,
+    booking_ref: &str,
+) -> Result<Vec<(u32, String)>, Error> {
+    Query::new(
+        "SELECT res_type, res_id FROM Reservation \
+         WHERE customer_id = ? AND (booking_ref = ? OR package_ref = ?) \
+           AND status IN ('held', 'confirmed') \
+         FOR UPDATE",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .bind(booking_ref)
+    .map(tx, |x: (u32, String)| x)
+}
+
+/// give back what a cancelled or expired reservation took of the item, dated
+/// categories count the overlapping periods instead and have nothing to give back
+fn give_back(tx: &mut Transaction, res_type: u32, res_id: &str) -> Result<(), Error> {
+    match category(res_type) {
+        Some(category) if !category.dated => Query::new(format!(
+            "UPDATE {} SET num_available = num_available + 1 WHERE {} = ?",
+            category.table, category.key_column
+        ))
+        .bind(res_id)
+        .execute(tx),
+        _ => Ok(()),
+    }
+}
+
+/// lock the item and add one reservation of it within `tx`, the caller commits.
+///
+/// The item row is locked with `SELECT ... FOR UPDATE` while the reservations
+/// on it are counted, dated items only count the reservations overlapping
+/// `period`.
+fn reserve_item(
+    tx: &mut Transaction,
+    category: &Category,
+    user_id: u32,
+    res_id: &str,
+    period: Option<Period>,
+    package_ref: Option<&str>,
+    redeem: bool,
+) -> Result<Booking, Error> {
+    // 不按日期预定的类别不记录日期
+    let period = period.filter(|_| category.dated);
+    let (start, end) = match period {
+        Some(period) => (
+            Some(period.start().to_string()),
+            Some(period.end().to_string()),
+        ),
+        None => (None, None),
+    };
+
+    // 按天预定的以开始日期为出发日期
+    let (capacity, base, departure): (u32, u32, Option<String>) = match Query::new(format!(
+        "SELECT {capacity}, price, DATE_FORMAT({time}, '%Y-%m-%d')
+         FROM {table} WHERE {key} = ? FOR UPDATE",
+        capacity = category.capacity_column,
+        time = if category.dated {
+            "NULL"
+        } else {
+            category.time_column
+        },
+        table = category.table,
+        key = category.key_column
+    ))
+    .bind(res_id)
+    .first(tx)?
+    {
+        Some(row) => row,
+        None => return Ok(Booking::NotFound),
+    };
+    let mut booked = Query::new(format!(
+        "SELECT count(*) FROM Reservation WHERE res_type = ? AND res_id = ? AND {}",
+        ACTIVE_RESERVATION
+    ))
+    .bind(category.res_type)
+    .bind(res_id);
+    // 还车当天不算占用，和按天收费一致
+    if let Some(period) = period {
+        booked = booked
+            .push(&format!(" AND start_date < ? AND {} > ?", RETURNED))
+            .bind(period.returned().to_string())
+            .bind(period.start().to_string());
+    }
+    let booked: u32 = booked.first(tx)?.unwrap_or(0);
+    if booked >= capacity {
+        return Ok(Booking::SoldOut);
+    }
+
+    // 套餐中的项目按套餐价格收费，不单独报价，也不单独积分
+    let (quoted_price, used) = match package_ref {
+        Some(_) => (None, None),
+        None => {
+            let departure = match period {
+                Some(period) => Some(period.start()),
+                None => departure.and_then(|x| x.parse().ok()),
+            };
+            let demand = Demand::new(booked, capacity, departure, Date::today());
+            let rules = pricing_rules(tx, category.res_type)?;
+            let days = period.filter(|_| category.dated).map_or(1, |x| x.days());
+            // 租期太长时总价会超出范围，不能预定
+            let price =
+                match pricing::quote(base, &rules, category.res_type, &demand).checked_mul(days) {
+                    Some(price) => price,
+                    None => return Ok(Booking::TooExpensive),
+                };
+            let (price, used) = loyalty_discount(tx, user_id, price, redeem)?;
+            (Some(price), Some(used))
+        }
+    };
+
+    let booking_ref = insert_with_booking_ref(tx, |booking_ref| {
+        Query::new(
+            "INSERT INTO Reservation \
+                (customer_id, res_type, res_id, id, booking_ref, start_date, end_date, package_ref, \
+                 quoted_price) \
+             VALUES (?, ?, ?, null, ?, ?, ?, ?, ?)",
+        )
+        .bind(user_id)
+        .bind(category.res_type)
+        .bind(res_id)
+        .bind(booking_ref)
+        .bind(&start)
+        .bind(&end)
+        .bind(package_ref)
+        .bind(quoted_price)
+    })?;
+    if let (Some(booking_ref), Some(used)) = (&booking_ref, used) {
+        redeem_points(tx, user_id, booking_ref, used)?;
+    }
+    Ok(booking_ref.map_or(Booking::RefExhausted, Booking::Booked))
+}
+
+type PaymentRow = (u32, String, String, u32, String, Option<String>, String);
+
+fn payment(
+    (id, booking_ref, kind, amount, status, transaction_id, created_at): PaymentRow,
+) -> Option<Payment> {
+    Some(Payment::new(
+        id,
+        booking_ref,
+        PaymentKind::parse(&kind)?,
+        amount,
+        PaymentStatus::parse(&status)?,
+        transaction_id,
+        created_at,
+    ))
+}
+
+/// the points of `user_id`
+fn loyalty_account<C: Queryable>(conn: &mut C, user_id: u32) -> Result<LoyaltyAccount, Error> {
+    let (balance, earned): (i64, i64) = Query::new(
+        "SELECT CAST(COALESCE(SUM(points), 0) AS SIGNED),
+                CAST(COALESCE(SUM(CASE WHEN kind IN ('earn', 'reverse') THEN points END), 0) AS SIGNED)
+         FROM LoyaltyLedger
+         WHERE customer_id = ?",
+    )
+    .bind(user_id)
+    .first(conn)?
+    .unwrap_or_default();
+    Ok(LoyaltyAccount::new(balance, earned))
+}
+
+/// what is left to pay of `price` after using the points of `user_id` if
+/// `redeem` is set, with the points used.
+///
+/// The customer row is locked first, so two bookings at the same time can not
+/// spend the same points twice.
+fn loyalty_discount(
+    tx: &mut Transaction,
+    user_id: u32,
+    price: u32,
+    redeem: bool,
+) -> Result<(u32, u32), Error> {
+    Query::new("SELECT id FROM Customer WHERE id = ? FOR UPDATE")
+        .bind(user_id)
+        .first::<_, u32>(tx)?;
+    let account = loyalty_account(tx, user_id)?;
+    let (discount, used) = if redeem {
+        account.redemption(price)
+    } else {
+        (0, 0)
+    };
+    Ok((price - discount, used))
+}
+
+/// write the points used by a new booking into the ledger
+fn redeem_points(
+    tx: &mut Transaction,
+    user_id: u32,
+    booking_ref: &str,
+    used: u32,
+) -> Result<(), Error> {
+    if used == 0 {
+        return Ok(());
+    }
+    add_points(
+        tx,
+        user_id,
+        Some(booking_ref),
+        PointsKind::Redeem,
+        -i64::from(used),
+    )
+}
+
+/// give the points a confirmed booking earns by the price paid for it, once.
+///
+/// A booking is either a reservation with its own price or a package booking,
+/// the items of a package do not earn points on their own.
+fn earn_points(tx: &mut Transaction, user_id: u32, booking_ref: &str) -> Result<(), Error> {
+    let earned: u32 = Query::new(
+        "SELECT count(*) FROM LoyaltyLedger \
+         WHERE customer_id = ? AND booking_ref = ? AND kind = 'earn'",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .first(tx)?
+    .unwrap_or(0);
+    if earned > 0 {
+        return Ok(());
+    }
+    let paid: Option<u32> = Query::new(
+        "SELECT quoted_price FROM Reservation \
+         WHERE customer_id = ? AND booking_ref = ? AND quoted_price IS NOT NULL \
+         UNION ALL \
+         SELECT price FROM PackageBooking WHERE customer_id = ? AND booking_ref = ?",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .bind(user_id)
+    .bind(booking_ref)
+    .first(tx)?;
+    let points = match paid {
+        Some(paid) => loyalty_account(tx, user_id)?.tier().earn(paid),
+        None => return Ok(()),
+    };
+    if points == 0 {
+        return Ok(());
+    }
+    add_points(
+        tx,
+        user_id,
+        Some(booking_ref),
+        PointsKind::Earn,
+        // 不超过价格的两倍，不会超出 i64
+        points as i64,
+    )
+}
+
+/// take back the points earned by a cancelled booking and give back the points it used
+fn reverse_points(tx: &mut Transaction, user_id: u32, booking_ref: &str) -> Result<(), Error> {
+    let (earned, used): (i64, i64) = Query::new(
+        "SELECT CAST(COALESCE(SUM(CASE WHEN kind IN ('earn', 'reverse') THEN points END), 0) AS SIGNED),
+                CAST(COALESCE(-SUM(CASE WHEN kind IN ('redeem', 'restore') THEN points END), 0) AS SIGNED)
+         FROM LoyaltyLedger
+         WHERE customer_id = ? AND booking_ref = ?",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .first(tx)?
+    .unwrap_or_default();
+    if earned > 0 {
+        add_points(tx, user_id, Some(booking_ref), PointsKind::Reverse, -earned)?;
+    }
+    if used > 0 {
+        add_points(tx, user_id, Some(booking_ref), PointsKind::Restore, used)?;
+    }
+    Ok(())
+}
+
+fn add_points(
+    tx: &mut Transaction,
+    user_id: u32,
+    booking_ref: Option<&str>,
+    kind: PointsKind,
+    points: i64,
+) -> Result<(), Error> {
+    Query::new(
+        "INSERT INTO LoyaltyLedger (customer_id, booking_ref, kind, points, created_at) \
+         VALUES (?, ?, ?, ?, NOW())",
+    )
+    .bind(user_id)
+    .bind(booking_ref)
+    .bind(kind.as_str())
+    .bind(points)
+    .execute(tx)
+}
+
+/// the pricing rules that apply to `res_type`
+fn pricing_rules<C: Queryable>(conn: &mut C, res_type: u32) -> Result<Vec<PricingRule>, Error> {
+    Query::new(format!(
+        "SELECT {} FROM PricingRule WHERE res_type IS NULL OR res_type = ? ORDER BY id",
+        PRICING_RULE_COLUMNS
+    ))
+    .bind(res_type)
+    .map(conn, pricing_rule)
+}
+
+fn pricing_rule(
+    (res_type, min_load, max_load, min_days, max_days, weekday, adjust_percent): PricingRuleRow,
+) -> PricingRule {
+    let mut rule = PricingRule::new(adjust_percent);
+    rule.set_res_type(res_type);
+    rule.set_load(min_load, max_load);
+    rule.set_days_before(min_days, max_days);
+    rule.set_weekday(weekday);
+    rule
+}
+
+/// run the insert built by `insert` with a new booking reference until one is
+/// not taken yet, `None` if every try collided
+fn insert_with_booking_ref(
+    tx: &mut Transaction,
+    insert: impl Fn(&str) -> Query,
+) -> Result<Option<String>, Error> {
+    for _ in 0..BOOKING_REF_RETRY {
+        let booking_ref = generate_booking_ref();
+        match insert(&booking_ref).execute(tx) {
+            Ok(()) => return Ok(Some(booking_ref)),
+            Err(Error::MySqlError(ref e)) if e.code == DUPLICATE_ENTRY => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+fn is_deadlock(e: &Error) -> bool {
+    matches!(e, Error::MySqlError(e) if e.code == DEADLOCK || e.code == LOCK_WAIT_TIMEOUT)
 }
@@ -0,0 +1,291 @@
+//! Confirmation emails for bookings and cancellations.
+//!
+//! Messages are rendered from the templates in `templates/<locale>/` and put in
+//! the `Notification` table first, then every pending message is handed to a
+//! [`Transport`]. A message that fails to go out stays in the table and is
+//! tried again later, so nothing is lost when the mail server is down.
+
+use crate::config::Config;
+use crate::i18n::{locale, Locale};
+use crate::mysql_connection::MySQLConnection;
+use std::fmt::Display;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_SMTP_SERVER: &str = "127.0.0.1:25";
+pub const DEFAULT_MAIL_FROM: &str = "noreply@localhost";
+pub const DEFAULT_OUTBOX: &str = "outbox";
+/// a message is given up after this many failed deliveries
+pub const NOTIFY_RETRY: u32 = 10;
+
+const SMTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// an email ready to be delivered
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    to: String,
+    subject: String,
+    body: String,
+}
+
+impl Message {
+    pub fn new(to: String, subject: String, body: String) -> Self {
+        Message { to, subject, body }
+    }
+
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    /// the message in internet mail format, lines end with CRLF
+    pub fn format(&self, from: &str) -> String {
+        let mut mail = format!(
+            "From: <{}>\r\nTo: <{}>\r\nSubject: {}\r\nMIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=UTF-8\r\nContent-Transfer-Encoding: 8bit\r\n\r\n",
+            from,
+            self.to,
+            encode_header(&self.subject)
+        );
+        for line in self.body.lines() {
+            mail.push_str(line);
+            mail.push_str("\r\n");
+        }
+        mail
+    }
+}
+
+/// the messages sent to customers, one template file per locale
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Template {
+    /// `{name}`, `{booking_ref}`, `{item}`, `{price}`
+    BookingConfirmed,
+    /// `{name}`, `{booking_ref}`, `{item}`, `{fee}`, `{refund}`
+    BookingCancelled,
+}
+
+impl Template {
+    fn source(&self, locale: Locale) -> &'static str {
+        match (self, locale) {
+            (Template::BookingConfirmed, Locale::ZhCn) => {
+                include_str!("../templates/zh-CN/booking_confirmed.txt")
+            }
+            (Template::BookingConfirmed, Locale::En) => {
+                include_str!("../templates/en/booking_confirmed.txt")
+            }
+            (Template::BookingCancelled, Locale::ZhCn) => {
+                include_str!("../templates/zh-CN/booking_cancelled.txt")
+            }
+            (Template::BookingCancelled, Locale::En) => {
+                include_str!("../templates/en/booking_cancelled.txt")
+            }
+        }
+    }
+
+    /// fill in the template of the current locale.
+    ///
+    /// The first line of a template is `Subject: ...`, the body follows the
+    /// blank line after it. Every `{key}` of `vars` is replaced in both.
+    pub fn render(&self, to: &str, vars: &[(&str, &dyn Display)]) -> Message {
+        let mut text = self.source(locale()).to_string();
+        for (key, value) in vars {
+            text = text.replace(&format!("{{{}}}", key), &value.to_string());
+        }
+        let (head, body) = text.split_once('\n').unwrap_or((&text, ""));
+        let subject = head.trim().strip_prefix("Subject:").unwrap_or(head).trim();
+        Message::new(
+            to.to_string(),
+            subject.to_string(),
+            body.trim_start_matches(['\r', '\n']).to_string(),
+        )
+    }
+}
+
+/// where messages are delivered to
+pub trait Transport {
+    fn send(&mut self, message: &Message) -> io::Result<()>;
+}
+
+/// plain SMTP without authentication, e.g. a local relay or a test sink
+pub struct Smtp {
+    server: String,
+    from: String,
+}
+
+impl Smtp {
+    pub fn new(server: String, from: String) -> Self {
+        Smtp { server, from }
+    }
+}
+
+impl Transport for Smtp {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        let mut stream = TcpStream::connect(&self.server)?;
+        stream.set_read_timeout(Some(SMTP_TIMEOUT))?;
+        stream.set_write_timeout(Some(SMTP_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        reply(&mut reader, 220)?;
+        command(&mut stream, &mut reader, "EHLO localhost", 250)?;
+        command(
+            &mut stream,
+            &mut reader,
+            &format!("MAIL FROM:<{}>", self.from),
+            250,
+        )?;
+        command(
+            &mut stream,
+            &mut reader,
+            &format!("RCPT TO:<{}>", message.to()),
+            250,
+        )?;
+        command(&mut stream, &mut reader, "DATA", 354)?;
+        // 以 . 开头的行要多写一个 . ，否则会被当作结束标记
+        let mut data = String::new();
+        for line in message.format(&self.from).lines() {
+            if line.starts_with('.') {
+                data.push('.');
+            }
+            data.push_str(line);
+            data.push_str("\r\n");
+        }
+        stream.write_all(data.as_bytes())?;
+        command(&mut stream, &mut reader, ".", 250)?;
+        command(&mut stream, &mut reader, "QUIT", 221)
+    }
+}
+
+/// send one SMTP command and check the reply code
+fn command(
+    stream: &mut TcpStream,
+    reader: &mut impl BufRead,
+    line: &str,
+    expected: u16,
+) -> io::Result<()> {
+    stream.write_all(format!("{}\r\n", line).as_bytes())?;
+    stream.flush()?;
+    reply(reader, expected)
+}
+
+/// read a possibly multi-line reply, e.g. `250-...` lines ending with `250 ...`
+fn reply(reader: &mut impl BufRead, expected: u16) -> io::Result<()> {
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "SMTP server closed the connection",
+            ));
+        }
+        let code = line.get(..3).and_then(|x| x.parse::<u16>().ok());
+        if code != Some(expected) {
+            return Err(io::Error::other(format!("SMTP: {}", line.trim_end())));
+        }
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok(());
+        }
+    }
+}
+
+/// write every message into its own `.eml` file of a directory
+pub struct Outbox {
+    dir: PathBuf,
+    from: String,
+}
+
+impl Outbox {
+    pub fn new(dir: impl Into<PathBuf>, from: String) -> Self {
+        Outbox {
+            dir: dir.into(),
+            from,
+        }
+    }
+}
+
+impl Transport for Outbox {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path = self.dir.join(format!("{}.eml", nanos));
+        // 不覆盖已有的邮件，重名时留到下次重试
+        OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)?
+            .write_all(message.format(&self.from).as_bytes())
+    }
+}
+
+/// the transport chosen by `notify = smtp | outbox` in the config file,
+/// `None` when notifications are off
+pub fn transport(config: &Config) -> Option<Box<dyn Transport>> {
+    let from = config
+        .get("mail_from")
+        .unwrap_or(DEFAULT_MAIL_FROM)
+        .to_string();
+    match config.get("notify") {
+        Some("smtp") => {
+            let server = config.get("smtp_server").unwrap_or(DEFAULT_SMTP_SERVER);
+            Some(Box::new(Smtp::new(server.to_string(), from)))
+        }
+        Some("outbox") => {
+            let dir = config.get("outbox").unwrap_or(DEFAULT_OUTBOX);
+            Some(Box::new(Outbox::new(dir, from)))
+        }
+        _ => None,
+    }
+}
+
+/// hand every pending message of the queue to `transport`, return how many went out
+pub fn deliver(connection: &mut MySQLConnection, transport: &mut dyn Transport) -> usize {
+    let mut sent = 0;
+    for (id, message) in connection.pending_notifications() {
+        match transport.send(&message) {
+            Ok(()) => {
+                connection.notification_sent(id);
+                sent += 1;
+            }
+            Err(e) => connection.notification_failed(id, &e.to_string()),
+        }
+    }
+    sent
+}
+
+// 非 ASCII 的标题按 RFC 2047 编码
+fn encode_header(text: &str) -> String {
+    if text.is_ascii() {
+        text.to_string()
+    } else {
+        format!("=?UTF-8?B?{}?=", base64(text.as_bytes()))
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, x)| n | (u32::from(*x) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(CHARS[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
@@ -0,0 +1,168 @@
+use crate::bookable::{category, Category};
+use crate::reservation::ReservationStatus;
+use crate::{t, ToRow};
+use prettytable::{Cell, Row};
+
+/// `CancellationPolicy.res_type` of tour packages, `res_id` is the package id
+pub const PACKAGE_RES_TYPE: u32 = 0;
+
+/// one flight/hotel/bus of a tour package
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageItem {
+    res_type: u32,
+    res_id: String,
+}
+
+impl PackageItem {
+    pub fn new(res_type: u32, res_id: String) -> Self {
+        PackageItem { res_type, res_id }
+    }
+
+    pub fn res_type(&self) -> u32 {
+        self.res_type
+    }
+    pub fn res_id(&self) -> &str {
+        &self.res_id
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        category(self.res_type)
+            .map(Category::name)
+            .unwrap_or_else(|| t!("type.unknown"))
+    }
+}
+
+/// flights, hotels and buses sold together at one price, defined by the admins
+/// in `Package` and `PackageItem`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Package {
+    package_id: String,
+    name: String,
+    price: u32,
+    items: Vec<PackageItem>,
+}
+
+impl Package {
+    pub fn new(package_id: String, name: String, price: u32, items: Vec<PackageItem>) -> Self {
+        Package {
+            package_id,
+            name,
+            price,
+            items,
+        }
+    }
+
+    pub fn package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn price(&self) -> u32 {
+        self.price
+    }
+    pub fn items(&self) -> &[PackageItem] {
+        &self.items
+    }
+
+    pub fn set_package_id(&mut self, package_id: String) {
+        self.package_id = package_id;
+    }
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+    pub fn set_price(&mut self, price: u32) {
+        self.price = price;
+    }
+    pub fn set_items(&mut self, items: Vec<PackageItem>) {
+        self.items = items;
+    }
+
+    /// what the package contains, e.g. `航班 F1, 酒店 H1`
+    pub fn content(&self) -> String {
+        self.items
+            .iter()
+            .map(|x| format!("{} {}", x.type_name(), x.res_id()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl ToRow for Package {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(self.package_id()),
+            Cell::new(self.name()),
+            Cell::new(&self.price().to_string()),
+            Cell::new(&self.content()),
+        ])
+    }
+}
+
+/// a tour package booked by a customer, its items are reservations sharing
+/// the booking reference in `Reservation.package_ref`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackageBooking {
+    booking_ref: String,
+    customer: u32,
+    package_id: String,
+    name: String,
+    /// package price at the time of booking
+    price: u32,
+    /// held until it is paid, like the reservations of its items
+    status: ReservationStatus,
+}
+
+impl PackageBooking {
+    pub fn new(
+        booking_ref: String,
+        customer: u32,
+        package_id: String,
+        name: String,
+        price: u32,
+    ) -> Self {
+        PackageBooking {
+            booking_ref,
+            customer,
+            package_id,
+            name,
+            price,
+            status: ReservationStatus::Confirmed,
+        }
+    }
+
+    pub fn set_status(&mut self, status: ReservationStatus) {
+        self.status = status;
+    }
+
+    pub fn booking_ref(&self) -> &str {
+        &self.booking_ref
+    }
+    pub fn customer(&self) -> u32 {
+        self.customer
+    }
+    pub fn package_id(&self) -> &str {
+        &self.package_id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn price(&self) -> u32 {
+        self.price
+    }
+    pub fn status(&self) -> ReservationStatus {
+        self.status
+    }
+}
+
+impl ToRow for PackageBooking {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(self.booking_ref()),
+            Cell::new(self.package_id()),
+            Cell::new(self.name()),
+            Cell::new(&self.price().to_string()),
+            Cell::new(self.status.label()),
+        ])
+    }
+}
@@ -0,0 +1,271 @@
+//! Payments for bookings.
+//!
+//! A booking is charged through a [`Gateway`] right after it is made, and expires
+//! right away if the charge does not go through. Cancelling a paid booking
+//! refunds what the cancellation policy gives back through the same gateway.
+//! Every charge and refund is recorded in the `Payment` table; a refund that
+//! timed out is tried again the next time the program starts.
+
+use crate::config::Config;
+use crate::t;
+use crate::ToRow;
+use prettytable::{Cell, Row};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// why a charge or refund did not go through
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PaymentError {
+    /// the provider said no, trying again will not help
+    Declined(String),
+    /// no answer from the provider, the payment may be tried again
+    Timeout,
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PaymentError::Declined(reason) => write!(f, "{}", reason),
+            PaymentError::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+/// a payment provider, the `Ok` value is the provider's transaction id
+pub trait Gateway {
+    /// name of the provider recorded with each payment
+    fn name(&self) -> &str;
+    /// charge `amount` for the booking `booking_ref`
+    fn charge(&mut self, booking_ref: &str, amount: u32) -> Result<String, PaymentError>;
+    /// give back `amount` of the charge `transaction_id`
+    fn refund(&mut self, transaction_id: &str, amount: u32) -> Result<String, PaymentError>;
+}
+
+/// what the mock provider answers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockOutcome {
+    Approve,
+    Decline,
+    Timeout,
+}
+
+impl MockOutcome {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "approve" => Some(MockOutcome::Approve),
+            "decline" => Some(MockOutcome::Decline),
+            "timeout" => Some(MockOutcome::Timeout),
+            _ => None,
+        }
+    }
+}
+
+/// a provider for local testing that answers every request with the same outcome
+pub struct MockGateway {
+    outcome: MockOutcome,
+    count: u32,
+}
+
+impl MockGateway {
+    pub fn new(outcome: MockOutcome) -> Self {
+        MockGateway { outcome, count: 0 }
+    }
+
+    pub fn set_outcome(&mut self, outcome: MockOutcome) {
+        self.outcome = outcome;
+    }
+
+    fn answer(&mut self, prefix: &str) -> Result<String, PaymentError> {
+        match self.outcome {
+            MockOutcome::Approve => {
+                // 进程内计数加上时间，重启后也不会重复
+                self.count += 1;
+                let nanos = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_nanos())
+                    .unwrap_or_default();
+                Ok(format!("mock_{}_{}_{}", prefix, nanos, self.count))
+            }
+            MockOutcome::Decline => Err(PaymentError::Declined("card declined".to_string())),
+            MockOutcome::Timeout => Err(PaymentError::Timeout),
+        }
+    }
+}
+
+impl Gateway for MockGateway {
+    fn name(&self) -> &str {
+        "mock"
+    }
+
+    fn charge(&mut self, _booking_ref: &str, _amount: u32) -> Result<String, PaymentError> {
+        self.answer("ch")
+    }
+
+    fn refund(&mut self, _transaction_id: &str, _amount: u32) -> Result<String, PaymentError> {
+        self.answer("re")
+    }
+}
+
+/// the gateway chosen by `payment = mock` in the config file, `None` when
+/// bookings are confirmed without payment.
+///
+/// `payment_mock = approve | decline | timeout` sets what the mock answers.
+pub fn gateway(config: &Config) -> Option<Box<dyn Gateway>> {
+    match config.get("payment") {
+        Some("mock") => {
+            let outcome = config
+                .get("payment_mock")
+                .and_then(MockOutcome::parse)
+                .unwrap_or(MockOutcome::Approve);
+            Some(Box::new(MockGateway::new(outcome)))
+        }
+        _ => None,
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaymentKind {
+    Charge,
+    Refund,
+}
+
+impl PaymentKind {
+    /// value of `Payment.kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentKind::Charge => "charge",
+            PaymentKind::Refund => "refund",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "charge" => Some(PaymentKind::Charge),
+            "refund" => Some(PaymentKind::Refund),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaymentKind::Charge => t!("payment.charge"),
+            PaymentKind::Refund => t!("payment.refund"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaymentStatus {
+    /// sent to the provider, no answer recorded yet
+    Pending,
+    Approved,
+    Declined,
+    Timeout,
+}
+
+impl PaymentStatus {
+    /// value of `Payment.status`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PaymentStatus::Pending => "pending",
+            PaymentStatus::Approved => "approved",
+            PaymentStatus::Declined => "declined",
+            PaymentStatus::Timeout => "timeout",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pending" => Some(PaymentStatus::Pending),
+            "approved" => Some(PaymentStatus::Approved),
+            "declined" => Some(PaymentStatus::Declined),
+            "timeout" => Some(PaymentStatus::Timeout),
+            _ => None,
+        }
+    }
+
+    /// the status a gateway answer is recorded with
+    pub fn of<T>(result: &Result<T, PaymentError>) -> Self {
+        match result {
+            Ok(_) => PaymentStatus::Approved,
+            Err(PaymentError::Declined(_)) => PaymentStatus::Declined,
+            Err(PaymentError::Timeout) => PaymentStatus::Timeout,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PaymentStatus::Pending => t!("payment.pending"),
+            PaymentStatus::Approved => t!("payment.approved"),
+            PaymentStatus::Declined => t!("payment.declined"),
+            PaymentStatus::Timeout => t!("payment.timeout"),
+        }
+    }
+}
+
+/// a row of the `Payment` table
+pub struct Payment {
+    id: u32,
+    booking_ref: String,
+    kind: PaymentKind,
+    amount: u32,
+    status: PaymentStatus,
+    transaction_id: Option<String>,
+    created_at: String,
+}
+
+impl Payment {
+    pub fn new(
+        id: u32,
+        booking_ref: String,
+        kind: PaymentKind,
+        amount: u32,
+        status: PaymentStatus,
+        transaction_id: Option<String>,
+        created_at: String,
+    ) -> Self {
+        Payment {
+            id,
+            booking_ref,
+            kind,
+            amount,
+            status,
+            transaction_id,
+            created_at,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+    pub fn booking_ref(&self) -> &str {
+        &self.booking_ref
+    }
+    pub fn kind(&self) -> PaymentKind {
+        self.kind
+    }
+    pub fn amount(&self) -> u32 {
+        self.amount
+    }
+    pub fn status(&self) -> PaymentStatus {
+        self.status
+    }
+    pub fn transaction_id(&self) -> Option<&str> {
+        self.transaction_id.as_deref()
+    }
+    pub fn created_at(&self) -> &str {
+        &self.created_at
+    }
+}
+
+impl ToRow for Payment {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(&self.created_at),
+            Cell::new(self.kind.label()),
+            Cell::new(&self.amount.to_string()),
+            Cell::new(self.status.label()),
+            Cell::new(self.transaction_id().unwrap_or_default()),
+        ])
+    }
+}
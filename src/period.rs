@@ -0,0 +1,164 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// a calendar date typed as `YYYY-MM-DD`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    year: u32,
+    month: u32,
+    day: u32,
+}
+
+impl Date {
+    pub fn new(year: u32, month: u32, day: u32) -> Option<Self> {
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return None;
+        }
+        Some(Date { year, month, day })
+    }
+
+    /// the current date in UTC
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Date::from_days((secs / 86_400) as i64)
+    }
+
+    pub fn year(&self) -> u32 {
+        self.year
+    }
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    /// the date `days` days later, earlier if negative
+    pub fn add_days(&self, days: i64) -> Self {
+        Date::from_days(self.days() + days)
+    }
+
+    /// days from `earlier` to this date, negative if `earlier` is later
+    pub fn days_since(&self, earlier: Date) -> i64 {
+        self.days() - earlier.days()
+    }
+
+    /// day of the week, 1 is Monday and 7 is Sunday
+    pub fn weekday(&self) -> u32 {
+        // 1970-01-01 是星期四
+        ((self.days() + 3).rem_euclid(7) + 1) as u32
+    }
+
+    /// days since 1970-01-01
+    fn days(&self) -> i64 {
+        // 按三月为一年之始计算，闰日落在年末
+        let year = i64::from(self.year) - i64::from(self.month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month = i64::from(self.month);
+        let day_of_year =
+            (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(self.day) - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    /// inverse of [`Date::days`]
+    fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days - era * 146_097;
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month + 2) / 5 + 1;
+        let month = if month < 10 { month + 3 } else { month - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+        Date {
+            year: year as u32,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(3, '-').map(|x| x.parse::<u32>());
+        match (parts.next(), parts.next(), parts.next()) {
+            (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) => {
+                Date::new(year, month, day).ok_or(())
+            }
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// the days a reservation covers, e.g. a car rental
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Period {
+    start: Date,
+    end: Date,
+}
+
+impl Period {
+    /// `None` if `end` is before `start`
+    pub fn new(start: Date, end: Date) -> Option<Self> {
+        if end < start {
+            return None;
+        }
+        Some(Period { start, end })
+    }
+
+    /// read back from the `start_date`/`end_date` columns of a reservation
+    pub fn from_columns(start: Option<String>, end: Option<String>) -> Option<Self> {
+        Period::new(start?.parse().ok()?, end?.parse().ok()?)
+    }
+
+    pub fn start(&self) -> Date {
+        self.start
+    }
+    pub fn end(&self) -> Date {
+        self.end
+    }
+
+    /// days charged, picking up and returning on the same day counts as one
+    pub fn days(&self) -> u32 {
+        (self.end.days() - self.start.days()).max(1) as u32
+    }
+
+    /// the first day the item is free again, the period takes up the days
+    /// from `start` up to but not including it, as many as are charged
+    pub fn returned(&self) -> Date {
+        self.start.add_days(i64::from(self.days()))
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ~ {}", self.start, self.end)
+    }
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
@@ -0,0 +1,192 @@
+//! Prices that follow demand.
+//!
+//! The `price` column of a catalog is the base price. Every [`PricingRule`]
+//! whose conditions all hold adds its `adjust_percent` (negative lowers the
+//! price), and the result is locked into the reservation when it is booked.
+
+use crate::bookable::Category;
+use crate::period::Date;
+use crate::{t, ToRow};
+use prettytable::{Cell, Row};
+
+/// how full an item is and how soon it leaves, when it is priced
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Demand {
+    /// booked share of the capacity, in percent
+    load_percent: u32,
+    /// days from today until departure/check-in, `None` when it has no date
+    days_before: Option<i64>,
+    /// day of the week of departure/check-in, 1 is Monday and 7 is Sunday
+    weekday: Option<u32>,
+}
+
+impl Demand {
+    pub fn new(booked: u32, capacity: u32, departure: Option<Date>, today: Date) -> Self {
+        let load_percent = if capacity == 0 {
+            100
+        } else {
+            (u64::from(booked) * 100 / u64::from(capacity)) as u32
+        };
+        Demand {
+            load_percent,
+            days_before: departure.map(|x| x.days_since(today)),
+            weekday: departure.map(|x| x.weekday()),
+        }
+    }
+
+    pub fn load_percent(&self) -> u32 {
+        self.load_percent
+    }
+    pub fn days_before(&self) -> Option<i64> {
+        self.days_before
+    }
+    pub fn weekday(&self) -> Option<u32> {
+        self.weekday
+    }
+}
+
+/// a row of `PricingRule`, a condition left `None` always holds
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PricingRule {
+    res_type: Option<u32>,
+    min_load: Option<u32>,
+    max_load: Option<u32>,
+    min_days: Option<u32>,
+    max_days: Option<u32>,
+    weekday: Option<u32>,
+    adjust_percent: i32,
+}
+
+impl PricingRule {
+    pub fn new(adjust_percent: i32) -> Self {
+        PricingRule {
+            adjust_percent,
+            ..PricingRule::default()
+        }
+    }
+
+    pub fn res_type(&self) -> Option<u32> {
+        self.res_type
+    }
+    pub fn adjust_percent(&self) -> i32 {
+        self.adjust_percent
+    }
+
+    /// only for one catalog type
+    pub fn set_res_type(&mut self, res_type: Option<u32>) {
+        self.res_type = res_type;
+    }
+    /// load factor between `min` and `max` percent, both included
+    pub fn set_load(&mut self, min: Option<u32>, max: Option<u32>) {
+        self.min_load = min;
+        self.max_load = max;
+    }
+    /// departure between `min` and `max` days from today, both included
+    pub fn set_days_before(&mut self, min: Option<u32>, max: Option<u32>) {
+        self.min_days = min;
+        self.max_days = max;
+    }
+    /// departure on this day of the week, 1 is Monday and 7 is Sunday
+    pub fn set_weekday(&mut self, weekday: Option<u32>) {
+        self.weekday = weekday;
+    }
+
+    pub fn matches(&self, res_type: u32, demand: &Demand) -> bool {
+        let at_least = |min: Option<u32>, value: i64| min.is_none_or(|x| value >= i64::from(x));
+        let at_most = |max: Option<u32>, value: i64| max.is_none_or(|x| value <= i64::from(x));
+        let load = i64::from(demand.load_percent);
+        // 没有日期的项目不满足任何与日期有关的条件
+        let days = |min: Option<u32>, max: Option<u32>| match demand.days_before {
+            Some(days) => at_least(min, days) && at_most(max, days),
+            None => min.is_none() && max.is_none(),
+        };
+        self.res_type.is_none_or(|x| x == res_type)
+            && at_least(self.min_load, load)
+            && at_most(self.max_load, load)
+            && days(self.min_days, self.max_days)
+            && self.weekday.is_none_or(|x| demand.weekday == Some(x))
+    }
+}
+
+/// price `base` with every rule that matches, never below zero and never
+/// above `u32::MAX`
+pub fn quote(base: u32, rules: &[PricingRule], res_type: u32, demand: &Demand) -> u32 {
+    let percent: i64 = 100
+        + rules
+            .iter()
+            .filter(|x| x.matches(res_type, demand))
+            .map(|x| i64::from(x.adjust_percent))
+            .sum::<i64>();
+    // 四舍五入到整数
+    ((i64::from(base) * percent.max(0) + 50) / 100).min(i64::from(u32::MAX)) as u32
+}
+
+/// what an item of the catalog would cost if it were booked now
+pub struct PriceQuote {
+    category: &'static Category,
+    res_id: String,
+    base: u32,
+    demand: Demand,
+    price: u32,
+}
+
+impl PriceQuote {
+    pub fn new(
+        category: &'static Category,
+        res_id: String,
+        base: u32,
+        demand: Demand,
+        price: u32,
+    ) -> Self {
+        PriceQuote {
+            category,
+            res_id,
+            base,
+            demand,
+            price,
+        }
+    }
+
+    pub fn category(&self) -> &'static Category {
+        self.category
+    }
+    pub fn res_id(&self) -> &str {
+        &self.res_id
+    }
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+    pub fn demand(&self) -> Demand {
+        self.demand
+    }
+    pub fn price(&self) -> u32 {
+        self.price
+    }
+}
+
+impl ToRow for PriceQuote {
+    fn to_row(&self) -> Row {
+        let optional = |x: Option<String>| x.unwrap_or_else(|| "-".to_string());
+        Row::new(vec![
+            Cell::new(self.category.name()),
+            Cell::new(&self.res_id),
+            Cell::new(&self.base.to_string()),
+            Cell::new(&format!("{}%", self.demand.load_percent)),
+            Cell::new(&optional(self.demand.days_before.map(|x| x.to_string()))),
+            Cell::new(self.demand.weekday.map_or("-", weekday_name)),
+            Cell::new(&self.price.to_string()),
+        ])
+    }
+}
+
+fn weekday_name(weekday: u32) -> &'static str {
+    match weekday {
+        1 => t!("weekday.monday"),
+        2 => t!("weekday.tuesday"),
+        3 => t!("weekday.wednesday"),
+        4 => t!("weekday.thursday"),
+        5 => t!("weekday.friday"),
+        6 => t!("weekday.saturday"),
+        _ => t!("weekday.sunday"),
+    }
+}
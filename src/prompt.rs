@@ -0,0 +1,115 @@
+//! Typed, validated input for the prompt mode.
+//!
+//! Every read retries until the input parses, typing [`BACK_COMMAND`]
+//! leaves the current prompt and end of input ends the session, both are
+//! reported as an [`Interrupt`] so callers can unwind with `?`.
+
+use crate::t;
+use std::cell::RefCell;
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// output shared by the prompts and the view, so a whole session goes to one stream
+pub type Output = Rc<RefCell<Box<dyn Write>>>;
+
+/// typing this at a prompt goes back to the previous menu, it starts with the
+/// prefix of the paging commands so it is never taken for a name, a city or an id
+pub const BACK_COMMAND: &str = ":q";
+
+/// why a prompt did not produce a value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interrupt {
+    /// the user asked to go back to the previous menu
+    Back,
+    /// the input is closed, e.g. Ctrl-D or the end of a script
+    Eof,
+}
+
+pub type PromptResult<T> = Result<T, Interrupt>;
+
+pub struct Prompt {
+    reader: Box<dyn BufRead>,
+    out: Output,
+}
+
+impl Prompt {
+    /// read from `reader`, retry messages are written to `out`
+    pub fn new(reader: Box<dyn BufRead>, out: Output) -> Self {
+        Prompt { reader, out }
+    }
+
+    pub fn stdin(out: Output) -> Self {
+        Prompt::new(Box::new(io::BufReader::new(io::stdin())), out)
+    }
+
+    fn invalid_input(&mut self) {
+        let mut out = self.out.borrow_mut();
+        out.write_all(t!("prompt.invalid").as_bytes())
+            .and_then(|_| out.flush())
+            .expect("Error: write output failed");
+    }
+
+    /// one trimmed line, empty lines included
+    pub fn line(&mut self) -> PromptResult<String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) | Err(_) => Err(Interrupt::Eof),
+            Ok(_) => {
+                let line = line.trim();
+                if line.eq_ignore_ascii_case(BACK_COMMAND) {
+                    Err(Interrupt::Back)
+                } else {
+                    Ok(line.to_string())
+                }
+            }
+        }
+    }
+
+    /// a non-empty line that parses as `T`, asking again until it does
+    pub fn read<T: FromStr>(&mut self) -> PromptResult<T> {
+        loop {
+            match self.line()?.parse() {
+                Ok(value) => return Ok(value),
+                Err(_) => self.invalid_input(),
+            }
+        }
+    }
+
+    /// like [`Prompt::read`], but an empty line means "no value"
+    pub fn optional<T: FromStr>(&mut self) -> PromptResult<Option<T>> {
+        loop {
+            let line = self.line()?;
+            if line.is_empty() {
+                return Ok(None);
+            }
+            match line.parse() {
+                Ok(value) => return Ok(Some(value)),
+                Err(_) => self.invalid_input(),
+            }
+        }
+    }
+
+    /// a non-empty line of text
+    pub fn text(&mut self) -> PromptResult<String> {
+        loop {
+            let line = self.line()?;
+            if !line.is_empty() {
+                return Ok(line);
+            }
+            self.invalid_input();
+        }
+    }
+
+    /// `y` or `n`, an empty line gives `default` if there is one
+    pub fn confirm(&mut self, default: Option<bool>) -> PromptResult<bool> {
+        loop {
+            match (self.line()?.as_str(), default) {
+                ("y" | "Y", _) => return Ok(true),
+                ("n" | "N", _) => return Ok(false),
+                ("", Some(default)) => return Ok(default),
+                _ => self.invalid_input(),
+            }
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! A small typed layer over prepared statements.
+//!
+//! Values only ever travel as bound `?` parameters, the SQL text itself is made
+//! of literals and the table/column names this crate picks, so ids and names
+//! coming from users or imported data can't change the statement.
+
+use mysql::prelude::{FromRow, Queryable};
+use mysql::{Result, Value};
+
+/// a statement with the values of its `?` placeholders, in order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Query {
+    sql: String,
+    params: Vec<Value>,
+}
+
+impl Query {
+    pub fn new(sql: impl Into<String>) -> Self {
+        Query {
+            sql: sql.into(),
+            params: vec![],
+        }
+    }
+
+    /// append more SQL text, never put values in here
+    pub fn push(mut self, sql: &str) -> Self {
+        self.sql.push_str(sql);
+        self
+    }
+
+    /// bind the value of the next `?`
+    pub fn bind(mut self, value: impl Into<Value>) -> Self {
+        self.params.push(value.into());
+        self
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+    pub fn params(&self) -> &[Value] {
+        &self.params
+    }
+
+    /// every row, converted by `f`
+    pub fn map<C, T, U, F>(self, conn: &mut C, f: F) -> Result<Vec<U>>
+    where
+        C: Queryable,
+        T: FromRow,
+        F: FnMut(T) -> U,
+    {
+        conn.exec_map(self.sql, self.params, f)
+    }
+
+    /// the first row, if any
+    pub fn first<C: Queryable, T: FromRow>(self, conn: &mut C) -> Result<Option<T>> {
+        conn.exec_first(self.sql, self.params)
+    }
+
+    /// run a statement that returns no rows
+    pub fn execute<C: Queryable>(self, conn: &mut C) -> Result<()> {
+        conn.exec_drop(self.sql, self.params)
+    }
+}
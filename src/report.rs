@@ -0,0 +1,178 @@
+//! Sales reports for admins.
+//!
+//! Every report is a plain table computed from `Reservation` joined with the
+//! catalogs, shown in the terminal or exported as CSV. Items booked in a tour
+//! package are not charged on their own, the package price counts instead.
+
+use crate::bookable::{Category, CATEGORIES};
+use crate::i18n::tr;
+use crate::{t, ToRow};
+use prettytable::{Attr, Cell, Row};
+
+/// rows of the top customers and most popular routes
+pub const REPORT_LIMIT: usize = 10;
+
+/// the reports an admin can ask for
+#[derive(Clone, Copy)]
+pub enum ReportKind {
+    /// booked seats/rooms against the capacity of every item, e.g. the load
+    /// factor per flight or the occupancy per hotel
+    Occupancy(&'static Category),
+    RevenueByCategory,
+    /// revenue of the arrival city of routes and the city of everything else
+    RevenueByCity,
+    /// revenue by the month the bookings were made
+    RevenueByMonth,
+    TopCustomers,
+    PopularRoutes,
+}
+
+impl ReportKind {
+    /// every report, in the order of the menu; dated catalogs have no fixed
+    /// capacity to report on
+    pub fn all() -> Vec<ReportKind> {
+        let mut kinds: Vec<ReportKind> = CATEGORIES
+            .iter()
+            .filter(|x| !x.dated())
+            .map(|x| ReportKind::Occupancy(x))
+            .collect();
+        kinds.extend_from_slice(&[
+            ReportKind::RevenueByCategory,
+            ReportKind::RevenueByCity,
+            ReportKind::RevenueByMonth,
+            ReportKind::TopCustomers,
+            ReportKind::PopularRoutes,
+        ]);
+        kinds
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            ReportKind::Occupancy(category) => t!("report.occupancy", category.name()),
+            ReportKind::RevenueByCategory => t!("report.revenue_by_category").to_string(),
+            ReportKind::RevenueByCity => t!("report.revenue_by_city").to_string(),
+            ReportKind::RevenueByMonth => t!("report.revenue_by_month").to_string(),
+            ReportKind::TopCustomers => t!("report.top_customers", REPORT_LIMIT),
+            ReportKind::PopularRoutes => t!("report.popular_routes", REPORT_LIMIT),
+        }
+    }
+
+    /// name of the exported file without the extension
+    pub fn file_stem(&self) -> String {
+        match self {
+            ReportKind::Occupancy(category) => {
+                format!("report_occupancy_{}", category.table.to_lowercase())
+            }
+            ReportKind::RevenueByCategory => "report_revenue_by_category".to_string(),
+            ReportKind::RevenueByCity => "report_revenue_by_city".to_string(),
+            ReportKind::RevenueByMonth => "report_revenue_by_month".to_string(),
+            ReportKind::TopCustomers => "report_top_customers".to_string(),
+            ReportKind::PopularRoutes => "report_popular_routes".to_string(),
+        }
+    }
+
+    /// message keys of the columns
+    fn header(&self) -> &'static [&'static str] {
+        match self {
+            ReportKind::Occupancy(_) => &[
+                "report.item",
+                "report.place",
+                "report.capacity",
+                "report.booked",
+                "report.load",
+            ],
+            ReportKind::RevenueByCategory => {
+                &["report.category", "report.bookings", "report.revenue"]
+            }
+            ReportKind::RevenueByCity => &["report.city", "report.bookings", "report.revenue"],
+            ReportKind::RevenueByMonth => &["report.month", "report.bookings", "report.revenue"],
+            ReportKind::TopCustomers => &[
+                "report.customer_id",
+                "report.customer",
+                "report.bookings",
+                "report.revenue",
+            ],
+            ReportKind::PopularRoutes => &["report.route", "report.bookings", "report.revenue"],
+        }
+    }
+}
+
+/// a computed report
+pub struct Report {
+    kind: ReportKind,
+    rows: Vec<Vec<String>>,
+}
+
+impl Report {
+    pub fn new(kind: ReportKind, rows: Vec<Vec<String>>) -> Self {
+        Report { kind, rows }
+    }
+
+    pub fn kind(&self) -> ReportKind {
+        self.kind
+    }
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    pub fn header(&self) -> Row {
+        Row::new(
+            self.columns()
+                .iter()
+                .map(|x| Cell::new(x).with_style(Attr::Bold))
+                .collect(),
+        )
+    }
+
+    /// the rows ready for [`View::querying`](crate::view::View::querying)
+    pub fn table_rows(&self) -> Vec<Row> {
+        self.rows.iter().map(|x| x.to_row()).collect()
+    }
+
+    /// the report as CSV (RFC 4180) with the header as its first line
+    pub fn to_csv(&self) -> String {
+        let mut csv = csv_line(&self.columns());
+        for row in &self.rows {
+            csv.push_str(&csv_line(row));
+        }
+        csv
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.kind
+            .header()
+            .iter()
+            .map(|x| tr(x).to_string())
+            .collect()
+    }
+}
+
+impl ToRow for Vec<String> {
+    fn to_row(&self) -> Row {
+        Row::new(self.iter().map(|x| Cell::new(x)).collect())
+    }
+}
+
+/// `booked` of `capacity` as a percentage with one decimal, e.g. `87.5`
+pub fn load_factor(booked: u64, capacity: u64) -> String {
+    if capacity == 0 {
+        return "-".to_string();
+    }
+    format!("{:.1}", booked as f64 * 100.0 / capacity as f64)
+}
+
+// 含逗号、引号或换行的字段加引号，引号写两遍
+fn csv_line<T: AsRef<str>>(fields: &[T]) -> String {
+    let fields: Vec<String> = fields
+        .iter()
+        .map(|x| {
+            let x = x.as_ref();
+            if x.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", x.replace('"', "\"\""))
+            } else {
+                x.to_string()
+            }
+        })
+        .collect();
+    format!("{}\r\n", fields.join(","))
+}
@@ -1,17 +1,109 @@
+use crate::bookable::{category, Category};
+use crate::period::Period;
+use crate::{t, ToRow};
+use prettytable::{Cell, Row};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// length of the booking reference shown to customers
+pub const BOOKING_REF_LEN: usize = 6;
+
+// 去掉了容易混淆的 0/O、1/I
+const BOOKING_REF_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// where a reservation is in its lifecycle, the value of `Reservation.status`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReservationStatus {
+    /// the seat/room is kept for a while until the booking is paid
+    Held,
+    Confirmed,
+    /// cancelled by the customer, the seat/room is free again
+    Cancelled,
+    /// the hold ran out or the payment failed, the seat/room is free again
+    Expired,
+    /// the trip is over
+    Completed,
+}
+
+impl ReservationStatus {
+    pub const ALL: [ReservationStatus; 5] = [
+        ReservationStatus::Held,
+        ReservationStatus::Confirmed,
+        ReservationStatus::Cancelled,
+        ReservationStatus::Expired,
+        ReservationStatus::Completed,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReservationStatus::Held => "held",
+            ReservationStatus::Confirmed => "confirmed",
+            ReservationStatus::Cancelled => "cancelled",
+            ReservationStatus::Expired => "expired",
+            ReservationStatus::Completed => "completed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        ReservationStatus::ALL
+            .iter()
+            .copied()
+            .find(|x| x.as_str() == value)
+    }
+
+    /// the reservation still takes up its seat/room, or did until the trip was over
+    pub fn is_active(&self) -> bool {
+        !matches!(
+            self,
+            ReservationStatus::Cancelled | ReservationStatus::Expired
+        )
+    }
+
+    /// the customer can still cancel it
+    pub fn is_cancellable(&self) -> bool {
+        matches!(self, ReservationStatus::Held | ReservationStatus::Confirmed)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReservationStatus::Held => t!("status.held"),
+            ReservationStatus::Confirmed => t!("status.confirmed"),
+            ReservationStatus::Cancelled => t!("status.cancelled"),
+            ReservationStatus::Expired => t!("status.expired"),
+            ReservationStatus::Completed => t!("status.completed"),
+        }
+    }
+}
+
 pub struct Reservation {
     customer: u32,
     res_type: u32,
     res_id: String,
     id: u32,
+    booking_ref: String,
+    /// days of a dated reservation, e.g. a car rental
+    period: Option<Period>,
+    status: ReservationStatus,
 }
 
 impl Reservation {
-    pub fn new(customer: u32, res_type: u32, res_id: String, id: u32) -> Self {
+    pub fn new(
+        customer: u32,
+        res_type: u32,
+        res_id: String,
+        id: u32,
+        booking_ref: String,
+        period: Option<Period>,
+    ) -> Self {
         Reservation {
             customer,
             res_type,
             res_id,
             id,
+            booking_ref,
+            period,
+            status: ReservationStatus::Confirmed,
         }
     }
 
@@ -27,6 +119,15 @@ impl Reservation {
     pub fn set_id(&mut self, id: u32) {
         self.id = id;
     }
+    pub fn set_booking_ref(&mut self, booking_ref: String) {
+        self.booking_ref = booking_ref;
+    }
+    pub fn set_period(&mut self, period: Option<Period>) {
+        self.period = period;
+    }
+    pub fn set_status(&mut self, status: ReservationStatus) {
+        self.status = status;
+    }
     pub fn customer(&self) -> u32 {
         self.customer
     }
@@ -39,4 +140,58 @@ impl Reservation {
     pub fn id(&self) -> u32 {
         self.id
     }
+    pub fn booking_ref(&self) -> &str {
+        &self.booking_ref
+    }
+    pub fn period(&self) -> Option<Period> {
+        self.period
+    }
+    pub fn status(&self) -> ReservationStatus {
+        self.status
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        category(self.res_type)
+            .map(Category::name)
+            .unwrap_or_else(|| t!("type.unknown"))
+    }
+}
+
+impl ToRow for Reservation {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(self.booking_ref()),
+            Cell::new(self.type_name()),
+            Cell::new(self.res_id()),
+            Cell::new(&self.period().map(|x| x.to_string()).unwrap_or_default()),
+            Cell::new(self.status.label()),
+        ])
+    }
+}
+
+/// generate a PNR-style booking reference, e.g. `K7QX2M`.
+///
+/// Uniqueness is guaranteed by the `UNIQUE` index on `Reservation.booking_ref`,
+/// the caller should retry with a new reference when the insert collides.
+pub fn generate_booking_ref() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    hasher.write_u128(nanos);
+    let mut seed = hasher.finish();
+
+    let mut booking_ref = String::with_capacity(BOOKING_REF_LEN);
+    for _ in 0..BOOKING_REF_LEN {
+        let idx = (seed % BOOKING_REF_CHARS.len() as u64) as usize;
+        booking_ref.push(BOOKING_REF_CHARS[idx] as char);
+        seed /= BOOKING_REF_CHARS.len() as u64;
+    }
+    booking_ref
+}
+
+/// normalize what the user typed so that `k7qx2m ` matches `K7QX2M`
+pub fn normalize_booking_ref(input: &str) -> String {
+    input.trim().to_uppercase()
 }
@@ -0,0 +1,72 @@
+use crate::bookable::Category;
+use crate::ToRow;
+use prettytable::{Attr, Cell, Row};
+
+/// how many of the cheapest items are proposed per city and catalog
+pub const SUGGESTIONS_PER_CITY: u32 = 3;
+
+/// an available hotel/bus proposed in one of the cities of the booked route
+pub struct Suggestion {
+    category: &'static Category,
+    city: String,
+    row: Row,
+}
+
+impl Suggestion {
+    pub fn new(category: &'static Category, city: String, row: Row) -> Self {
+        Suggestion {
+            category,
+            city,
+            row,
+        }
+    }
+
+    pub fn category(&self) -> &'static Category {
+        self.category
+    }
+    pub fn city(&self) -> &str {
+        &self.city
+    }
+    pub fn row(&self) -> &Row {
+        &self.row
+    }
+
+    /// number of the item, the first column of its row
+    pub fn res_id(&self) -> String {
+        self.row
+            .get_cell(0)
+            .map(Cell::get_content)
+            .unwrap_or_default()
+    }
+
+    /// the row with the number to pick it from the list in front
+    pub fn numbered_row(&self, number: usize) -> Row {
+        let mut row = self.row.clone();
+        row.insert_cell(0, Cell::new(&number.to_string()));
+        row
+    }
+}
+
+impl ToRow for Suggestion {
+    fn to_row(&self) -> Row {
+        self.row.clone()
+    }
+}
+
+/// header of [`Suggestion::numbered_row`]
+pub fn numbered_header(category: &Category) -> Row {
+    let mut header = category.header();
+    header.insert_cell(0, Cell::new("#").with_style(Attr::Bold));
+    header
+}
+
+/// cities of the booked routes in the order they are first visited, each once
+pub fn route_cities(routes: &[Vec<String>]) -> Vec<String> {
+    let mut cities: Vec<String> = Vec::new();
+    for city in routes.iter().flatten() {
+        if !cities.contains(city) {
+            cities.push(city.clone());
+        }
+    }
+    cities
+}
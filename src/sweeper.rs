@@ -0,0 +1,72 @@
+//! Background upkeep of reservations.
+//!
+//! The sweeper runs on its own thread with its own connection. Right away and
+//! then every `sweep_seconds` it lets the holds that ran out go, which gives
+//! their seats and rooms back, and marks the reservations whose trip is over
+//! as completed, see [`MySQLConnection::sweep`].
+
+use crate::config::Config;
+use crate::mysql_connection::MySQLConnection;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// minutes a booking is held while it is being paid
+pub const DEFAULT_HOLD_MINUTES: u32 = 15;
+pub const DEFAULT_SWEEP_SECONDS: u64 = 60;
+
+/// `hold_minutes` of the config file
+pub fn hold_minutes(config: &Config) -> u32 {
+    config
+        .get("hold_minutes")
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(DEFAULT_HOLD_MINUTES)
+}
+
+/// `sweep_seconds` of the config file
+pub fn interval(config: &Config) -> Duration {
+    let seconds = config
+        .get("sweep_seconds")
+        .and_then(|x| x.parse().ok())
+        .filter(|x| *x > 0)
+        .unwrap_or(DEFAULT_SWEEP_SECONDS);
+    Duration::from_secs(seconds)
+}
+
+/// the sweeper thread, it is stopped when this is dropped
+pub struct Sweeper {
+    stop: Option<Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Sweeper {
+    /// start sweeping the database at `url` every `interval`
+    pub fn spawn(url: String, interval: Duration) -> Self {
+        let (stop, stopped) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            let mut connection = MySQLConnection::new(&url);
+            loop {
+                connection.sweep();
+                // 发送方被丢弃即停止
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    _ => break,
+                }
+            }
+        });
+        Sweeper {
+            stop: Some(stop),
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for Sweeper {
+    fn drop(&mut self) {
+        self.stop.take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
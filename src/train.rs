@@ -0,0 +1,123 @@
+use crate::bookable::{Bookable, Category, Place};
+use crate::ToRow;
+use prettytable::{Cell, Row};
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Train {
+    train_num: String,
+    price: u32,
+    from_city: String,
+    arrive_city: String,
+    seat_class: String,
+    // 格式为 `YYYY-MM-DD HH:MM`，未排班时为空
+    depart_time: Option<String>,
+}
+
+impl Train {
+    pub fn new(
+        train_num: String,
+        price: u32,
+        from_city: String,
+        arrive_city: String,
+        seat_class: String,
+        depart_time: Option<String>,
+    ) -> Self {
+        Train {
+            train_num,
+            price,
+            from_city,
+            arrive_city,
+            seat_class,
+            depart_time,
+        }
+    }
+
+    pub fn train_num(&self) -> &str {
+        &self.train_num
+    }
+    pub fn price(&self) -> u32 {
+        self.price
+    }
+    pub fn from_city(&self) -> &str {
+        &self.from_city
+    }
+    pub fn arrive_city(&self) -> &str {
+        &self.arrive_city
+    }
+    pub fn seat_class(&self) -> &str {
+        &self.seat_class
+    }
+    pub fn depart_time(&self) -> Option<&str> {
+        self.depart_time.as_deref()
+    }
+
+    pub fn set_train_num(&mut self, train_num: String) {
+        self.train_num = train_num;
+    }
+    pub fn set_price(&mut self, price: u32) {
+        self.price = price;
+    }
+    pub fn set_from_city(&mut self, from_city: String) {
+        self.from_city = from_city;
+    }
+    pub fn set_arrive_city(&mut self, arrive_city: String) {
+        self.arrive_city = arrive_city;
+    }
+    pub fn set_seat_class(&mut self, seat_class: String) {
+        self.seat_class = seat_class;
+    }
+    pub fn set_depart_time(&mut self, depart_time: Option<String>) {
+        self.depart_time = depart_time;
+    }
+}
+
+impl ToRow for Train {
+    fn to_row(&self) -> Row {
+        Row::new(vec![
+            Cell::new(self.train_num()),
+            Cell::new(&self.price().to_string()),
+            Cell::new(self.from_city()),
+            Cell::new(self.arrive_city()),
+            Cell::new(self.seat_class()),
+            Cell::new(self.depart_time().unwrap_or("-")),
+        ])
+    }
+}
+
+impl Bookable for Train {
+    const CATEGORY: Category = Category {
+        res_type: 4,
+        table: "Trains",
+        key_column: "train_num",
+        capacity_column: "num_seat",
+        time_column: "depart_time",
+        end_column: "arrive_time",
+        dated: false,
+        columns: "train_num, price, from_city, arrive_city, seat_class, \
+                  DATE_FORMAT(depart_time, '%Y-%m-%d %H:%i')",
+        place: Place::Route("from_city", "arrive_city"),
+        name: "type.train",
+        header: &[
+            "header.train_num",
+            "header.price",
+            "header.from_station",
+            "header.arrive_station",
+            "header.seat_class",
+            "header.depart_time",
+        ],
+        to_row: |row| Train::from_row(row).to_row(),
+    };
+
+    fn from_row(row: mysql::Row) -> Self {
+        let (train_num, price, from_city, arrive_city, seat_class, depart_time) =
+            mysql::from_row(row);
+        Train::new(
+            train_num,
+            price,
+            from_city,
+            arrive_city,
+            seat_class,
+            depart_time,
+        )
+    }
+}
@@ -0,0 +1,512 @@
+//! Full-screen terminal interface.
+//!
+//! Everything here goes through the public operations of [`Controller`], the
+//! classic prompt mode in `Controller::run` keeps working unchanged. The state
+//! and the key bindings live in [`Screen`], which never touches the terminal or
+//! the database; what the controller prints while the screen is up ends up on
+//! the status line.
+
+use crate::bookable::{Category, CATEGORIES};
+use crate::cancellation::CancelQuote;
+use crate::catalog_filter::{CatalogFilter, Page};
+use crate::controller::{reservation_hint, Controller};
+use crate::{t, Status, ToRow};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+/// which table the arrow keys move in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Focus {
+    Catalog,
+    Itinerary,
+}
+
+/// what the controller has to do after a key press
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Action {
+    Nothing,
+    Quit,
+    Login,
+    /// the tab or the page changed, load the catalog again
+    Reload,
+    Book(String),
+    /// show what cancelling the reservation with this id gives back
+    AskCancel(u32),
+    Cancel(u32),
+}
+
+/// what the full-screen interface shows, without the terminal and the database
+pub struct Screen {
+    tab: usize,
+    page: Page,
+    total_pages: u32,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+    catalog: TableState,
+    reservations: Vec<(u32, Vec<String>)>,
+    itinerary: TableState,
+    complete: bool,
+    focus: Focus,
+    confirm: Option<(u32, CancelQuote)>,
+    status: String,
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Screen::new()
+    }
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Screen {
+            tab: 0,
+            page: Page::default(),
+            total_pages: 1,
+            header: vec![],
+            rows: vec![],
+            catalog: TableState::default(),
+            reservations: vec![],
+            itinerary: TableState::default(),
+            complete: true,
+            focus: Focus::Catalog,
+            confirm: None,
+            status: t!("tui.help").to_string(),
+        }
+    }
+
+    pub fn category(&self) -> &'static Category {
+        CATEGORIES[self.tab]
+    }
+    pub fn page(&self) -> &Page {
+        &self.page
+    }
+    pub fn focus(&self) -> Focus {
+        self.focus
+    }
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+    }
+
+    /// the catalog row the cursor is on
+    pub fn selected_item(&self) -> Option<&[String]> {
+        self.catalog
+            .selected()
+            .and_then(|x| self.rows.get(x))
+            .map(|x| x.as_slice())
+    }
+
+    /// id of the reservation the cursor is on
+    pub fn selected_reservation(&self) -> Option<u32> {
+        self.itinerary
+            .selected()
+            .and_then(|x| self.reservations.get(x))
+            .map(|(id, _)| *id)
+    }
+
+    /// id of the reservation waiting for the user to confirm its cancellation
+    pub fn confirming(&self) -> Option<u32> {
+        self.confirm.as_ref().map(|(id, _)| *id)
+    }
+
+    /// the catalog now has `total_pages`, stay on the last one if it shrank
+    pub fn set_total_pages(&mut self, total_pages: u32) {
+        self.total_pages = total_pages.max(1);
+        if self.page.number() > self.total_pages {
+            self.page.set_number(self.total_pages);
+        }
+    }
+
+    /// show a freshly loaded page, the cursor goes back to its first row
+    pub fn set_catalog(&mut self, header: Vec<String>, rows: Vec<Vec<String>>) {
+        self.header = header;
+        self.rows = rows;
+        self.catalog
+            .select(if self.rows.is_empty() { None } else { Some(0) });
+    }
+
+    /// show the reservations again, the cursor stays where it was if it still can
+    pub fn set_itinerary(&mut self, reservations: Vec<(u32, Vec<String>)>, complete: bool) {
+        self.reservations = reservations;
+        self.complete = complete;
+        let selected = self.itinerary.selected().unwrap_or(0);
+        self.itinerary.select(if self.reservations.is_empty() {
+            None
+        } else {
+            Some(selected.min(self.reservations.len() - 1))
+        });
+    }
+
+    /// ask the user to confirm cancelling reservation `id`
+    pub fn ask_confirm(&mut self, id: u32, quote: CancelQuote) {
+        self.confirm = Some((id, quote));
+    }
+
+    pub fn handle_key(&mut self, code: KeyCode) -> Action {
+        // 确认取消时任何其他按键都放弃取消
+        if let Some((id, _)) = self.confirm.take() {
+            if let KeyCode::Char('y') | KeyCode::Char('Y') = code {
+                return Action::Cancel(id);
+            }
+            self.status = t!("tui.cancel_aborted").to_string();
+            return Action::Nothing;
+        }
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+            KeyCode::Char('l') => Action::Login,
+            KeyCode::Tab => {
+                self.tab = (self.tab + 1) % CATEGORIES.len();
+                self.page = Page::default();
+                Action::Reload
+            }
+            KeyCode::Char('f') => {
+                self.focus = match self.focus {
+                    Focus::Catalog => Focus::Itinerary,
+                    Focus::Itinerary => Focus::Catalog,
+                };
+                Action::Nothing
+            }
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Right | KeyCode::PageDown if self.page.number() < self.total_pages => {
+                self.page.set_number(self.page.number() + 1);
+                Action::Reload
+            }
+            KeyCode::Left | KeyCode::PageUp if self.page.number() > 1 => {
+                self.page.set_number(self.page.number() - 1);
+                Action::Reload
+            }
+            KeyCode::Char('b') => self.book(),
+            KeyCode::Char('c') => match self.selected_reservation() {
+                Some(id) => Action::AskCancel(id),
+                None => {
+                    self.status = t!("tui.select_reservation").to_string();
+                    Action::Nothing
+                }
+            },
+            _ => Action::Nothing,
+        }
+    }
+
+    fn move_selection(&mut self, step: i32) -> Action {
+        let (state, len) = match self.focus {
+            Focus::Catalog => (&mut self.catalog, self.rows.len()),
+            Focus::Itinerary => (&mut self.itinerary, self.reservations.len()),
+        };
+        if len > 0 {
+            let current = state.selected().unwrap_or(0) as i32;
+            state.select(Some((current + step).clamp(0, len as i32 - 1) as usize));
+        }
+        Action::Nothing
+    }
+
+    fn book(&mut self) -> Action {
+        let res_id = match self.selected_item() {
+            Some(row) => row[0].clone(),
+            None => return Action::Nothing,
+        };
+        // 按日期预定的类别需要输入日期，只能在提示符模式下预定
+        if self.category().dated() {
+            self.status = t!("tui.dated_unsupported", self.category().name());
+            return Action::Nothing;
+        }
+        Action::Book(res_id)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [tabs, body, status] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Fill(1),
+            Constraint::Length(3),
+        ])
+        .areas(frame.area());
+        let [catalog, side] =
+            Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)])
+                .areas(body);
+        let [detail, itinerary] =
+            Layout::vertical([Constraint::Length(8), Constraint::Fill(1)]).areas(side);
+
+        let titles: Vec<&str> = CATEGORIES.iter().map(|x| x.name()).collect();
+        frame.render_widget(
+            Tabs::new(titles)
+                .select(self.tab)
+                .highlight_style(Style::default().fg(Color::Yellow))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(t!("app.title")),
+                ),
+            tabs,
+        );
+
+        self.draw_catalog(frame, catalog);
+        self.draw_detail(frame, detail);
+        self.draw_itinerary(frame, itinerary);
+
+        frame.render_widget(
+            Paragraph::new(self.status.as_str()).block(Block::default().borders(Borders::ALL)),
+            status,
+        );
+
+        if let Some((_, quote)) = &self.confirm {
+            draw_confirm(frame, quote);
+        }
+    }
+
+    fn draw_catalog(&mut self, frame: &mut Frame, area: Rect) {
+        let title = t!(
+            "page.status",
+            self.page.number(),
+            self.total_pages,
+            self.page.size()
+        );
+        let table =
+            table(&self.header, &self.rows).block(focus_block(title, self.focus == Focus::Catalog));
+        frame.render_stateful_widget(table, area, &mut self.catalog);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect) {
+        let lines: Vec<Line> = match self.catalog.selected().and_then(|x| self.rows.get(x)) {
+            Some(row) => self
+                .header
+                .iter()
+                .zip(row)
+                .map(|(name, value)| Line::from(format!("{}: {}", name, value)))
+                .collect(),
+            None => vec![Line::from(t!("query.empty"))],
+        };
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(t!("tui.detail")),
+            ),
+            area,
+        );
+    }
+
+    fn draw_itinerary(&mut self, frame: &mut Frame, area: Rect) {
+        let status = if self.complete {
+            t!("completeness.ok")
+        } else {
+            t!("completeness.broken")
+        };
+        let header: Vec<String> = reservation_hint().iter().map(|x| x.get_content()).collect();
+        let rows: Vec<Vec<String>> = self.reservations.iter().map(|(_, x)| x.clone()).collect();
+        let title = format!("{} {}", t!("tui.itinerary"), status);
+        let table = table(&header, &rows).block(focus_block(title, self.focus == Focus::Itinerary));
+        frame.render_stateful_widget(table, area, &mut self.itinerary);
+    }
+}
+
+/// what the controller writes while the screen is up, shown on the status line
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Captured {
+    /// the lines written since the last call, on one line
+    fn take(&self) -> String {
+        let bytes = std::mem::take(&mut *self.0.borrow_mut());
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct App<'a> {
+    controller: &'a mut Controller,
+    screen: Screen,
+    captured: Captured,
+}
+
+/// run the full-screen interface for the user that is logged in on `controller`
+pub fn run(controller: &mut Controller) -> Status {
+    // 界面打开期间不能直接写终端
+    let captured = Captured::default();
+    let out = controller.swap_output(Box::new(captured.clone()));
+    let mut terminal = ratatui::init();
+    let res = App::new(controller, captured).event_loop(&mut terminal);
+    ratatui::restore();
+    controller.swap_output(out);
+    res.unwrap_or_else(|e| {
+        controller.show_error(&e.to_string());
+        Status::Quit
+    })
+}
+
+impl<'a> App<'a> {
+    fn new(controller: &'a mut Controller, captured: Captured) -> Self {
+        let mut app = App {
+            controller,
+            screen: Screen::new(),
+            captured,
+        };
+        app.refresh_itinerary();
+        app.load_page();
+        app.captured.take();
+        app
+    }
+
+    /// load the current page of the current tab
+    fn load_page(&mut self) {
+        let filter = CatalogFilter::new();
+        let category = self.screen.category();
+        let total = self.controller.count_catalog(category, &filter);
+        self.screen
+            .set_total_pages(self.screen.page().total_pages(total));
+        let rows = self
+            .controller
+            .search(category, &filter, self.screen.page())
+            .iter()
+            .map(row_cells)
+            .collect();
+        self.screen.set_catalog(row_cells(&category.header()), rows);
+    }
+
+    fn refresh_itinerary(&mut self) {
+        let complete = self.controller.check_completeness();
+        self.controller.query_reservations();
+        let reservations = self
+            .controller
+            .reservations()
+            .iter()
+            .map(|x| (x.id(), row_cells(&x.to_row())))
+            .collect();
+        self.screen.set_itinerary(reservations, complete);
+    }
+
+    /// show `outcome` followed by whatever the controller reported on the way
+    fn report(&mut self, outcome: &str) {
+        let messages = self.captured.take();
+        self.screen.set_status(if messages.is_empty() {
+            outcome.to_string()
+        } else {
+            format!("{} {}", outcome, messages)
+        });
+    }
+
+    fn event_loop(&mut self, terminal: &mut DefaultTerminal) -> io::Result<Status> {
+        loop {
+            terminal.draw(|frame| self.screen.draw(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match self.screen.handle_key(key.code) {
+                Action::Quit => return Ok(Status::Quit),
+                Action::Login => return Ok(Status::Login),
+                Action::Reload => self.load_page(),
+                Action::Book(res_id) => self.book(res_id),
+                Action::AskCancel(id) => match self.controller.cancel_quote(id) {
+                    Some(quote) => self.screen.ask_confirm(id, quote),
+                    None => self.report(t!("booking.not_found")),
+                },
+                Action::Cancel(id) => self.cancel(id),
+                Action::Nothing => {}
+            }
+        }
+    }
+
+    fn book(&mut self, res_id: String) {
+        let category = self.screen.category();
+        match self.controller.book(category, res_id) {
+            Some(booking_ref) => self.report(&t!("booking.success", booking_ref)),
+            None => self.report(t!("tui.book_failed")),
+        }
+        self.refresh_itinerary();
+        self.load_page();
+    }
+
+    fn cancel(&mut self, id: u32) {
+        if self.controller.cancel_reservation(id) {
+            self.report(t!("common.success"));
+        } else {
+            self.report(t!("tui.cancel_failed"));
+        }
+        self.refresh_itinerary();
+        self.load_page();
+    }
+}
+
+fn draw_confirm(frame: &mut Frame, quote: &CancelQuote) {
+    let [_, area, _] = Layout::vertical([
+        Constraint::Fill(1),
+        Constraint::Length(7),
+        Constraint::Fill(1),
+    ])
+    .areas(frame.area());
+    let [_, area, _] = Layout::horizontal([
+        Constraint::Fill(1),
+        Constraint::Percentage(50),
+        Constraint::Fill(1),
+    ])
+    .areas(area);
+    let lines = vec![
+        Line::from(format!("{}: {}", t!("cancel.price"), quote.price())),
+        Line::from(format!("{}: {}", t!("cancel.fee"), quote.fee())),
+        Line::from(format!("{}: {}", t!("cancel.refund"), quote.refund())),
+        Line::from(t!("cancel.confirm")),
+    ];
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(t!("cancel.title")),
+        ),
+        area,
+    );
+}
+
+fn table<'t>(header: &'t [String], rows: &'t [Vec<String>]) -> Table<'t> {
+    let widths = vec![Constraint::Fill(1); header.len().max(1)];
+    Table::new(
+        rows.iter()
+            .map(|row| Row::new(row.iter().map(|x| Cell::from(x.as_str())))),
+        widths,
+    )
+    .header(
+        Row::new(header.iter().map(|x| Cell::from(x.as_str())))
+            .style(Style::default().add_modifier(Modifier::BOLD)),
+    )
+    .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+fn focus_block(title: String, focused: bool) -> Block<'static> {
+    let style = if focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(style)
+        .title(title)
+}
+
+fn row_cells(row: &prettytable::Row) -> Vec<String> {
+    row.iter().map(|x| x.get_content()).collect()
+}
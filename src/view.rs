@@ -1,103 +1,260 @@
-use crate::{read, MySQLConnection, ToRow};
+use crate::bookable::CATEGORIES;
+use crate::cancellation::CancelQuote;
+use crate::catalog_filter::Page;
+use crate::customer::{Customer, ProfileField};
+use crate::loyalty::{LoyaltyAccount, POINTS_PER_DISCOUNT};
+use crate::prompt::Output;
+use crate::report::ReportKind;
+use crate::{t, ToRow};
 use prettytable::{format, table, Row, Table};
-use std::io::{stdin, stdout, Stdout, Write};
+use std::io::Write;
 
-pub(crate) struct View;
+/// everything the prompt mode shows goes through here
+pub(crate) struct View {
+    out: Output,
+    // 输出到终端时保留表格的粗体等样式
+    tty: bool,
+}
 
 impl View {
-    pub fn login_menu() {
-        println!("------------- TouringBookingSystem -------------");
-        print!("请输入您的ID(不存在的id将会进行注册):");
-        stdout().flush();
+    pub fn new(out: Output) -> Self {
+        View { out, tty: false }
+    }
+
+    pub fn stdout(out: Output) -> Self {
+        View { out, tty: true }
+    }
+
+    fn print(&mut self, text: impl AsRef<str>) {
+        let mut out = self.out.borrow_mut();
+        out.write_all(text.as_ref().as_bytes())
+            .and_then(|_| out.flush())
+            .expect("Error: write output failed");
+    }
+
+    fn println(&mut self, text: impl AsRef<str>) {
+        self.print(format!("{}\n", text.as_ref()));
+    }
+
+    fn print_table(&mut self, table: &Table) {
+        if self.tty {
+            table.printstd();
+        } else {
+            table
+                .print(&mut *self.out.borrow_mut())
+                .expect("Error: write output failed");
+        }
+    }
+
+    /// print text that was already rendered, e.g. by [`View::render`]
+    pub fn text(&mut self, text: &str) {
+        self.println(text);
     }
 
-    pub fn sign_up(id: u32) {
-        print!("账号[{}]不存在，是否要注册账号?(y/n)", id);
-        stdout().flush();
+    /// a failure the user should know about, e.g. a declined payment
+    pub fn error(&mut self, message: impl AsRef<str>) {
+        self.println(message);
     }
 
-    pub fn new_name() {
-        print!("请输入对应的账号名：");
-        stdout().flush();
+    pub fn login_menu(&mut self) {
+        self.println(format!("------------- {} -------------", t!("app.title")));
+        self.print(t!("login.prompt"));
     }
 
-    pub fn user_menu() {
+    pub fn sign_up(&mut self, id: u32) {
+        self.print(t!("signup.prompt", id));
+    }
+
+    pub fn new_name(&mut self) {
+        self.print(t!("signup.name"));
+    }
+
+    /// the main menu, admins get the admin tools too
+    pub fn user_menu(&mut self, admin: bool) {
         let mut table = table!(
-            ["1. 预定航班/大巴车/宾馆房间"],
-            ["2. 取消预定航班/大巴车/宾馆房间"],
-            ["3. 查询航班/大巴车/宾馆房间/客户和预订信息"],
-            ["4. 查询旅行线路"],
-            ["5. 检查预定线路的完整性"],
-            ["0. 退出当前用户"],
-            ["9. 退出程序"]
+            [t!("menu.user.book")],
+            [t!("menu.user.cancel")],
+            [t!("menu.user.query")],
+            [t!("menu.user.travel")],
+            [t!("menu.user.completeness")],
+            [t!("menu.user.suggest")],
+            [t!("menu.user.profile")]
         );
-        table.set_titles(row![bc->"TouringBookingSystem"]);
+        if admin {
+            table.add_row(row![t!("menu.user.admin")]);
+        }
+        table.add_row(row![t!("menu.user.logout")]);
+        table.add_row(row![t!("menu.user.quit")]);
+        table.set_titles(row![bc->t!("app.title")]);
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        table.printstd();
-        print!("$> 选择服务：");
-        stdout().flush();
+        self.print_table(&table);
+        self.print(t!("menu.user.prompt"));
     }
 
-    pub fn booking_menu() {
-        let mut table = table!(
-            ["请输入您要预定的类型："],
-            ["1. 预定航班"],
-            ["2. 预定大巴车"],
-            ["3. 预定宾馆房间"],
-            ["4. 返回上一级"]
-        );
-        table.set_titles(row![bc->"预定服务"]);
+    pub fn booking_menu(&mut self) {
+        let mut items: Vec<String> = CATEGORIES
+            .iter()
+            .map(|x| t!("menu.book.item", x.name()))
+            .collect();
+        items.push(t!("menu.book.package").to_string());
+        items.push(t!("menu.back").to_string());
+        self.menu(t!("menu.book.title"), t!("menu.book.hint"), &items);
+        self.print(t!("menu.type_prompt"));
+    }
+
+    pub fn cancel_menu(&mut self) {
+        let mut items: Vec<String> = CATEGORIES
+            .iter()
+            .map(|x| t!("menu.cancel.item", x.name()))
+            .collect();
+        items.push(t!("menu.cancel.by_ref").to_string());
+        items.push(t!("menu.cancel.package").to_string());
+        items.push(t!("menu.back").to_string());
+        self.menu(t!("menu.cancel.title"), t!("menu.cancel.hint"), &items);
+        self.print(t!("menu.type_prompt"));
+    }
+
+    pub fn query_menu(&mut self) {
+        let mut items: Vec<String> = CATEGORIES
+            .iter()
+            .map(|x| t!("menu.query.item", x.name()))
+            .collect();
+        items.push(t!("menu.query.mine").to_string());
+        items.push(t!("menu.query.by_ref").to_string());
+        items.push(t!("menu.query.export").to_string());
+        items.push(t!("menu.query.itinerary").to_string());
+        items.push(t!("menu.query.itinerary_html").to_string());
+        items.push(t!("menu.back").to_string());
+        self.menu(t!("menu.query.title"), t!("menu.query.hint"), &items);
+        self.print(t!("menu.type_prompt"));
+    }
+
+    /// the profile followed by the fields that can be edited
+    pub fn profile(&mut self, customer: &Customer, account: &LoyaltyAccount) {
+        let mut table = Table::new();
+        for field in ProfileField::ALL.iter() {
+            table.add_row(row![
+                field.label(),
+                customer.get(*field).unwrap_or_default()
+            ]);
+        }
+        table.add_row(row![t!("loyalty.tier"), account.tier().name()]);
+        table.add_row(row![t!("loyalty.balance"), account.balance()]);
+        if let Some(points) = account.to_next_tier() {
+            table.add_row(row![t!("loyalty.to_next_tier"), points]);
+        }
+        table.set_titles(row![bc->t!("profile.title"), bc->customer.id()]);
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        table.printstd();
-        print!("$> 选择类型：");
-        stdout().flush();
+        self.print_table(&table);
+
+        let mut items: Vec<String> = ProfileField::ALL
+            .iter()
+            .map(|x| t!("profile.edit", x.label()))
+            .collect();
+        items.push(t!("loyalty.statement").to_string());
+        items.push(t!("menu.back").to_string());
+        self.menu(t!("profile.title"), t!("profile.hint"), &items);
+        self.print(t!("menu.user.prompt"));
     }
 
-    pub fn cancel_menu() {
-        let mut table = table!(
-            ["请输入您要取消预定的类型："],
-            ["1. 取消预定航班"],
-            ["2. 取消预定大巴车"],
-            ["3. 取消预定宾馆房间"],
-            ["4. 返回上一级"]
-        );
-        table.set_titles(row![bc->"取消预订"]);
+    pub fn redeem_hint(&mut self, account: &LoyaltyAccount) {
+        self.print(t!(
+            "loyalty.redeem_prompt",
+            account.balance(),
+            POINTS_PER_DISCOUNT
+        ));
+    }
+
+    pub fn profile_value_hint(&mut self, field: ProfileField) {
+        self.print(t!("profile.new_value", field.label()));
+    }
+
+    pub fn admin_menu(&mut self) {
+        let items = [
+            t!("admin.search_customers").to_string(),
+            t!("admin.reports").to_string(),
+            t!("admin.pricing").to_string(),
+            t!("menu.back").to_string(),
+        ];
+        self.menu(t!("admin.title"), t!("admin.hint"), &items);
+        self.print(t!("menu.user.prompt"));
+    }
+
+    pub fn customer_search_hint(&mut self) {
+        self.print(t!("admin.search_prompt"));
+    }
+
+    pub fn report_menu(&mut self, kinds: &[ReportKind]) {
+        let mut items: Vec<String> = kinds.iter().map(ReportKind::title).collect();
+        items.push(t!("menu.back").to_string());
+        self.menu(t!("report.title"), t!("report.hint"), &items);
+        self.print(t!("menu.type_prompt"));
+    }
+
+    pub fn report_title(&mut self, title: &str) {
+        self.println(format!("{}:", title));
+    }
+
+    pub fn report_export_hint(&mut self) {
+        self.print(t!("report.export_prompt"));
+    }
+
+    /// items numbered from 1, in the catalog menus the catalogs come first so their
+    /// numbers follow [`CATEGORIES`]
+    fn menu(&mut self, title: &str, hint: &str, items: &[String]) {
+        let mut table = table!([hint]);
+        items.iter().enumerate().for_each(|(i, x)| {
+            table.add_row(row![format!("{}. {}", i + 1, x)]);
+        });
+        table.set_titles(row![bc->title]);
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        table.printstd();
-        print!("$> 选择类型：");
-        stdout().flush();
+        self.print_table(&table);
+    }
+
+    pub fn filter_hint(&mut self, hint: &str) {
+        self.print(t!("common.skip_hint", hint));
     }
 
-    pub fn query_menu() {
+    pub fn sort_menu(&mut self) {
         let mut table = table!(
-            ["请输入您要查询的类型："],
-            ["1. 查询航班"],
-            ["2. 查询大巴车"],
-            ["3. 查询宾馆房间"],
-            ["4. 返回上一级"]
+            [t!("menu.sort.price_asc")],
+            [t!("menu.sort.price_desc")],
+            [t!("menu.sort.city")],
+            [t!("menu.sort.default")]
         );
-        table.set_titles(row![bc->"查询服务"]);
+        table.set_titles(row![bc->t!("menu.sort.title")]);
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
-        table.printstd();
-        print!("$> 选择类型：");
-        stdout().flush();
+        self.print_table(&table);
+        self.print(t!("menu.sort.prompt"));
     }
 
-    pub fn booking<T: ToRow>(hint: &Row, output: &Vec<T>) {
+    pub fn page<T: ToRow>(&mut self, hint: &Row, output: &[T], page: &Page, total_pages: u32) {
         let mut table = Table::new();
         table.set_titles(hint.clone());
         table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
         output.iter().for_each(|x| {
             table.add_row(x.to_row());
         });
-        table.printstd();
-        print!("$> 请选择想要预约的号码/地址：");
-        stdout().flush();
+        self.print_table(&table);
+        self.println(t!("page.status", page.number(), total_pages, page.size()));
+    }
+
+    pub fn page_prompt(&mut self, booking: bool) {
+        self.println(t!("page.commands"));
+        if booking {
+            self.print(t!("page.book_prompt"));
+        } else {
+            self.print("$> ");
+        }
+    }
+
+    pub fn wrong_command(&mut self) {
+        self.println(t!("common.wrong_command"));
     }
 
-    pub fn querying<T: ToRow>(hint: &Row, output: &Vec<T>) {
-        if output.len() == 0 {
-            println!("<!>没有相关预定");
+    pub fn querying<T: ToRow>(&mut self, hint: &Row, output: &[T]) {
+        if output.is_empty() {
+            self.println(t!("query.empty"));
             return;
         }
         let mut table = Table::new();
@@ -106,43 +263,131 @@ impl View {
         output.iter().for_each(|x| {
             table.add_row(x.to_row());
         });
-        table.printstd();
+        self.print_table(&table);
+    }
+
+    /// render a table into a `String` instead of printing it, e.g. for export
+    pub fn render<T: ToRow>(hint: &Row, output: &[T]) -> String {
+        let mut table = Table::new();
+        table.set_titles(hint.clone());
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        output.iter().for_each(|x| {
+            table.add_row(x.to_row());
+        });
+        table.to_string()
+    }
+
+    pub fn success_hint(&mut self) {
+        self.println(t!("common.success"));
+    }
+
+    pub fn payment_approved(&mut self, amount: u32) {
+        self.println(t!("payment.paid", amount));
+    }
+
+    pub fn refund_approved(&mut self, amount: u32) {
+        self.println(t!("payment.refunded", amount));
+    }
+
+    pub fn booking_ref(&mut self, booking_ref: &str) {
+        self.println(t!("booking.success", booking_ref));
     }
 
-    pub fn success_hint() {
-        println!("成功！");
+    pub fn package_hint(&mut self) {
+        self.print(t!("booking.package_prompt"));
     }
 
-    pub fn cancel_hint() {
-        print!("请输入需要取消的航班/大巴/酒店号：");
-        stdout().flush();
+    pub fn start_date_hint(&mut self) {
+        self.print(t!("booking.start_date"));
     }
 
-    pub fn travel_flight() {
-        println!("航班相关预定：");
+    pub fn end_date_hint(&mut self) {
+        self.print(t!("booking.end_date"));
     }
 
-    pub fn travel_bus() {
-        println!("大巴相关预定：");
+    pub fn invalid_period(&mut self) {
+        self.println(t!("booking.invalid_period"));
     }
 
-    pub fn travel_hotel() {
-        println!("酒店相关预定");
+    pub fn booking_ref_hint(&mut self) {
+        self.print(t!("booking.ref_prompt"));
     }
 
-    pub fn check_completeness(success: bool) -> bool {
-        return if success {
-            println!("--- 路线完整 ---");
-            false
+    pub fn reservation_not_found(&mut self) {
+        self.println(t!("booking.not_found"));
+    }
+
+    pub fn cancel_quote(&mut self, quote: &CancelQuote) {
+        let mut table = table!(
+            [t!("cancel.price"), quote.price()],
+            [t!("cancel.fee"), quote.fee()],
+            [t!("cancel.refund"), quote.refund()]
+        );
+        if let Some(hours) = quote.hours_before() {
+            table.add_row(row![t!("cancel.hours_before"), hours]);
+        }
+        table.set_titles(row![bc->t!("cancel.title"), bc->""]);
+        table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+        self.print_table(&table);
+        self.print(t!("cancel.confirm"));
+    }
+
+    pub fn booking_ref_not_found(&mut self, booking_ref: &str) {
+        self.println(t!("booking.ref_not_found", booking_ref));
+    }
+
+    pub fn export_success(&mut self, path: &str) {
+        self.println(t!("booking.export_success", path));
+    }
+
+    pub fn itinerary_empty(&mut self) {
+        self.println(t!("ical.empty"));
+    }
+
+    pub fn cancel_hint(&mut self) {
+        self.print(t!("cancel.prompt"));
+    }
+
+    pub fn travel_category(&mut self, name: &str) {
+        self.println(t!("travel.item", name));
+    }
+
+    pub fn travel_reservation(&mut self) {
+        self.println(t!("travel.reservation"));
+    }
+
+    pub fn suggestion_category(&mut self, name: &str) {
+        self.println(t!("suggest.item", name));
+    }
+
+    pub fn suggestion_prompt(&mut self) {
+        self.print(t!("suggest.prompt"));
+    }
+
+    pub fn no_suggestions(&mut self) {
+        self.println(t!("suggest.empty"));
+    }
+
+    pub fn travel_packages(&mut self) {
+        self.println(t!("travel.package"));
+    }
+
+    pub fn trip_total(&mut self, total: u64) {
+        self.println(t!("travel.total", total));
+    }
+
+    pub fn check_completeness(&mut self, success: bool) -> bool {
+        if success {
+            self.println(t!("completeness.ok"));
         } else {
-            println!("路线不完整，注意<!>：");
-            println!("1. 航班是否覆盖酒店和大巴所在的城市");
-            println!("2. 是否在旅行过程中无法连贯乘坐全部的航班");
-            false
-        };
+            self.println(t!("completeness.broken"));
+            self.println(t!("completeness.hint_city"));
+            self.println(t!("completeness.hint_path"));
+        }
+        false
     }
 
-    pub fn init_check() {
-        println!("正在进行数据库一致性检查...");
+    pub fn init_check(&mut self) {
+        self.println(t!("app.dbcc_running"));
     }
 }
@@ -0,0 +1,71 @@
+use std::collections::HashSet;
+use tour_booking_system::bookable::{category, Bookable, Place, CATEGORIES};
+use tour_booking_system::bus::Bus;
+use tour_booking_system::car_rental::CarRental;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::train::Train;
+use tour_booking_system::ToRow;
+
+#[test]
+fn every_category_has_its_own_res_type() {
+    let res_types: HashSet<u32> = CATEGORIES.iter().map(|x| x.res_type()).collect();
+    assert_eq!(res_types.len(), CATEGORIES.len());
+    for c in CATEGORIES.iter() {
+        assert_eq!(
+            category(c.res_type()).map(|x| x.res_type()),
+            Some(c.res_type())
+        );
+    }
+    assert!(category(0).is_none());
+}
+
+#[test]
+fn headers_match_the_rows() {
+    let flight = Flight::new("F1".into(), 100, "北京".into(), "上海".into());
+    let hotel = Hotels::new("H1".into(), "上海".into(), 100);
+    let bus = Bus::new("B1".into(), "上海".into(), 100);
+    let train = Train::new(
+        "G101".into(),
+        553,
+        "北京".into(),
+        "上海".into(),
+        "二等座".into(),
+        None,
+    );
+    let car = CarRental::new(
+        "C1".into(),
+        "紧凑型".into(),
+        "上海".into(),
+        "杭州".into(),
+        260,
+        8,
+    );
+    assert_eq!(flight.to_row().len(), Flight::CATEGORY.header().len());
+    assert_eq!(hotel.to_row().len(), Hotels::CATEGORY.header().len());
+    assert_eq!(bus.to_row().len(), Bus::CATEGORY.header().len());
+    assert_eq!(train.to_row().len(), Train::CATEGORY.header().len());
+    assert_eq!(car.to_row().len(), CarRental::CATEGORY.header().len());
+}
+
+#[test]
+fn flights_and_trains_are_routes_and_hotels_stay_in_one_place() {
+    assert!(matches!(Flight::CATEGORY.place(), Place::Route(..)));
+    assert!(matches!(Train::CATEGORY.place(), Place::Route(..)));
+    assert_eq!(Hotels::CATEGORY.place().columns(), vec!["location"]);
+    assert_eq!(Bus::CATEGORY.place().columns(), vec!["location"]);
+}
+
+#[test]
+fn only_rental_cars_are_dated() {
+    let dated: Vec<u32> = CATEGORIES
+        .iter()
+        .filter(|x| x.dated())
+        .map(|x| x.res_type())
+        .collect();
+    assert_eq!(dated, vec![CarRental::CATEGORY.res_type()]);
+    assert_eq!(
+        CarRental::CATEGORY.place(),
+        Place::OnRoute("pickup_city", "dropoff_city")
+    );
+}
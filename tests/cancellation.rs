@@ -0,0 +1,200 @@
+mod common;
+
+use common::*;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::bus::Bus;
+use tour_booking_system::cancellation::CancellationPolicy;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+
+const ALICE: u32 = 910_001;
+const BOB: u32 = 910_002;
+
+fn setup() -> mysql::PooledConn {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, ALICE, "alice");
+    add_customer(&mut conn, BOB, "bob");
+    conn
+}
+
+fn teardown(conn: &mut mysql::PooledConn) {
+    remove_customer(conn, ALICE);
+    remove_customer(conn, BOB);
+}
+
+#[test]
+fn fees_of_large_prices_do_not_overflow() {
+    let quote = CancellationPolicy::new(24, 100, true).quote(u32::MAX, Some(1));
+    assert_eq!((quote.fee(), quote.refund()), (u32::MAX, 0));
+    let quote = CancellationPolicy::new(24, 50, true).quote(u32::MAX, Some(1));
+    assert_eq!(quote.fee(), u32::MAX / 2);
+    assert_eq!(quote.fee() + quote.refund(), u32::MAX);
+}
+
+#[test]
+fn unpaid_bookings_refund_nothing() {
+    let quote = CancellationPolicy::new(24, 20, true)
+        .quote(100, Some(1))
+        .unpaid();
+    assert_eq!((quote.price(), quote.fee(), quote.refund()), (100, 0, 0));
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn cancel_flight_keeps_other_customers_booking() {
+    let mut conn = setup();
+    add_flight(&mut conn, "T-CXL-F1", 10, "北京", "上海");
+    let mut db = connection();
+
+    assert!(db
+        .book(&Flight::CATEGORY, ALICE, "T-CXL-F1".to_string())
+        .is_some());
+    assert!(db
+        .book(&Flight::CATEGORY, BOB, "T-CXL-F1".to_string())
+        .is_some());
+
+    assert!(db.cancel(&Flight::CATEGORY, ALICE, "T-CXL-F1".to_string()));
+    assert_eq!(reservation_count(&mut conn, ALICE, 1, "T-CXL-F1"), 0);
+    assert_eq!(reservation_count(&mut conn, BOB, 1, "T-CXL-F1"), 1);
+
+    remove_flight(&mut conn, "T-CXL-F1");
+    teardown(&mut conn);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn cancel_bus_keeps_other_customers_booking() {
+    let mut conn = setup();
+    add_bus(&mut conn, "T-CXL-B1", 10, "上海");
+    let mut db = connection();
+
+    assert!(db
+        .book(&Bus::CATEGORY, ALICE, "T-CXL-B1".to_string())
+        .is_some());
+    assert!(db
+        .book(&Bus::CATEGORY, BOB, "T-CXL-B1".to_string())
+        .is_some());
+
+    assert!(db.cancel(&Bus::CATEGORY, BOB, "T-CXL-B1".to_string()));
+    assert_eq!(reservation_count(&mut conn, ALICE, 3, "T-CXL-B1"), 1);
+    assert_eq!(reservation_count(&mut conn, BOB, 3, "T-CXL-B1"), 0);
+
+    remove_bus(&mut conn, "T-CXL-B1");
+    teardown(&mut conn);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn cancel_hotel_removes_only_own_reservation() {
+    let mut conn = setup();
+    add_hotel(&mut conn, "T-CXL-H1", 10, "上海");
+    let mut db = connection();
+
+    assert!(db
+        .book(&Hotels::CATEGORY, ALICE, "T-CXL-H1".to_string())
+        .is_some());
+    assert!(db
+        .book(&Hotels::CATEGORY, ALICE, "T-CXL-H1".to_string())
+        .is_some());
+    assert!(db
+        .book(&Hotels::CATEGORY, BOB, "T-CXL-H1".to_string())
+        .is_some());
+
+    // two rooms booked by alice, cancelling one keeps the other
+    assert!(db.cancel(&Hotels::CATEGORY, ALICE, "T-CXL-H1".to_string()));
+    assert_eq!(reservation_count(&mut conn, ALICE, 2, "T-CXL-H1"), 1);
+    assert_eq!(reservation_count(&mut conn, BOB, 2, "T-CXL-H1"), 1);
+
+    remove_hotel(&mut conn, "T-CXL-H1");
+    teardown(&mut conn);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn cancel_reservation_rejects_other_customers_reservation() {
+    let mut conn = setup();
+    add_flight(&mut conn, "T-CXL-F2", 10, "北京", "上海");
+    let mut db = connection();
+
+    let booking_ref = db
+        .book(&Flight::CATEGORY, BOB, "T-CXL-F2".to_string())
+        .unwrap();
+    let bobs = db.query_reservation_by_ref(BOB, &booking_ref).unwrap();
+
+    assert!(!db.cancel_reservation(ALICE, bobs.id()));
+    assert!(!db.cancel_by_ref(ALICE, &booking_ref));
+    assert_eq!(reservation_count(&mut conn, BOB, 1, "T-CXL-F2"), 1);
+
+    assert!(db.cancel_reservation(BOB, bobs.id()));
+    assert_eq!(reservation_count(&mut conn, BOB, 1, "T-CXL-F2"), 0);
+
+    remove_flight(&mut conn, "T-CXL-F2");
+    teardown(&mut conn);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn late_cancellation_records_refund_minus_fee() {
+    let mut conn = setup();
+    add_flight(&mut conn, "T-CXL-F3", 10, "北京", "上海");
+    set_flight_departure(&mut conn, "T-CXL-F3", 2);
+    set_policy(&mut conn, 1, "T-CXL-F3", 24, 20, true);
+    let mut db = connection();
+
+    let booking_ref = db
+        .book(&Flight::CATEGORY, ALICE, "T-CXL-F3".to_string())
+        .unwrap();
+    let id = db
+        .query_reservation_by_ref(ALICE, &booking_ref)
+        .unwrap()
+        .id();
+
+    let (_, quote) = db.cancel_quote(ALICE, id).unwrap();
+    assert_eq!(quote.fee(), 20);
+    assert_eq!(quote.refund(), 80);
+
+    assert!(db.cancel_reservation(ALICE, id));
+    assert_eq!(db.query_refund(id), Some(80));
+
+    remove_policy(&mut conn, 1, "T-CXL-F3");
+    remove_flight(&mut conn, "T-CXL-F3");
+    teardown(&mut conn);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn each_type_has_a_single_type_level_policy() {
+    use mysql::params;
+    use mysql::prelude::Queryable;
+
+    // 没有用到的类型，避免影响其他测试
+    const RES_TYPE: u32 = 99;
+    let mut conn = raw_conn();
+    let add_type_policy = |conn: &mut mysql::PooledConn, fee_percent: u32| {
+        conn.exec_drop(
+            "INSERT INTO CancellationPolicy (res_type, res_id, free_hours, fee_percent, refundable)
+             VALUES (:res_type, NULL, 24, :fee_percent, TRUE)",
+            params! { "res_type" => RES_TYPE, "fee_percent" => fee_percent },
+        )
+    };
+    assert!(add_type_policy(&mut conn, 10).is_ok());
+    assert!(add_type_policy(&mut conn, 30).is_err());
+
+    // 单项政策优先于类型级政策
+    set_policy(&mut conn, RES_TYPE, "T-CXL-X1", 0, 50, true);
+    let mut db = connection();
+    assert_eq!(
+        db.cancellation_policy(RES_TYPE, "T-CXL-X1").fee_percent(),
+        50
+    );
+    assert_eq!(
+        db.cancellation_policy(RES_TYPE, "T-CXL-X2").fee_percent(),
+        10
+    );
+
+    conn.exec_drop(
+        "DELETE FROM CancellationPolicy WHERE res_type = :res_type",
+        params! { "res_type" => RES_TYPE },
+    )
+    .unwrap();
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use common::*;
+use mysql::prelude::Queryable;
+use std::io::Cursor;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::car_rental::CarRental;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::period::Period;
+use tour_booking_system::t;
+
+const FRANK: u32 = 910_501;
+
+fn period(start: &str, end: &str) -> Period {
+    Period::new(start.parse().unwrap(), end.parse().unwrap()).unwrap()
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn cars_are_shared_by_periods_that_do_not_overlap() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, FRANK, "frank");
+    add_car(&mut conn, "T-CAR-1", 1, "上海", "杭州");
+    let mut db = connection();
+    let car = || "T-CAR-1".to_string();
+
+    // 没有日期不能预定
+    assert!(db.book(&CarRental::CATEGORY, FRANK, car()).is_none());
+    let booking_ref = db
+        .book_period(
+            &CarRental::CATEGORY,
+            FRANK,
+            car(),
+            period("2030-05-01", "2030-05-03"),
+        )
+        .unwrap();
+    assert!(db
+        .book_period(
+            &CarRental::CATEGORY,
+            FRANK,
+            car(),
+            period("2030-05-02", "2030-05-04")
+        )
+        .is_none());
+    // 还车当天按天收费时不计，可以再租出去
+    assert!(db
+        .book_period(
+            &CarRental::CATEGORY,
+            FRANK,
+            car(),
+            period("2030-05-03", "2030-05-04")
+        )
+        .is_some());
+    // 当天取还占用当天
+    assert!(db
+        .book_period(
+            &CarRental::CATEGORY,
+            FRANK,
+            car(),
+            period("2030-05-04", "2030-05-04")
+        )
+        .is_some());
+    assert!(db
+        .book_period(
+            &CarRental::CATEGORY,
+            FRANK,
+            car(),
+            period("2030-05-04", "2030-05-04")
+        )
+        .is_none());
+    assert!(db.check_consistency());
+
+    let reservation = db.query_reservation_by_ref(FRANK, &booking_ref).unwrap();
+    assert_eq!(
+        reservation.period(),
+        Some(period("2030-05-01", "2030-05-03"))
+    );
+    // 日租金 200，两天
+    let (_, quote) = db.cancel_quote(FRANK, reservation.id()).unwrap();
+    assert_eq!(quote.price(), 400);
+    assert_eq!(db.trip_total(FRANK), 400 + 200 + 200);
+
+    remove_car(&mut conn, "T-CAR-1");
+    remove_customer(&mut conn, FRANK);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn cars_must_be_picked_up_and_dropped_off_on_the_route() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, FRANK, "frank");
+    add_flight(&mut conn, "T-CAR-F1", 5, "北京", "上海");
+    add_car(&mut conn, "T-CAR-2", 5, "上海", "北京");
+    add_car(&mut conn, "T-CAR-3", 5, "上海", "杭州");
+    let mut db = connection();
+    db.book(&Flight::CATEGORY, FRANK, "T-CAR-F1".to_string())
+        .unwrap();
+
+    let completeness = |script: &str| {
+        let out = Transcript::default();
+        let mut controller = Controller::with_io(
+            connection(),
+            Box::new(Cursor::new(format!("{}\n{}\n9\n", FRANK, script))),
+            Box::new(out.clone()),
+        );
+        controller.run();
+        out.text()
+    };
+
+    // 预定菜单中租车在最后一项，航班的积分够抵扣，不使用积分
+    let book_car = |car: &str| format!("1\n5\n{}\n2030-05-01\n2030-05-02\nn\n", car);
+    let output = completeness(&format!("{}5\n4", book_car("T-CAR-2")));
+    assert!(output.contains(t!("completeness.ok")));
+    assert!(output.contains(&t!("travel.item", t!("type.car"))));
+    assert!(output.contains(&t!("travel.total", 200)));
+
+    let output = completeness(&format!("{}5", book_car("T-CAR-3")));
+    assert!(output.contains(t!("completeness.broken")));
+
+    remove_flight(&mut conn, "T-CAR-F1");
+    remove_car(&mut conn, "T-CAR-2");
+    remove_car(&mut conn, "T-CAR-3");
+    remove_customer(&mut conn, FRANK);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn rentals_whose_price_overflows_are_rejected() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, FRANK, "frank");
+    add_car(&mut conn, "T-CAR-4", 1, "上海", "杭州");
+    conn.exec_drop(
+        "UPDATE CarRentals SET price = 100000 WHERE car_num = 'T-CAR-4'",
+        (),
+    )
+    .unwrap();
+    let mut db = connection();
+
+    // 十万日租金乘以几百万天超出了价格的范围
+    assert!(db
+        .book_period(
+            &CarRental::CATEGORY,
+            FRANK,
+            "T-CAR-4".to_string(),
+            period("2030-01-01", "9999-12-31")
+        )
+        .is_none());
+    assert_eq!(reservation_count(&mut conn, FRANK, 5, "T-CAR-4"), 0);
+
+    remove_car(&mut conn, "T-CAR-4");
+    remove_customer(&mut conn, FRANK);
+}
@@ -0,0 +1,134 @@
+use mysql::Value;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::car_rental::CarRental;
+use tour_booking_system::catalog_filter::{CatalogFilter, Page, PageCommand, SortOrder};
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::query::Query;
+
+fn select_flights(filter: &CatalogFilter) -> Query {
+    filter.condition(Query::new("SELECT 1 FROM Flights"), &Flight::CATEGORY)
+}
+
+#[test]
+fn empty_filter_matches_everything() {
+    let query = select_flights(&CatalogFilter::new());
+    assert_eq!(query.sql(), "SELECT 1 FROM Flights WHERE 1 = 1");
+    assert!(query.params().is_empty());
+}
+
+#[test]
+fn combined_filters_bind_in_order() {
+    let mut filter = CatalogFilter::new();
+    filter.set_from_city(Some("北京".to_string()));
+    filter.set_arrive_city(Some("上海".to_string()));
+    filter.set_min_price(Some(100));
+    filter.set_max_price(Some(500));
+    filter.set_available_only(true);
+
+    let query = select_flights(&filter);
+    assert_eq!(
+        query.sql(),
+        "SELECT 1 FROM Flights WHERE 1 = 1 AND from_city = ? AND arrive_city = ? \
+         AND price >= ? AND price <= ? AND num_available > 0"
+    );
+    assert_eq!(
+        query.params(),
+        &[
+            Value::from("北京"),
+            Value::from("上海"),
+            Value::from(100u32),
+            Value::from(500u32),
+        ]
+    );
+}
+
+#[test]
+fn filters_only_use_the_columns_of_the_category() {
+    let mut filter = CatalogFilter::new();
+    filter.set_from_city(Some("北京".to_string()));
+    filter.set_location(Some("杭州".to_string()));
+    filter.set_max_price(Some(300));
+
+    // 航班只按出发/到达城市过滤
+    let query = select_flights(&filter);
+    assert_eq!(
+        query.sql(),
+        "SELECT 1 FROM Flights WHERE 1 = 1 AND from_city = ? AND price <= ?"
+    );
+    assert_eq!(query.params(), &[Value::from("北京"), Value::from(300u32)]);
+
+    // 酒店只按所在城市过滤
+    let query = filter.condition(Query::new("SELECT 1 FROM Hotels"), &Hotels::CATEGORY);
+    assert_eq!(
+        query.sql(),
+        "SELECT 1 FROM Hotels WHERE 1 = 1 AND location = ? AND price <= ?"
+    );
+    assert_eq!(query.params(), &[Value::from("杭州"), Value::from(300u32)]);
+}
+
+#[test]
+fn pages_cover_every_row() {
+    let page = Page::new(1, 20);
+    assert_eq!(page.total_pages(0), 1);
+    assert_eq!(page.total_pages(20), 1);
+    assert_eq!(page.total_pages(21), 2);
+    assert_eq!(Page::new(3, 20).offset(), 40);
+    // 页码和每页条数至少为 1
+    assert_eq!(Page::new(0, 0), Page::new(1, 1));
+}
+
+#[test]
+fn paging_stays_within_the_pages() {
+    let mut page = Page::new(1, 10);
+    page.apply(PageCommand::Previous, 3);
+    assert_eq!(page.number(), 1);
+    page.apply(PageCommand::Next, 3);
+    page.apply(PageCommand::Next, 3);
+    page.apply(PageCommand::Next, 3);
+    assert_eq!(page.number(), 3);
+    page.apply(PageCommand::Jump(99), 3);
+    assert_eq!(page.number(), 3);
+    page.set_number(0);
+    assert_eq!(page.number(), 1);
+
+    // 改变每页条数后回到第一页
+    page.apply(PageCommand::Jump(2), 3);
+    page.apply(PageCommand::Size(5), 3);
+    assert_eq!((page.number(), page.size()), (1, 5));
+}
+
+#[test]
+fn paging_commands_never_look_like_ids() {
+    assert_eq!(PageCommand::parse(":n"), Some(PageCommand::Next));
+    assert_eq!(PageCommand::parse(" :p "), Some(PageCommand::Previous));
+    assert_eq!(PageCommand::parse(":g12"), Some(PageCommand::Jump(12)));
+    assert_eq!(PageCommand::parse(":s5"), Some(PageCommand::Size(5)));
+    assert_eq!(PageCommand::parse(":q"), Some(PageCommand::Back));
+    assert_eq!(PageCommand::parse(":gx"), None);
+    // 和分页命令同名的号码仍然可以预定
+    for id in ["n", "p", "q", "g2", "s10"].iter() {
+        assert_eq!(PageCommand::parse(id), None);
+    }
+}
+
+#[test]
+fn sorting_breaks_ties_by_key() {
+    assert_eq!(SortOrder::Default.order_by(&Flight::CATEGORY), "flight_num");
+    assert_eq!(
+        SortOrder::PriceAsc.order_by(&Hotels::CATEGORY),
+        "price ASC, hotel_num"
+    );
+    assert_eq!(
+        SortOrder::PriceDesc.order_by(&Flight::CATEGORY),
+        "price DESC, flight_num"
+    );
+    assert_eq!(
+        SortOrder::City.order_by(&Flight::CATEGORY),
+        "from_city, arrive_city, flight_num"
+    );
+    assert_eq!(
+        SortOrder::City.order_by(&CarRental::CATEGORY),
+        "pickup_city, dropoff_city, car_num"
+    );
+}
@@ -0,0 +1,354 @@
+//! Helpers shared by the integration tests.
+//!
+//! These tests talk to a real `BookingSystem` database (see README), so they are
+//! `#[ignore]`d by default. Run them with `cargo test -- --ignored`; the database
+//! url can be overridden through `TOUR_BOOKING_URL`.
+#![allow(dead_code)]
+
+use mysql::prelude::Queryable;
+use mysql::{params, Pool, PooledConn};
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+use tour_booking_system::config::DEFAULT_URL;
+use tour_booking_system::mysql_connection::MySQLConnection;
+
+pub fn url() -> String {
+    std::env::var("TOUR_BOOKING_URL").unwrap_or_else(|_| DEFAULT_URL.to_string())
+}
+
+pub fn connection() -> MySQLConnection {
+    MySQLConnection::new(&url())
+}
+
+pub fn raw_conn() -> PooledConn {
+    Pool::new(url().as_str()).unwrap().get_conn().unwrap()
+}
+
+pub fn add_customer(conn: &mut PooledConn, id: u32, name: &str) {
+    remove_customer(conn, id);
+    conn.exec_drop(
+        "INSERT INTO Customer (name, id) VALUES (:name, :id)",
+        params! { "name" => name, "id" => id },
+    )
+    .unwrap();
+}
+
+pub fn remove_customer(conn: &mut PooledConn, id: u32) {
+    conn.exec_drop(
+        "DELETE FROM Payment WHERE customer_id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM LoyaltyLedger WHERE customer_id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Notification WHERE customer_id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Refund WHERE customer_id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Reservation WHERE customer_id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM PackageRefund WHERE customer_id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM PackageBooking WHERE customer_id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Customer WHERE id = :id",
+        params! { "id" => id },
+    )
+    .unwrap();
+}
+
+pub fn add_flight(conn: &mut PooledConn, flight_num: &str, seats: u32, from: &str, to: &str) {
+    remove_flight(conn, flight_num);
+    conn.exec_drop(
+        "INSERT INTO Flights (flight_num, price, num_seat, num_available, from_city, arrive_city)
+         VALUES (:flight_num, 100, :seats, :seats, :from_city, :arrive_city)",
+        params! {
+            "flight_num" => flight_num,
+            "seats" => seats,
+            "from_city" => from,
+            "arrive_city" => to,
+        },
+    )
+    .unwrap();
+}
+
+pub fn remove_flight(conn: &mut PooledConn, flight_num: &str) {
+    conn.exec_drop(
+        "DELETE FROM Reservation WHERE res_type = 1 AND res_id = :res_id",
+        params! { "res_id" => flight_num },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Flights WHERE flight_num = :flight_num",
+        params! { "flight_num" => flight_num },
+    )
+    .unwrap();
+}
+
+pub fn add_hotel(conn: &mut PooledConn, hotel_num: &str, rooms: u32, location: &str) {
+    remove_hotel(conn, hotel_num);
+    conn.exec_drop(
+        "INSERT INTO Hotels (hotel_num, location, price, num_rooms, num_available)
+         VALUES (:hotel_num, :location, 100, :rooms, :rooms)",
+        params! {
+            "hotel_num" => hotel_num,
+            "location" => location,
+            "rooms" => rooms,
+        },
+    )
+    .unwrap();
+}
+
+pub fn remove_hotel(conn: &mut PooledConn, hotel_num: &str) {
+    conn.exec_drop(
+        "DELETE FROM Reservation WHERE res_type = 2 AND res_id = :res_id",
+        params! { "res_id" => hotel_num },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Hotels WHERE hotel_num = :hotel_num",
+        params! { "hotel_num" => hotel_num },
+    )
+    .unwrap();
+}
+
+pub fn add_bus(conn: &mut PooledConn, bus_num: &str, seats: u32, location: &str) {
+    remove_bus(conn, bus_num);
+    conn.exec_drop(
+        "INSERT INTO Bus (bus_num, location, price, num_bus, num_available)
+         VALUES (:bus_num, :location, 100, :seats, :seats)",
+        params! {
+            "bus_num" => bus_num,
+            "location" => location,
+            "seats" => seats,
+        },
+    )
+    .unwrap();
+}
+
+pub fn remove_bus(conn: &mut PooledConn, bus_num: &str) {
+    conn.exec_drop(
+        "DELETE FROM Reservation WHERE res_type = 3 AND res_id = :res_id",
+        params! { "res_id" => bus_num },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Bus WHERE bus_num = :bus_num",
+        params! { "bus_num" => bus_num },
+    )
+    .unwrap();
+}
+
+/// number of reservations `customer_id` holds on the given item, cancelled and
+/// expired ones are kept as history and not counted
+pub fn reservation_count(
+    conn: &mut PooledConn,
+    customer_id: u32,
+    res_type: u32,
+    res_id: &str,
+) -> u32 {
+    conn.exec_first(
+        "SELECT count(*) FROM Reservation
+         WHERE customer_id = :customer_id AND res_type = :res_type AND res_id = :res_id
+           AND status NOT IN ('cancelled', 'expired')",
+        params! {
+            "customer_id" => customer_id,
+            "res_type" => res_type,
+            "res_id" => res_id,
+        },
+    )
+    .unwrap()
+    .unwrap()
+}
+
+pub fn set_flight_departure(conn: &mut PooledConn, flight_num: &str, hours_from_now: i64) {
+    conn.exec_drop(
+        "UPDATE Flights SET depart_time = NOW() + INTERVAL :hours HOUR
+         WHERE flight_num = :flight_num",
+        params! { "hours" => hours_from_now, "flight_num" => flight_num },
+    )
+    .unwrap();
+}
+
+pub fn set_policy(
+    conn: &mut PooledConn,
+    res_type: u32,
+    res_id: &str,
+    free_hours: u32,
+    fee_percent: u32,
+    refundable: bool,
+) {
+    remove_policy(conn, res_type, res_id);
+    conn.exec_drop(
+        "INSERT INTO CancellationPolicy (res_type, res_id, free_hours, fee_percent, refundable)
+         VALUES (:res_type, :res_id, :free_hours, :fee_percent, :refundable)",
+        params! {
+            "res_type" => res_type,
+            "res_id" => res_id,
+            "free_hours" => free_hours,
+            "fee_percent" => fee_percent,
+            "refundable" => refundable,
+        },
+    )
+    .unwrap();
+}
+
+pub fn remove_policy(conn: &mut PooledConn, res_type: u32, res_id: &str) {
+    conn.exec_drop(
+        "DELETE FROM CancellationPolicy WHERE res_type = :res_type AND res_id = :res_id",
+        params! { "res_type" => res_type, "res_id" => res_id },
+    )
+    .unwrap();
+}
+
+/// an output stream that keeps what was written so the test can read it back
+#[derive(Clone, Default)]
+pub struct Transcript(Rc<RefCell<Vec<u8>>>);
+
+impl Transcript {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Transcript {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn add_train(conn: &mut PooledConn, train_num: &str, seats: u32, from: &str, to: &str) {
+    remove_train(conn, train_num);
+    conn.exec_drop(
+        "INSERT INTO Trains
+            (train_num, price, num_seat, num_available, from_city, arrive_city, seat_class, depart_time)
+         VALUES (:train_num, 100, :seats, :seats, :from_city, :arrive_city, '二等座', NOW() + INTERVAL 2 DAY)",
+        params! {
+            "train_num" => train_num,
+            "seats" => seats,
+            "from_city" => from,
+            "arrive_city" => to,
+        },
+    )
+    .unwrap();
+}
+
+pub fn remove_train(conn: &mut PooledConn, train_num: &str) {
+    conn.exec_drop(
+        "DELETE FROM Reservation WHERE res_type = 4 AND res_id = :res_id",
+        params! { "res_id" => train_num },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Trains WHERE train_num = :train_num",
+        params! { "train_num" => train_num },
+    )
+    .unwrap();
+}
+
+pub fn add_car(conn: &mut PooledConn, car_num: &str, cars: u32, pickup: &str, dropoff: &str) {
+    remove_car(conn, car_num);
+    conn.exec_drop(
+        "INSERT INTO CarRentals
+            (car_num, price, num_cars, num_available, vehicle_class, pickup_city, dropoff_city)
+         VALUES (:car_num, 200, :cars, :cars, '紧凑型', :pickup_city, :dropoff_city)",
+        params! {
+            "car_num" => car_num,
+            "cars" => cars,
+            "pickup_city" => pickup,
+            "dropoff_city" => dropoff,
+        },
+    )
+    .unwrap();
+}
+
+pub fn remove_car(conn: &mut PooledConn, car_num: &str) {
+    conn.exec_drop(
+        "DELETE FROM Reservation WHERE res_type = 5 AND res_id = :res_id",
+        params! { "res_id" => car_num },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM CarRentals WHERE car_num = :car_num",
+        params! { "car_num" => car_num },
+    )
+    .unwrap();
+}
+
+/// a tour package of `items`, given as `(res_type, res_id)`
+pub fn add_package(conn: &mut PooledConn, package_id: &str, price: u32, items: &[(u32, &str)]) {
+    remove_package(conn, package_id);
+    conn.exec_drop(
+        "INSERT INTO Package (package_id, name, price) VALUES (:package_id, :package_id, :price)",
+        params! {
+            "package_id" => package_id,
+            "price" => price,
+        },
+    )
+    .unwrap();
+    for (res_type, res_id) in items {
+        conn.exec_drop(
+            "INSERT INTO PackageItem (package_id, res_type, res_id) VALUES (:package_id, :res_type, :res_id)",
+            params! {
+                "package_id" => package_id,
+                "res_type" => res_type,
+                "res_id" => res_id,
+            },
+        )
+        .unwrap();
+    }
+}
+
+pub fn remove_package(conn: &mut PooledConn, package_id: &str) {
+    conn.exec_drop(
+        "DELETE FROM PackageItem WHERE package_id = :package_id",
+        params! { "package_id" => package_id },
+    )
+    .unwrap();
+    conn.exec_drop(
+        "DELETE FROM Package WHERE package_id = :package_id",
+        params! { "package_id" => package_id },
+    )
+    .unwrap();
+}
+
+pub fn set_admin(conn: &mut PooledConn, id: u32, admin: bool) {
+    conn.exec_drop(
+        "UPDATE Customer SET is_admin = :admin WHERE id = :id",
+        params! { "admin" => admin, "id" => id },
+    )
+    .unwrap();
+}
+
+pub fn pending_notification_count(conn: &mut PooledConn, id: u32) -> u32 {
+    conn.exec_first(
+        "SELECT COUNT(*) FROM Notification WHERE customer_id = :id AND sent_at IS NULL",
+        params! { "id" => id },
+    )
+    .unwrap()
+    .unwrap()
+}
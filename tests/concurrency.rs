@@ -0,0 +1,132 @@
+mod common;
+
+use common::*;
+use mysql::prelude::Queryable;
+use mysql::{params, Pool};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use tour_booking_system::bookable::{Bookable, Category};
+use tour_booking_system::bus::Bus;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::mysql_connection::MySQLConnection;
+
+const FIRST_CUSTOMER: u32 = 910_201;
+const THREADS: u32 = 16;
+
+fn total_reservations(conn: &mut mysql::PooledConn, res_type: u32, res_id: &str) -> u32 {
+    conn.exec_first(
+        "SELECT count(*) FROM Reservation WHERE res_type = :res_type AND res_id = :res_id",
+        params! { "res_type" => res_type, "res_id" => res_id },
+    )
+    .unwrap()
+    .unwrap()
+}
+
+/// every thread books `tries` times at once for its own customer,
+/// returns how many of the bookings succeeded
+fn book_concurrently(category: &'static Category, res_id: &str, tries: u32) -> u32 {
+    let pool = Pool::new(url().as_str()).unwrap();
+    let barrier = Arc::new(Barrier::new(THREADS as usize));
+    let handles: Vec<_> = (0..THREADS)
+        .map(|i| {
+            let pool = pool.clone();
+            let barrier = barrier.clone();
+            let res_id = res_id.to_string();
+            thread::spawn(move || {
+                let mut db = MySQLConnection::from_pool(&pool);
+                barrier.wait();
+                (0..tries)
+                    .filter(|_| {
+                        let user_id = FIRST_CUSTOMER + i;
+                        db.book(category, user_id, res_id.clone()).is_some()
+                    })
+                    .count() as u32
+            })
+        })
+        .collect();
+    handles.into_iter().map(|x| x.join().unwrap()).sum()
+}
+
+fn setup() -> mysql::PooledConn {
+    let mut conn = raw_conn();
+    for i in 0..THREADS {
+        add_customer(&mut conn, FIRST_CUSTOMER + i, "stress");
+    }
+    conn
+}
+
+fn teardown(conn: &mut mysql::PooledConn) {
+    for i in 0..THREADS {
+        remove_customer(conn, FIRST_CUSTOMER + i);
+    }
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn flight_is_never_overbooked() {
+    let mut conn = setup();
+    add_flight(&mut conn, "T-CON-F1", 5, "北京", "上海");
+
+    let booked = book_concurrently(&Flight::CATEGORY, "T-CON-F1", 3);
+    assert_eq!(booked, 5);
+    assert_eq!(total_reservations(&mut conn, 1, "T-CON-F1"), 5);
+
+    remove_flight(&mut conn, "T-CON-F1");
+    teardown(&mut conn);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn hotel_and_bus_are_never_overbooked() {
+    let mut conn = setup();
+    add_hotel(&mut conn, "T-CON-H1", 7, "上海");
+    add_bus(&mut conn, "T-CON-B1", 3, "上海");
+
+    assert_eq!(book_concurrently(&Hotels::CATEGORY, "T-CON-H1", 2), 7);
+    assert_eq!(total_reservations(&mut conn, 2, "T-CON-H1"), 7);
+    assert_eq!(book_concurrently(&Bus::CATEGORY, "T-CON-B1", 2), 3);
+    assert_eq!(total_reservations(&mut conn, 3, "T-CON-B1"), 3);
+
+    remove_hotel(&mut conn, "T-CON-H1");
+    remove_bus(&mut conn, "T-CON-B1");
+    teardown(&mut conn);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn a_reservation_is_cancelled_only_once() {
+    let mut conn = setup();
+    add_flight(&mut conn, "T-CON-F2", 5, "北京", "上海");
+    let mut db = connection();
+    let booking_ref = db
+        .book(&Flight::CATEGORY, FIRST_CUSTOMER, "T-CON-F2".to_string())
+        .unwrap();
+    let id = db
+        .query_reservation_by_ref(FIRST_CUSTOMER, &booking_ref)
+        .unwrap()
+        .id();
+
+    let pool = Pool::new(url().as_str()).unwrap();
+    let barrier = Arc::new(Barrier::new(THREADS as usize));
+    let cancelled = (0..THREADS)
+        .map(|_| {
+            let pool = pool.clone();
+            let barrier = barrier.clone();
+            thread::spawn(move || {
+                let mut db = MySQLConnection::from_pool(&pool);
+                barrier.wait();
+                db.cancel_reservation(FIRST_CUSTOMER, id)
+            })
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
+        .map(|x| x.join().unwrap())
+        .filter(|x| *x)
+        .count();
+    assert_eq!(cancelled, 1);
+    assert!(db.query_refund(id).is_some());
+
+    remove_flight(&mut conn, "T-CON-F2");
+    teardown(&mut conn);
+}
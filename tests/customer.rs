@@ -0,0 +1,111 @@
+mod common;
+
+use common::*;
+use std::io::Cursor;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::customer::ProfileField;
+use tour_booking_system::t;
+
+const JUDY: u32 = 910_801;
+const KEN: u32 = 910_802;
+
+#[test]
+fn profile_fields_are_validated_and_normalized() {
+    let ok = |field: ProfileField, value: &str| field.validate(value).unwrap();
+    assert_eq!(
+        ok(ProfileField::Email, " judy@example.com "),
+        Some("judy@example.com".to_string())
+    );
+    assert_eq!(
+        ok(ProfileField::IdNumber, "e1234567"),
+        Some("E1234567".to_string())
+    );
+    assert_eq!(
+        ok(ProfileField::BirthDate, "1990-1-31"),
+        Some("1990-01-31".to_string())
+    );
+    assert!(ok(ProfileField::Phone, "+86 138-0000-0000").is_some());
+    assert!(ok(ProfileField::Nationality, "中国").is_some());
+    // 空值清空可选字段
+    assert_eq!(ok(ProfileField::Phone, ""), None);
+
+    for (field, value) in [
+        (ProfileField::Name, "  "),
+        (ProfileField::Email, "judy@example"),
+        (ProfileField::Email, "judy example@example.com"),
+        (ProfileField::Email, "@example.com"),
+        (ProfileField::Phone, "12345"),
+        (ProfileField::Phone, "138-abc-0000"),
+        (ProfileField::BirthDate, "2999-01-01"),
+        (ProfileField::BirthDate, "1990-02-30"),
+        (ProfileField::IdNumber, "E-123"),
+        (ProfileField::Nationality, "C4"),
+    ]
+    .iter()
+    {
+        assert!(field.validate(value).is_err(), "{:?} {}", field, value);
+    }
+    assert_eq!(
+        ProfileField::Email.validate("x"),
+        Err(t!("profile.invalid_email"))
+    );
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn profiles_are_edited_from_the_menu() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, JUDY, "judy");
+
+    let out = Transcript::default();
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!(
+            "{}\n7\n1\nJudy Hopps\n2\nnot-an-email\njudy@example.com\n0\n9\n",
+            JUDY
+        ))),
+        Box::new(out.clone()),
+    );
+    controller.run();
+    assert!(out.text().contains(t!("profile.invalid_email")));
+
+    let customer = connection().query_customer(JUDY).unwrap();
+    assert_eq!(customer.name(), "Judy Hopps");
+    assert_eq!(customer.email(), Some("judy@example.com"));
+    assert_eq!(customer.phone(), None);
+
+    remove_customer(&mut conn, JUDY);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn only_admins_search_customers() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, JUDY, "judy");
+    add_customer(&mut conn, KEN, "ken");
+    let mut db = connection();
+    db.update_profile(JUDY, ProfileField::Phone, Some("13800009108".to_string()));
+
+    let search = |user: u32, keyword: &str| {
+        let mut controller = Controller::with_io(
+            connection(),
+            Box::new(Cursor::new(format!("{}\n", user))),
+            Box::new(Transcript::default()),
+        );
+        controller.login_view().unwrap();
+        controller
+            .search_customers(keyword)
+            .iter()
+            .map(|x| x.id())
+            .collect::<Vec<_>>()
+    };
+
+    assert!(search(KEN, "0000910").is_empty());
+    set_admin(&mut conn, KEN, true);
+    assert_eq!(search(KEN, "0000910"), vec![JUDY]);
+    // % 按字面匹配
+    assert!(!search(KEN, "%").contains(&JUDY));
+
+    remove_customer(&mut conn, JUDY);
+    remove_customer(&mut conn, KEN);
+}
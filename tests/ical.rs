@@ -0,0 +1,118 @@
+mod common;
+
+use common::*;
+use mysql::params;
+use mysql::prelude::Queryable;
+use std::io::Cursor;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::ical::{calendar, Event, EventTime};
+use tour_booking_system::period::Date;
+
+const MIA: u32 = 911_001;
+
+fn time(s: &str) -> EventTime {
+    s.parse().unwrap()
+}
+
+fn event(booking_ref: &str, summary: &str, start: &str, end: Option<&str>) -> Event {
+    Event::new(
+        booking_ref.to_string(),
+        summary.to_string(),
+        "北京".to_string(),
+        time(start),
+        end.map(time),
+    )
+}
+
+#[test]
+fn event_times_are_parsed() {
+    let date = Date::new(2024, 5, 1).unwrap();
+    assert_eq!(time("2024-05-01"), EventTime::Date(date));
+    assert_eq!(time("2024-05-01 07:30"), EventTime::DateTime(date, 7, 30));
+    assert_eq!(time("2024-05-01 07:30").to_string(), "20240501T073000");
+    assert!("2024-05-01 24:00".parse::<EventTime>().is_err());
+    assert!("2024-05-01 7".parse::<EventTime>().is_err());
+}
+
+#[test]
+fn calendars_follow_rfc_5545() {
+    let ics = calendar(&[
+        event("CAR123", "租车 C1", "2024-05-03", Some("2024-05-05")),
+        event(
+            "ABC234",
+            "航班 MU5101 北京 → 上海",
+            "2024-05-01 07:30",
+            Some("2024-05-01 09:45"),
+        ),
+    ]);
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    assert_eq!(ics.matches("BEGIN:VEVENT").count(), 2);
+    // 按开始时间排序
+    assert!(ics.find("UID:ABC234").unwrap() < ics.find("UID:CAR123").unwrap());
+    assert!(ics.contains("DTSTART:20240501T073000\r\nDTEND:20240501T094500\r\n"));
+    // 全天事件的结束日期不包含在内
+    assert!(ics.contains("DTSTART;VALUE=DATE:20240503\r\nDTEND;VALUE=DATE:20240506\r\n"));
+    assert!(ics.contains("LOCATION:北京\r\n"));
+    assert!(ics.contains("ABC234"));
+}
+
+#[test]
+fn long_lines_are_folded_and_text_is_escaped() {
+    let summary = format!("Hotel; {}, \\", "大".repeat(40));
+    let ics = calendar(&[event("ABC234", &summary, "2024-05-01 14:00", None)]);
+    for line in ics.split("\r\n") {
+        assert!(line.len() <= 75, "{}", line);
+    }
+    let unfolded = ics.replace("\r\n ", "");
+    assert!(unfolded.contains(&format!("SUMMARY:Hotel\\; {}\\, \\\\\r\n", "大".repeat(40))));
+    // 没有结束时间就不写 DTEND
+    assert!(!ics.contains("DTEND"));
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn itinerary_has_one_event_per_timed_item() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, MIA, "mia");
+    add_flight(&mut conn, "T-ICS-F1", 10, "北京", "上海");
+    add_hotel(&mut conn, "T-ICS-H1", 10, "上海");
+    conn.exec_drop(
+        "UPDATE Flights SET depart_time = '2030-05-01 07:30', arrive_time = '2030-05-01 09:45'
+         WHERE flight_num = :id",
+        params! { "id" => "T-ICS-F1" },
+    )
+    .unwrap();
+
+    let mut db = connection();
+    let flight_ref = db
+        .book(&Flight::CATEGORY, MIA, "T-ICS-F1".to_string())
+        .unwrap();
+    // 没有入住时间的酒店不能放进日历
+    db.book(&Hotels::CATEGORY, MIA, "T-ICS-H1".to_string())
+        .unwrap();
+
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!("{}\n", MIA))),
+        Box::new(Transcript::default()),
+    );
+    controller.login_view().unwrap();
+    let events: Vec<_> = controller
+        .itinerary()
+        .iter()
+        .filter_map(|x| x.event())
+        .collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].booking_ref(), flight_ref);
+    assert_eq!(events[0].location(), "北京");
+    assert!(events[0].summary().contains("T-ICS-F1"));
+    assert_eq!(events[0].end(), Some(time("2030-05-01 09:45")));
+
+    remove_customer(&mut conn, MIA);
+    remove_flight(&mut conn, "T-ICS-F1");
+    remove_hotel(&mut conn, "T-ICS-H1");
+}
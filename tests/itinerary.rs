@@ -0,0 +1,144 @@
+mod common;
+
+use common::*;
+use std::io::Cursor;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::bus::Bus;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::customer::Customer;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::itinerary::{self, ItineraryItem, DEFAULT_TEMPLATE};
+use tour_booking_system::package::PackageBooking;
+
+const NOAH: u32 = 911_101;
+
+fn flight(booking_ref: &str, start: Option<&str>) -> ItineraryItem {
+    ItineraryItem::new(
+        booking_ref.to_string(),
+        &Flight::CATEGORY,
+        "MU5101".to_string(),
+        "北京".to_string(),
+        Some("上海".to_string()),
+        start.map(|x| x.parse().unwrap()),
+        Some("2024-05-01 09:45".parse().unwrap()),
+        800,
+        None,
+    )
+}
+
+fn hotel(booking_ref: &str, package_ref: Option<&str>) -> ItineraryItem {
+    ItineraryItem::new(
+        booking_ref.to_string(),
+        &Hotels::CATEGORY,
+        "H<1>".to_string(),
+        "上海".to_string(),
+        None,
+        Some("2024-05-01 14:00".parse().unwrap()),
+        None,
+        300,
+        package_ref.map(str::to_string),
+    )
+}
+
+#[test]
+fn items_are_ordered_by_time() {
+    let mut items = vec![
+        flight("BBB222", None),
+        hotel("CCC333", None),
+        flight("AAA111", Some("2024-05-01 07:30")),
+    ];
+    itinerary::sort(&mut items);
+    let refs: Vec<_> = items.iter().map(|x| x.booking_ref()).collect();
+    assert_eq!(refs, vec!["AAA111", "CCC333", "BBB222"]);
+}
+
+#[test]
+fn html_is_rendered_from_the_template() {
+    let template = "<h1>{title}</h1><p>{customer} {customer_id}</p>{items}{packages}\
+                    <b>{total}</b><style>p { color: red }</style>{unknown}";
+    let page = itinerary::html(
+        template,
+        &Customer::new("Noah & <Co>".to_string(), NOAH),
+        &[
+            flight("AAA111", Some("2024-05-01 07:30")),
+            hotel("CCC333", Some("PKG123")),
+        ],
+        &[PackageBooking::new(
+            "PKG123".to_string(),
+            NOAH,
+            "P1".to_string(),
+            "上海三日游".to_string(),
+            999,
+        )],
+        1799,
+    );
+    assert!(page.contains("<p>Noah &amp; &lt;Co&gt; 911101</p>"));
+    assert!(page.contains("2024-05-01 07:30 ~ 2024-05-01 09:45"));
+    assert!(page.contains("北京 → 上海"));
+    assert!(page.contains("<td>800</td>"));
+    assert!(page.contains("H&lt;1&gt;"));
+    assert!(page.contains("上海三日游"));
+    assert!(page.contains("<b>1799</b>"));
+    // 不认识的大括号原样保留
+    assert!(page.contains("p { color: red }"));
+    assert!(page.contains("{unknown}"));
+    // 含在套餐中的项目不单独计价
+    assert!(!page.contains("<td>300</td>"));
+}
+
+#[test]
+fn the_default_template_uses_every_section() {
+    for key in ["{items}", "{packages}", "{total}", "{customer}", "{lang}"].iter() {
+        assert!(DEFAULT_TEMPLATE.contains(key), "{}", key);
+    }
+    let page = itinerary::html(
+        DEFAULT_TEMPLATE,
+        &Customer::new("noah".to_string(), NOAH),
+        &[],
+        &[],
+        0,
+    );
+    assert!(page.starts_with("<!DOCTYPE html>"));
+    assert!(!page.contains("{items}"));
+    assert!(!page.contains("class=\"packages\""));
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn itinerary_lists_everything_booked_with_the_total() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, NOAH, "noah");
+    add_flight(&mut conn, "T-ITN-F1", 10, "北京", "上海");
+    add_bus(&mut conn, "T-ITN-B1", 10, "上海");
+    set_flight_departure(&mut conn, "T-ITN-F1", 48);
+
+    let mut db = connection();
+    let flight_ref = db
+        .book(&Flight::CATEGORY, NOAH, "T-ITN-F1".to_string())
+        .unwrap();
+    let bus_ref = db
+        .book(&Bus::CATEGORY, NOAH, "T-ITN-B1".to_string())
+        .unwrap();
+
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!("{}\n", NOAH))),
+        Box::new(Transcript::default()),
+    );
+    controller.login_view().unwrap();
+    let items = controller.itinerary();
+    assert_eq!(items.len(), 2);
+    // 有出发时间的航班排在前面
+    assert_eq!(items[0].booking_ref(), flight_ref);
+    assert_eq!(items[1].booking_ref(), bus_ref);
+
+    let total = controller.trip_total();
+    let page = controller.itinerary_html().unwrap();
+    assert!(page.contains(&flight_ref) && page.contains(&bus_ref));
+    assert!(page.contains(&total.to_string()));
+
+    remove_customer(&mut conn, NOAH);
+    remove_flight(&mut conn, "T-ITN-F1");
+    remove_bus(&mut conn, "T-ITN-B1");
+}
@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+use tour_booking_system::i18n::{self, Locale};
+
+fn catalog(source: &str) -> BTreeMap<&str, &str> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect()
+}
+
+#[test]
+fn catalogs_have_the_same_keys_and_placeholders() {
+    let zh = catalog(include_str!("../locales/zh-CN.txt"));
+    let en = catalog(include_str!("../locales/en.txt"));
+
+    assert_eq!(zh.keys().collect::<Vec<_>>(), en.keys().collect::<Vec<_>>());
+    for (key, text) in &zh {
+        assert_eq!(
+            text.matches("{}").count(),
+            en[key].matches("{}").count(),
+            "placeholders of `{}` differ",
+            key
+        );
+    }
+}
+
+#[test]
+fn translates_with_arguments() {
+    i18n::set_locale(Locale::En);
+    assert_eq!(i18n::trf("login.welcome", &[&"Alice"]), "Hello, Alice!");
+    i18n::set_locale(Locale::ZhCn);
+    assert_eq!(i18n::trf("login.welcome", &[&"Alice"]), "您好，Alice!");
+    assert_eq!(i18n::tr("no.such.key"), "no.such.key");
+}
+
+#[test]
+fn parses_locale_names() {
+    assert_eq!(Locale::parse("zh_CN.UTF-8"), Some(Locale::ZhCn));
+    assert_eq!(Locale::parse("en-US"), Some(Locale::En));
+    assert_eq!(Locale::parse("fr"), None);
+}
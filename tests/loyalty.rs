@@ -0,0 +1,157 @@
+mod common;
+
+use common::*;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::loyalty::{LoyaltyAccount, PointsKind, Tier};
+
+const RUTH: u32 = 911_401;
+
+#[test]
+fn tiers_follow_the_points_earned() {
+    assert_eq!(Tier::from_points(0), Tier::Basic);
+    assert_eq!(Tier::from_points(4_999), Tier::Basic);
+    assert_eq!(Tier::from_points(5_000), Tier::Silver);
+    assert_eq!(Tier::from_points(20_000), Tier::Gold);
+    assert_eq!(Tier::from_points(1_000_000), Tier::Platinum);
+    assert_eq!(Tier::Gold.next(), Some(Tier::Platinum));
+    assert_eq!(Tier::Platinum.next(), None);
+
+    assert_eq!(Tier::Basic.earn(199), 199);
+    assert_eq!(Tier::Silver.earn(199), 248);
+    assert_eq!(Tier::Platinum.earn(199), 398);
+    // 大额付款不会回绕
+    assert_eq!(Tier::Platinum.earn(u32::MAX), 2 * u64::from(u32::MAX));
+
+    // 用掉的积分不影响等级
+    let account = LoyaltyAccount::new(100, 6_000);
+    assert_eq!(account.tier(), Tier::Silver);
+    assert_eq!(account.to_next_tier(), Some(14_000));
+    assert_eq!(LoyaltyAccount::new(0, 60_000).to_next_tier(), None);
+}
+
+#[test]
+fn redemption_uses_whole_discounts_only() {
+    assert_eq!(LoyaltyAccount::new(250, 250).redemption(100), (2, 200));
+    // 折扣不超过价格
+    assert_eq!(
+        LoyaltyAccount::new(50_000, 50_000).redemption(30),
+        (30, 3_000)
+    );
+    assert_eq!(LoyaltyAccount::new(99, 99).redemption(100), (0, 0));
+    assert_eq!(LoyaltyAccount::new(-10, 0).redemption(100), (0, 0));
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn points_are_earned_redeemed_and_reversed() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, RUTH, "ruth");
+    add_flight(&mut conn, "T-LOY-F1", 5, "北京", "上海");
+    let mut db = connection();
+
+    // 航班价格 100，普通会员每花 1 元得 1 分
+    db.book(&Flight::CATEGORY, RUTH, "T-LOY-F1".to_string())
+        .unwrap();
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(100, 100));
+
+    // 100 分抵 1 元，按 99 元计积分
+    let booking_ref = db
+        .book_with_points(&Flight::CATEGORY, RUTH, "T-LOY-F1".to_string(), None)
+        .unwrap();
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(99, 199));
+    let reservation = db.query_reservation_by_ref(RUTH, &booking_ref).unwrap();
+    assert_eq!(
+        db.cancel_quote(RUTH, reservation.id()).unwrap().1.price(),
+        99
+    );
+
+    // 取消后扣回获得的积分，退还抵扣的积分
+    assert!(db.cancel_reservation(RUTH, reservation.id()));
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(100, 100));
+    let kinds: Vec<_> = db
+        .points_statement(RUTH)
+        .iter()
+        .map(|x| (x.kind(), x.points()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (PointsKind::Restore, 100),
+            (PointsKind::Reverse, -99),
+            (PointsKind::Earn, 99),
+            (PointsKind::Redeem, -100),
+            (PointsKind::Earn, 100),
+        ]
+    );
+
+    remove_flight(&mut conn, "T-LOY-F1");
+    remove_customer(&mut conn, RUTH);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn held_and_expired_bookings_earn_nothing() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, RUTH, "ruth");
+    add_flight(&mut conn, "T-LOY-F2", 5, "北京", "上海");
+    let mut db = connection();
+    db.set_hold_minutes(Some(15));
+
+    // 付款确认前不得积分
+    let expired = db
+        .book(&Flight::CATEGORY, RUTH, "T-LOY-F2".to_string())
+        .unwrap();
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(0, 0));
+    assert!(db.expire_booking(RUTH, &expired));
+    assert!(!db.confirm_booking(RUTH, &expired));
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(0, 0));
+
+    // 确认后按价格 100 积分，重复确认不会再积分
+    let paid = db
+        .book(&Flight::CATEGORY, RUTH, "T-LOY-F2".to_string())
+        .unwrap();
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(0, 0));
+    assert!(db.confirm_booking(RUTH, &paid));
+    assert!(db.confirm_booking(RUTH, &paid));
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(100, 100));
+
+    remove_flight(&mut conn, "T-LOY-F2");
+    remove_customer(&mut conn, RUTH);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn expired_bookings_give_back_the_points_used() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, RUTH, "ruth");
+    add_flight(&mut conn, "T-LOY-F3", 5, "北京", "上海");
+    let mut db = connection();
+    db.book(&Flight::CATEGORY, RUTH, "T-LOY-F3".to_string())
+        .unwrap();
+
+    // 用积分抵扣的预定过期后退还积分，账目保留
+    db.set_hold_minutes(Some(15));
+    let expired = db
+        .book_with_points(&Flight::CATEGORY, RUTH, "T-LOY-F3".to_string(), None)
+        .unwrap();
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(0, 100));
+    assert!(db.expire_booking(RUTH, &expired));
+    assert_eq!(db.loyalty_account(RUTH), LoyaltyAccount::new(100, 100));
+    let kinds: Vec<_> = db
+        .points_statement(RUTH)
+        .iter()
+        .map(|x| (x.kind(), x.points()))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (PointsKind::Restore, 100),
+            (PointsKind::Redeem, -100),
+            (PointsKind::Earn, 100),
+        ]
+    );
+
+    remove_flight(&mut conn, "T-LOY-F3");
+    remove_customer(&mut conn, RUTH);
+}
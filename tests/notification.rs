@@ -0,0 +1,192 @@
+mod common;
+
+use common::*;
+use std::io::{self, BufRead, BufReader, Cursor, Write};
+use std::net::TcpListener;
+use std::thread;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::customer::ProfileField;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::i18n::{self, Locale};
+use tour_booking_system::notification::{Message, Outbox, Smtp, Template, Transport};
+
+const LIAM: u32 = 910_901;
+
+fn message() -> Message {
+    Message::new(
+        "liam@example.com".to_string(),
+        "预订成功".to_string(),
+        "Hi\n.hidden line\nbye".to_string(),
+    )
+}
+
+#[test]
+fn templates_are_rendered_in_the_current_locale() {
+    i18n::set_locale(Locale::En);
+    let message = Template::BookingCancelled.render(
+        "liam@example.com",
+        &[
+            ("name", &"Liam"),
+            ("booking_ref", &"R-1"),
+            ("item", &"Flight MU5101"),
+            ("fee", &20),
+            ("refund", &180),
+        ],
+    );
+    i18n::set_locale(Locale::ZhCn);
+    assert_eq!(message.to(), "liam@example.com");
+    assert!(message.subject().contains("R-1"));
+    assert!(!message.subject().starts_with("Subject"));
+    for text in ["Liam", "Flight MU5101", "20", "180"].iter() {
+        assert!(message.body().contains(text), "{}", text);
+    }
+    assert!(!message.body().contains('{'));
+
+    let message = Template::BookingConfirmed.render("liam@example.com", &[("name", &"Liam")]);
+    assert!(message.body().contains("Liam"));
+    assert!(!message.body().starts_with('\n'));
+}
+
+#[test]
+fn non_ascii_subjects_are_encoded() {
+    let mail = message().format("noreply@localhost");
+    assert!(mail.contains("Subject: =?UTF-8?B?6aKE6K6i5oiQ5Yqf?=\r\n"));
+    assert!(mail.contains("To: <liam@example.com>\r\n"));
+    assert!(mail.ends_with("\r\n\r\nHi\r\n.hidden line\r\nbye\r\n"));
+}
+
+#[test]
+fn outbox_writes_one_file_per_message() {
+    let dir = std::env::temp_dir().join(format!("tour_booking_outbox_{}", std::process::id()));
+    let mut outbox = Outbox::new(&dir, "noreply@localhost".to_string());
+    outbox.send(&message()).unwrap();
+    outbox.send(&message()).unwrap();
+
+    let files: Vec<_> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 2);
+    assert!(std::fs::read_to_string(&files[0])
+        .unwrap()
+        .contains("liam@example.com"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+// 一个只会回答成功的 SMTP 服务器，返回收到的全部内容
+fn smtp_sink(listener: TcpListener) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut stream = stream;
+        let mut received = String::new();
+        let mut in_data = false;
+        stream.write_all(b"220 sink\r\n").unwrap();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            received.push_str(&line);
+            let reply: &[u8] = if in_data {
+                if line != ".\r\n" {
+                    continue;
+                }
+                in_data = false;
+                b"250 queued\r\n"
+            } else if line.starts_with("EHLO") {
+                b"250-sink\r\n250 8BITMIME\r\n"
+            } else if line.starts_with("DATA") {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if line.starts_with("QUIT") {
+                stream.write_all(b"221 bye\r\n").unwrap();
+                break;
+            } else {
+                b"250 ok\r\n"
+            };
+            stream.write_all(reply).unwrap();
+        }
+        received
+    })
+}
+
+#[test]
+fn smtp_talks_to_a_local_sink() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = listener.local_addr().unwrap().to_string();
+    let sink = smtp_sink(listener);
+
+    let mut smtp = Smtp::new(server, "noreply@localhost".to_string());
+    smtp.send(&message()).unwrap();
+    let received = sink.join().unwrap();
+    assert!(received.contains("MAIL FROM:<noreply@localhost>\r\n"));
+    assert!(received.contains("RCPT TO:<liam@example.com>\r\n"));
+    // 以 . 开头的行被转义
+    assert!(received.contains("\r\n..hidden line\r\n"));
+    assert!(received.ends_with(".\r\nQUIT\r\n"));
+}
+
+#[test]
+fn smtp_fails_without_a_server() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let server = listener.local_addr().unwrap().to_string();
+    drop(listener);
+    let mut smtp = Smtp::new(server, "noreply@localhost".to_string());
+    assert!(smtp.send(&message()).is_err());
+}
+
+/// fails until it is told to work, then keeps what it sent
+#[derive(Default)]
+struct Flaky {
+    up: std::rc::Rc<std::cell::Cell<bool>>,
+    sent: std::rc::Rc<std::cell::RefCell<Vec<Message>>>,
+}
+
+impl Transport for Flaky {
+    fn send(&mut self, message: &Message) -> io::Result<()> {
+        if !self.up.get() {
+            return Err(io::Error::other("mail server is down"));
+        }
+        self.sent.borrow_mut().push(message.clone());
+        Ok(())
+    }
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn failed_emails_stay_queued_until_delivered() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, LIAM, "liam");
+    add_flight(&mut conn, "T-NTF-F1", 10, "北京", "上海");
+
+    let flaky = Flaky::default();
+    let (up, sent) = (flaky.up.clone(), flaky.sent.clone());
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!("{}\n", LIAM))),
+        Box::new(Transcript::default()),
+    );
+    controller.login_view().unwrap();
+    controller.set_transport(Box::new(flaky));
+    controller
+        .update_profile(ProfileField::Email, "liam@example.com")
+        .unwrap();
+
+    let booking_ref = controller
+        .book(&Flight::CATEGORY, "T-NTF-F1".to_string())
+        .unwrap();
+    assert_eq!(pending_notification_count(&mut conn, LIAM), 1);
+
+    up.set(true);
+    assert!(controller.deliver_notifications() >= 1);
+    assert_eq!(pending_notification_count(&mut conn, LIAM), 0);
+    let sent = sent.borrow();
+    let message = sent.iter().find(|x| x.to() == "liam@example.com").unwrap();
+    assert!(message.subject().contains(&booking_ref));
+    assert!(message.body().contains("T-NTF-F1"));
+
+    remove_customer(&mut conn, LIAM);
+    remove_flight(&mut conn, "T-NTF-F1");
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use common::*;
+use mysql::params;
+use mysql::prelude::Queryable;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::package::{Package, PackageBooking, PackageItem};
+use tour_booking_system::reservation::ReservationStatus;
+use tour_booking_system::{t, ToRow};
+
+const GRACE: u32 = 910_601;
+const HEIDI: u32 = 910_602;
+
+#[test]
+fn packages_list_their_items() {
+    let package = Package::new(
+        "P1".into(),
+        "三日游".into(),
+        1800,
+        vec![
+            PackageItem::new(1, "F1".into()),
+            PackageItem::new(2, "H1".into()),
+        ],
+    );
+    assert_eq!(
+        package.content(),
+        format!("{} F1, {} H1", t!("type.flight"), t!("type.hotel"))
+    );
+    assert_eq!(package.to_row().len(), 4);
+
+    let mut booking =
+        PackageBooking::new("K7QX2M".into(), GRACE, "P1".into(), "三日游".into(), 1800);
+    assert_eq!(booking.status(), ReservationStatus::Confirmed);
+    booking.set_status(ReservationStatus::Held);
+    assert!(booking
+        .to_row()
+        .iter()
+        .any(|x| x.get_content() == t!("status.held")));
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn packages_are_booked_and_cancelled_as_a_whole() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, GRACE, "grace");
+    add_flight(&mut conn, "T-PKG-F1", 5, "北京", "上海");
+    add_hotel(&mut conn, "T-PKG-H1", 5, "上海");
+    add_bus(&mut conn, "T-PKG-B1", 5, "上海");
+    add_package(
+        &mut conn,
+        "T-PKG-1",
+        900,
+        &[(1, "T-PKG-F1"), (2, "T-PKG-H1"), (3, "T-PKG-B1")],
+    );
+    let mut db = connection();
+
+    let package_ref = db.book_package(GRACE, "T-PKG-1").unwrap();
+    assert_eq!(db.query_reservations(GRACE).len(), 3);
+    assert_eq!(db.query_packages(GRACE)[0].price(), 900);
+    assert_eq!(db.trip_total(GRACE), 900);
+    assert!(db.check_consistency());
+
+    // 套餐中的单项不能单独取消
+    assert!(!db.cancel(&Flight::CATEGORY, GRACE, "T-PKG-F1".to_string()));
+    assert_eq!(db.query_reservations(GRACE).len(), 3);
+
+    assert!(db.cancel_package(GRACE, &package_ref));
+    assert!(db.query_reservations(GRACE).is_empty());
+    assert!(db.query_packages(GRACE).is_empty());
+    assert!(!db.cancel_package(GRACE, &package_ref));
+    // 取消的套餐保留为历史记录
+    let status: Option<String> = conn
+        .exec_first(
+            "SELECT status FROM PackageBooking WHERE booking_ref = :booking_ref",
+            params! { "booking_ref" => &package_ref },
+        )
+        .unwrap();
+    assert_eq!(status.as_deref(), Some("cancelled"));
+    assert_eq!(db.trip_total(GRACE), 0);
+    assert!(db.check_consistency());
+
+    remove_package(&mut conn, "T-PKG-1");
+    remove_flight(&mut conn, "T-PKG-F1");
+    remove_hotel(&mut conn, "T-PKG-H1");
+    remove_bus(&mut conn, "T-PKG-B1");
+    remove_customer(&mut conn, GRACE);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn nothing_is_booked_when_an_item_is_sold_out() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, GRACE, "grace");
+    add_customer(&mut conn, HEIDI, "heidi");
+    add_flight(&mut conn, "T-PKG-F2", 5, "北京", "上海");
+    add_hotel(&mut conn, "T-PKG-H2", 1, "上海");
+    add_package(
+        &mut conn,
+        "T-PKG-2",
+        900,
+        &[(1, "T-PKG-F2"), (2, "T-PKG-H2")],
+    );
+    let mut db = connection();
+
+    assert!(db.book_package(GRACE, "T-PKG-2").is_some());
+    // 酒店只剩 0 间，航班也不应被预定
+    assert!(db.book_package(HEIDI, "T-PKG-2").is_none());
+    assert!(db.query_reservations(HEIDI).is_empty());
+    assert!(db.query_packages(HEIDI).is_empty());
+    assert_eq!(reservation_count(&mut conn, GRACE, 1, "T-PKG-F2"), 1);
+    assert!(db.check_consistency());
+
+    remove_package(&mut conn, "T-PKG-2");
+    remove_flight(&mut conn, "T-PKG-F2");
+    remove_hotel(&mut conn, "T-PKG-H2");
+    remove_customer(&mut conn, GRACE);
+    remove_customer(&mut conn, HEIDI);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn held_packages_are_not_paid_for() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, GRACE, "grace");
+    add_flight(&mut conn, "T-PKG-F3", 5, "北京", "上海");
+    add_package(&mut conn, "T-PKG-3", 900, &[(1, "T-PKG-F3")]);
+    let mut db = connection();
+    db.set_hold_minutes(Some(15));
+    let status = |db: &mut tour_booking_system::mysql_connection::MySQLConnection| {
+        db.query_packages(GRACE)
+            .iter()
+            .map(|x| x.status())
+            .collect::<Vec<_>>()
+    };
+
+    // 付款前套餐和其中的各项一样待付款，不计入总价
+    let paid = db.book_package(GRACE, "T-PKG-3").unwrap();
+    assert_eq!(status(&mut db), vec![ReservationStatus::Held]);
+    assert_eq!(db.trip_total(GRACE), 0);
+    assert!(db.confirm_booking(GRACE, &paid));
+    assert_eq!(status(&mut db), vec![ReservationStatus::Confirmed]);
+    assert_eq!(db.trip_total(GRACE), 900);
+
+    // 过期的套餐不再列出
+    let expired = db.book_package(GRACE, "T-PKG-3").unwrap();
+    assert!(db.expire_booking(GRACE, &expired));
+    assert_eq!(status(&mut db), vec![ReservationStatus::Confirmed]);
+    assert!(db.check_consistency());
+
+    assert!(db.cancel_package(GRACE, &paid));
+    remove_package(&mut conn, "T-PKG-3");
+    remove_flight(&mut conn, "T-PKG-F3");
+    remove_customer(&mut conn, GRACE);
+}
@@ -0,0 +1,164 @@
+mod common;
+
+use common::*;
+use mysql::params;
+use mysql::prelude::Queryable;
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::rc::Rc;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::payment::{
+    Gateway, MockGateway, MockOutcome, PaymentError, PaymentKind, PaymentStatus,
+};
+use tour_booking_system::t;
+
+const MAYA: u32 = 911_501;
+
+#[test]
+fn mock_answers_with_the_configured_outcome() {
+    let mut gateway = MockGateway::new(MockOutcome::Approve);
+    let first = gateway.charge("ABC123", 100).unwrap();
+    let second = gateway.charge("ABC123", 100).unwrap();
+    assert_ne!(first, second);
+    assert!(gateway.refund(&first, 50).is_ok());
+
+    gateway.set_outcome(MockOutcome::Decline);
+    let declined = gateway.charge("ABC123", 100);
+    assert!(matches!(declined, Err(PaymentError::Declined(_))));
+    assert_eq!(PaymentStatus::of(&declined), PaymentStatus::Declined);
+
+    gateway.set_outcome(MockOutcome::Timeout);
+    let timeout = gateway.refund(&first, 50);
+    assert_eq!(timeout, Err(PaymentError::Timeout));
+    assert_eq!(PaymentStatus::of(&timeout), PaymentStatus::Timeout);
+
+    assert_eq!(MockOutcome::parse("decline"), Some(MockOutcome::Decline));
+    assert_eq!(MockOutcome::parse("maybe"), None);
+    assert_eq!(
+        PaymentStatus::parse(PaymentStatus::Pending.as_str()),
+        Some(PaymentStatus::Pending)
+    );
+}
+
+/// a mock whose outcome can still be changed after the controller owns it
+#[derive(Clone)]
+struct Shared(Rc<RefCell<MockGateway>>);
+
+impl Gateway for Shared {
+    fn name(&self) -> &str {
+        "mock"
+    }
+    fn charge(&mut self, booking_ref: &str, amount: u32) -> Result<String, PaymentError> {
+        self.0.borrow_mut().charge(booking_ref, amount)
+    }
+    fn refund(&mut self, transaction_id: &str, amount: u32) -> Result<String, PaymentError> {
+        self.0.borrow_mut().refund(transaction_id, amount)
+    }
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn bookings_are_paid_and_refunded() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, MAYA, "maya");
+    add_flight(&mut conn, "T-PAY-F1", 10, "北京", "上海");
+
+    let gateway = Shared(Rc::new(RefCell::new(MockGateway::new(
+        MockOutcome::Approve,
+    ))));
+    let set_outcome = |outcome| gateway.0.borrow_mut().set_outcome(outcome);
+    let out = Transcript::default();
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!("{}\n", MAYA))),
+        Box::new(out.clone()),
+    );
+    controller.login_view().unwrap();
+    controller.set_gateway(Box::new(gateway.clone()));
+
+    let booking_ref = controller
+        .book(&Flight::CATEGORY, "T-PAY-F1".to_string())
+        .unwrap();
+    let payments = controller.payments(&booking_ref);
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0].kind(), PaymentKind::Charge);
+    assert_eq!(payments[0].status(), PaymentStatus::Approved);
+    assert_eq!(payments[0].amount(), 100);
+
+    // 付款失败的预定不保留
+    set_outcome(MockOutcome::Decline);
+    assert!(controller
+        .book(&Flight::CATEGORY, "T-PAY-F1".to_string())
+        .is_none());
+    assert_eq!(connection().query_reservations(MAYA).len(), 1);
+    assert!(out
+        .text()
+        .contains(&t!("error.payment_failed", "card declined")));
+
+    // 退款超时，下次启动时重试
+    let id = connection()
+        .query_reservation_by_ref(MAYA, &booking_ref)
+        .unwrap()
+        .id();
+    let refund = controller.cancel_quote(id).unwrap().refund();
+    set_outcome(MockOutcome::Timeout);
+    assert!(controller.cancel_reservation(id));
+    let last = |controller: &mut Controller| {
+        let payments = controller.payments(&booking_ref);
+        let last = payments.last().unwrap();
+        (last.kind(), last.status(), last.amount())
+    };
+    assert_eq!(
+        last(&mut controller),
+        (PaymentKind::Refund, PaymentStatus::Timeout, refund)
+    );
+    assert!(out
+        .text()
+        .contains(&t!("error.refund_failed", refund, "timeout")));
+
+    set_outcome(MockOutcome::Approve);
+    assert!(controller.settle_refunds() >= 1);
+    assert_eq!(
+        last(&mut controller),
+        (PaymentKind::Refund, PaymentStatus::Approved, refund)
+    );
+
+    remove_customer(&mut conn, MAYA);
+    remove_flight(&mut conn, "T-PAY-F1");
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn unpaid_holds_are_cancelled_without_a_refund() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, MAYA, "maya");
+    add_flight(&mut conn, "T-PAY-F2", 10, "北京", "上海");
+    let mut db = connection();
+    db.set_hold_minutes(Some(15));
+
+    let booking_ref = db
+        .book(&Flight::CATEGORY, MAYA, "T-PAY-F2".to_string())
+        .unwrap();
+    let id = db
+        .query_reservation_by_ref(MAYA, &booking_ref)
+        .unwrap()
+        .id();
+    let (_, quote) = db.cancel_quote(MAYA, id).unwrap();
+    assert_eq!((quote.price(), quote.fee(), quote.refund()), (100, 0, 0));
+
+    // 没有付款就没有退款记录
+    assert!(db.cancel_reservation(MAYA, id));
+    let refunds: Option<u32> = conn
+        .exec_first(
+            "SELECT COUNT(*) FROM Refund WHERE booking_ref = :booking_ref",
+            params! { "booking_ref" => &booking_ref },
+        )
+        .unwrap();
+    assert_eq!(refunds, Some(0));
+    assert!(db.payments(MAYA, &booking_ref).is_empty());
+
+    remove_customer(&mut conn, MAYA);
+    remove_flight(&mut conn, "T-PAY-F2");
+}
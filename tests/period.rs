@@ -0,0 +1,52 @@
+use tour_booking_system::period::{Date, Period};
+
+fn date(s: &str) -> Date {
+    s.parse().unwrap()
+}
+
+#[test]
+fn dates_are_validated() {
+    assert_eq!(date("2024-02-29"), Date::new(2024, 2, 29).unwrap());
+    assert!("2023-02-29".parse::<Date>().is_err());
+    assert!("1900-02-29".parse::<Date>().is_err());
+    assert!("2024-13-01".parse::<Date>().is_err());
+    assert!("2024-04-31".parse::<Date>().is_err());
+    assert!("2024/04/01".parse::<Date>().is_err());
+    assert_eq!(date(" 2024-5-1 ").to_string(), "2024-05-01");
+}
+
+#[test]
+fn periods_count_days_across_months_and_years() {
+    let period = |start, end| Period::new(date(start), date(end)).unwrap();
+    assert_eq!(period("2024-05-01", "2024-05-04").days(), 3);
+    assert_eq!(period("2024-02-28", "2024-03-01").days(), 2);
+    assert_eq!(period("2023-12-30", "2024-01-02").days(), 3);
+    // 当天取还也按一天计
+    assert_eq!(period("2024-05-01", "2024-05-01").days(), 1);
+    // 还车当天可以再租出去
+    assert_eq!(
+        period("2024-05-01", "2024-05-03").returned(),
+        date("2024-05-03")
+    );
+    assert_eq!(
+        period("2024-05-01", "2024-05-01").returned(),
+        date("2024-05-02")
+    );
+    assert!(Period::new(date("2024-05-02"), date("2024-05-01")).is_none());
+    assert_eq!(date("2024-02-28").add_days(2), date("2024-03-01"));
+    assert_eq!(date("2024-01-01").add_days(-1), date("2023-12-31"));
+}
+
+#[test]
+fn periods_are_read_back_from_the_columns() {
+    let period = Period::from_columns(Some("2024-05-01".into()), Some("2024-05-03".into()));
+    assert_eq!(period.unwrap().to_string(), "2024-05-01 ~ 2024-05-03");
+    assert!(Period::from_columns(None, None).is_none());
+}
+
+#[test]
+fn today_is_a_valid_date() {
+    let today = Date::today();
+    assert_eq!(today.to_string().parse::<Date>(), Ok(today));
+    assert!(today > date("2020-01-01"));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::*;
+use mysql::params;
+use mysql::prelude::Queryable;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::bus::Bus;
+use tour_booking_system::period::Date;
+use tour_booking_system::pricing::{quote, Demand, PricingRule};
+
+const QUINN: u32 = 911_301;
+
+fn date(s: &str) -> Date {
+    s.parse().unwrap()
+}
+
+fn rule(adjust_percent: i32, setup: impl FnOnce(&mut PricingRule)) -> PricingRule {
+    let mut rule = PricingRule::new(adjust_percent);
+    setup(&mut rule);
+    rule
+}
+
+#[test]
+fn demand_counts_load_days_and_weekday() {
+    // 2024-05-03 是星期五
+    let demand = Demand::new(3, 4, Some(date("2024-05-03")), date("2024-05-01"));
+    assert_eq!(demand.load_percent(), 75);
+    assert_eq!(demand.days_before(), Some(2));
+    assert_eq!(demand.weekday(), Some(5));
+
+    let demand = Demand::new(0, 0, None, date("2024-05-01"));
+    assert_eq!(demand.load_percent(), 100);
+    assert_eq!(demand.days_before(), None);
+}
+
+#[test]
+fn quotes_never_wrap_around() {
+    let rules = [rule(200, |_| {})];
+    let demand = Demand::new(0, 10, None, date("2024-05-01"));
+    assert_eq!(quote(u32::MAX / 2, &rules, 1, &demand), u32::MAX);
+}
+
+#[test]
+fn matching_rules_add_up() {
+    let rules = [
+        rule(20, |x| x.set_load(Some(80), None)),
+        rule(-10, |x| x.set_days_before(Some(30), None)),
+        rule(10, |x| {
+            x.set_res_type(Some(1));
+            x.set_weekday(Some(5));
+        }),
+    ];
+    let today = date("2024-05-01");
+    let friday = Some(date("2024-05-03"));
+
+    let full_friday_flight = Demand::new(9, 10, friday, today);
+    assert_eq!(quote(1000, &rules, 1, &full_friday_flight), 1300);
+    // 只对航班生效的规则不影响大巴
+    assert_eq!(quote(1000, &rules, 3, &full_friday_flight), 1200);
+
+    let early = Demand::new(1, 10, Some(date("2024-06-30")), today);
+    assert_eq!(quote(999, &rules, 1, &early), 899);
+    // 没有日期时与日期有关的规则都不生效
+    assert_eq!(
+        quote(1000, &rules, 1, &Demand::new(1, 10, None, today)),
+        1000
+    );
+    // 价格不会低于零
+    assert_eq!(quote(1000, &[PricingRule::new(-150)], 1, &early), 0);
+}
+
+#[test]
+fn load_bounds_are_inclusive() {
+    let rule = rule(5, |x| x.set_load(Some(50), Some(80)));
+    let today = date("2024-05-01");
+    assert!(rule.matches(1, &Demand::new(5, 10, None, today)));
+    assert!(rule.matches(1, &Demand::new(8, 10, None, today)));
+    assert!(!rule.matches(1, &Demand::new(9, 10, None, today)));
+    assert!(!rule.matches(1, &Demand::new(4, 10, None, today)));
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn quoted_price_is_locked_at_booking() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, QUINN, "quinn");
+    add_bus(&mut conn, "T-PRC-B1", 2, "T-PRC-杭州");
+    conn.exec_drop(
+        "INSERT INTO PricingRule (res_type, min_load, adjust_percent) VALUES (:res_type, 50, 50)",
+        params! { "res_type" => Bus::CATEGORY.res_type() },
+    )
+    .unwrap();
+    let mut db = connection();
+    let price = |db: &mut tour_booking_system::mysql_connection::MySQLConnection,
+                 booking_ref: &str| {
+        let id = db
+            .query_reservation_by_ref(QUINN, booking_ref)
+            .unwrap()
+            .id();
+        db.cancel_quote(QUINN, id).unwrap().1.price()
+    };
+
+    let first = db
+        .book(&Bus::CATEGORY, QUINN, "T-PRC-B1".to_string())
+        .unwrap();
+    // 已订一半，第二张涨价 50%
+    let second = db
+        .book(&Bus::CATEGORY, QUINN, "T-PRC-B1".to_string())
+        .unwrap();
+    let base = price(&mut db, &first);
+    assert_eq!(price(&mut db, &second), base * 3 / 2);
+
+    let simulated = db
+        .pricing_simulation()
+        .into_iter()
+        .find(|x| x.res_id() == "T-PRC-B1")
+        .unwrap();
+    assert_eq!(simulated.demand().load_percent(), 100);
+    assert_eq!(simulated.price(), base * 3 / 2);
+
+    // 规则改了，已有的预定价格不变
+    conn.exec_drop(
+        "DELETE FROM PricingRule WHERE res_type = :res_type AND min_load = 50",
+        params! { "res_type" => Bus::CATEGORY.res_type() },
+    )
+    .unwrap();
+    assert_eq!(price(&mut db, &second), base * 3 / 2);
+    assert!(db
+        .pricing_simulation()
+        .iter()
+        .any(|x| x.res_id() == "T-PRC-B1" && x.price() == base));
+
+    remove_customer(&mut conn, QUINN);
+    remove_bus(&mut conn, "T-PRC-B1");
+}
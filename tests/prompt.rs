@@ -0,0 +1,64 @@
+mod common;
+
+use common::Transcript;
+use std::cell::RefCell;
+use std::io::Cursor;
+use std::rc::Rc;
+use tour_booking_system::prompt::{Interrupt, Prompt};
+use tour_booking_system::t;
+
+fn prompt_with(input: &str, out: &Transcript) -> Prompt {
+    Prompt::new(
+        Box::new(Cursor::new(input.to_string())),
+        Rc::new(RefCell::new(Box::new(out.clone()))),
+    )
+}
+
+fn prompt(input: &str) -> Prompt {
+    prompt_with(input, &Transcript::default())
+}
+
+#[test]
+fn retries_until_the_input_parses() {
+    let out = Transcript::default();
+    let mut prompt = prompt_with("abc\n-1\n42\n", &out);
+    assert_eq!(prompt.read::<u32>(), Ok(42));
+    assert_eq!(out.text(), t!("prompt.invalid").repeat(2));
+}
+
+#[test]
+fn end_of_input_is_reported_instead_of_panicking() {
+    let mut prompt = prompt("abc\n");
+    assert_eq!(prompt.read::<u32>(), Err(Interrupt::Eof));
+    assert_eq!(prompt.line(), Err(Interrupt::Eof));
+}
+
+#[test]
+fn the_back_command_leaves_any_prompt() {
+    let mut prompt = prompt(":q\n:Q\n :q \n");
+    assert_eq!(prompt.read::<u32>(), Err(Interrupt::Back));
+    assert_eq!(prompt.text(), Err(Interrupt::Back));
+    assert_eq!(prompt.confirm(None), Err(Interrupt::Back));
+}
+
+#[test]
+fn words_are_values_not_commands() {
+    let mut prompt = prompt("back\ncancel\n返回\n取消\n");
+    for word in ["back", "cancel", "返回", "取消"].iter() {
+        assert_eq!(prompt.text().as_deref(), Ok(*word));
+    }
+}
+
+#[test]
+fn confirm_asks_again_on_anything_but_yes_or_no() {
+    let mut prompt = prompt("x\nmaybe\nY\n\n");
+    assert_eq!(prompt.confirm(None), Ok(true));
+    assert_eq!(prompt.confirm(Some(false)), Ok(false));
+}
+
+#[test]
+fn optional_values_can_be_skipped() {
+    let mut prompt = prompt("\n12x\n300\n");
+    assert_eq!(prompt.optional::<u32>(), Ok(None));
+    assert_eq!(prompt.optional::<u32>(), Ok(Some(300)));
+}
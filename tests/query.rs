@@ -0,0 +1,100 @@
+mod common;
+
+use common::*;
+use mysql::Value;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::bus::Bus;
+use tour_booking_system::catalog_filter::{CatalogFilter, Page};
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::query::Query;
+
+const HOSTILE: [&str; 4] = [
+    "T'; DROP TABLE Flights; --",
+    "T\" OR \"1\" = \"1",
+    "T' OR '1' = '1",
+    "T\\'; DELETE FROM Reservation; #",
+];
+
+const MALLORY: u32 = 910_301;
+
+#[test]
+fn bound_values_stay_out_of_the_sql() {
+    for hostile in HOSTILE.iter() {
+        let query = Query::new("SELECT name FROM Customer WHERE name = ?").bind(*hostile);
+        assert_eq!(query.sql(), "SELECT name FROM Customer WHERE name = ?");
+        assert_eq!(query.params(), &[Value::from(*hostile)]);
+    }
+}
+
+#[test]
+fn values_are_bound_in_order() {
+    let query = Query::new("SELECT 1 FROM Flights WHERE price >= ?")
+        .bind(100u32)
+        .push(" AND from_city = ?")
+        .bind("北京");
+    assert_eq!(
+        query.sql(),
+        "SELECT 1 FROM Flights WHERE price >= ? AND from_city = ?"
+    );
+    assert_eq!(query.params(), &[Value::from(100u32), Value::from("北京")]);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn hostile_ids_are_booked_and_queried_literally() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, MALLORY, "O'Brien\"; DROP TABLE Customer; --");
+    for hostile in HOSTILE.iter() {
+        add_flight(&mut conn, hostile, 5, "北京", "上海");
+    }
+    let mut db = connection();
+
+    for hostile in HOSTILE.iter() {
+        assert!(db
+            .book(&Flight::CATEGORY, MALLORY, hostile.to_string())
+            .is_some());
+    }
+    let mut booked: Vec<String> = db
+        .query::<Flight>(MALLORY)
+        .iter()
+        .map(|x| x.flight_num().to_string())
+        .collect();
+    booked.sort();
+    let mut expected: Vec<String> = HOSTILE.iter().map(|x| x.to_string()).collect();
+    expected.sort();
+    assert_eq!(booked, expected);
+
+    assert_eq!(
+        db.query_user_name(MALLORY),
+        "O'Brien\"; DROP TABLE Customer; --"
+    );
+    // 一致性检查会把 res_id 带进查询，恶意的编号不能改变语句
+    db.check_consistency();
+    for hostile in HOSTILE.iter() {
+        assert!(db.find::<Flight>(hostile).is_some());
+        assert_eq!(reservation_count(&mut conn, MALLORY, 1, hostile), 1);
+    }
+
+    for hostile in HOSTILE.iter() {
+        remove_flight(&mut conn, hostile);
+    }
+    remove_customer(&mut conn, MALLORY);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn hostile_filters_match_nothing() {
+    let mut db = connection();
+    for hostile in HOSTILE.iter() {
+        let mut filter = CatalogFilter::new();
+        filter.set_from_city(Some(hostile.to_string()));
+        assert_eq!(db.count(&Flight::CATEGORY, &filter), 0);
+        assert!(db.search::<Flight>(&filter, &Page::default()).is_empty());
+
+        let mut filter = CatalogFilter::new();
+        filter.set_location(Some(hostile.to_string()));
+        assert_eq!(db.count(&Hotels::CATEGORY, &filter), 0);
+        assert_eq!(db.count(&Bus::CATEGORY, &filter), 0);
+    }
+}
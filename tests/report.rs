@@ -0,0 +1,129 @@
+mod common;
+
+use common::*;
+use std::io::Cursor;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::car_rental::CarRental;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::report::{load_factor, Report, ReportKind};
+
+const OLIVIA: u32 = 911_201;
+const PAUL: u32 = 911_202;
+
+#[test]
+fn reports_are_exported_as_csv() {
+    let report = Report::new(
+        ReportKind::RevenueByCity,
+        vec![
+            vec!["上海".to_string(), "2".to_string(), "1600".to_string()],
+            vec![
+                "Washington, D.C.".to_string(),
+                "1".to_string(),
+                "say \"hi\"".to_string(),
+            ],
+        ],
+    );
+    let csv = report.to_csv();
+    let lines: Vec<&str> = csv.split("\r\n").collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0].split(',').count(), 3);
+    assert_eq!(lines[1], "上海,2,1600");
+    assert_eq!(lines[2], "\"Washington, D.C.\",1,\"say \"\"hi\"\"\"");
+    assert_eq!(lines[3], "");
+}
+
+#[test]
+fn load_factors_are_percentages() {
+    assert_eq!(load_factor(3, 4), "75.0");
+    assert_eq!(load_factor(1, 3), "33.3");
+    assert_eq!(load_factor(0, 0), "-");
+}
+
+#[test]
+fn dated_catalogs_have_no_occupancy_report() {
+    let kinds = ReportKind::all();
+    assert!(kinds
+        .iter()
+        .any(|x| x.file_stem() == "report_occupancy_flights"));
+    assert!(!kinds.iter().any(|x| matches!(x,
+        ReportKind::Occupancy(category) if category.res_type() == CarRental::CATEGORY.res_type())));
+    let mut stems: Vec<String> = kinds.iter().map(ReportKind::file_stem).collect();
+    stems.dedup();
+    assert_eq!(stems.len(), kinds.len());
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn only_admins_see_the_reports() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, OLIVIA, "olivia");
+    add_customer(&mut conn, PAUL, "paul");
+    add_flight(&mut conn, "T-RPT-F1", 4, "T-RPT-北京", "T-RPT-上海");
+
+    let mut db = connection();
+    for user in [OLIVIA, OLIVIA, PAUL].iter() {
+        db.book(&Flight::CATEGORY, *user, "T-RPT-F1".to_string())
+            .unwrap();
+    }
+
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!("{}\n", PAUL))),
+        Box::new(Transcript::default()),
+    );
+    controller.login_view().unwrap();
+    let occupancy = ReportKind::Occupancy(&Flight::CATEGORY);
+    assert!(controller.report(occupancy).is_none());
+
+    set_admin(&mut conn, PAUL, true);
+    let report = controller.report(occupancy).unwrap();
+    let row = report.rows().iter().find(|x| x[0] == "T-RPT-F1").unwrap();
+    assert_eq!(row[1], "T-RPT-北京 → T-RPT-上海");
+    assert_eq!(&row[2..], &["4", "3", "75.0"]);
+
+    let report = controller.report(ReportKind::PopularRoutes).unwrap();
+    assert!(report
+        .rows()
+        .iter()
+        .any(|x| x[0] == "T-RPT-北京 → T-RPT-上海" && x[1] == "3"));
+
+    let report = controller.report(ReportKind::RevenueByCity).unwrap();
+    let row = report.rows().iter().find(|x| x[0] == "T-RPT-上海").unwrap();
+    assert_eq!(row[1], "3");
+
+    let report = controller.report(ReportKind::TopCustomers).unwrap();
+    assert!(report.to_csv().lines().count() >= 2);
+
+    remove_customer(&mut conn, OLIVIA);
+    remove_customer(&mut conn, PAUL);
+    remove_flight(&mut conn, "T-RPT-F1");
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn held_bookings_are_not_revenue() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, OLIVIA, "olivia");
+    add_flight(&mut conn, "T-RPT-F2", 4, "T-RPT-杭州", "T-RPT-南京");
+    let mut db = connection();
+    let revenue = |db: &mut tour_booking_system::mysql_connection::MySQLConnection| {
+        db.report(ReportKind::RevenueByCity)
+            .rows()
+            .iter()
+            .find(|x| x[0] == "T-RPT-南京")
+            .map(|x| x[1].clone())
+    };
+
+    // 待付款的预定可能过期，不算收入
+    db.set_hold_minutes(Some(15));
+    let held = db
+        .book(&Flight::CATEGORY, OLIVIA, "T-RPT-F2".to_string())
+        .unwrap();
+    assert_eq!(revenue(&mut db), None);
+    assert!(db.confirm_booking(OLIVIA, &held));
+    assert_eq!(revenue(&mut db).as_deref(), Some("1"));
+
+    remove_customer(&mut conn, OLIVIA);
+    remove_flight(&mut conn, "T-RPT-F2");
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::*;
+use mysql::params;
+use mysql::prelude::Queryable;
+use std::time::Duration;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::config::Config;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::reservation::ReservationStatus;
+use tour_booking_system::sweeper;
+
+const NINA: u32 = 911_601;
+
+#[test]
+fn only_live_reservations_can_be_cancelled() {
+    for status in ReservationStatus::ALL.iter() {
+        assert_eq!(ReservationStatus::parse(status.as_str()), Some(*status));
+    }
+    assert_eq!(ReservationStatus::parse("deleted"), None);
+
+    assert!(ReservationStatus::Held.is_cancellable());
+    assert!(ReservationStatus::Confirmed.is_cancellable());
+    assert!(!ReservationStatus::Completed.is_cancellable());
+    // 已完成的行程仍然计入占用和营业额
+    assert!(ReservationStatus::Completed.is_active());
+    assert!(!ReservationStatus::Cancelled.is_active());
+    assert!(!ReservationStatus::Expired.is_active());
+}
+
+#[test]
+fn holds_and_sweeps_have_defaults() {
+    let config = Config::default();
+    assert_eq!(
+        sweeper::hold_minutes(&config),
+        sweeper::DEFAULT_HOLD_MINUTES
+    );
+    assert_eq!(
+        sweeper::interval(&config),
+        Duration::from_secs(sweeper::DEFAULT_SWEEP_SECONDS)
+    );
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn holds_expire_and_trips_complete() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, NINA, "nina");
+    add_flight(&mut conn, "T-LFC-F1", 1, "北京", "上海");
+    let available = |conn: &mut mysql::PooledConn| -> u32 {
+        conn.exec_first(
+            "SELECT num_available FROM Flights WHERE flight_num = 'T-LFC-F1'",
+            (),
+        )
+        .unwrap()
+        .unwrap()
+    };
+    let mut db = connection();
+    let status = |db: &mut tour_booking_system::mysql_connection::MySQLConnection,
+                  booking_ref: &str| {
+        db.query_reservation_by_ref(NINA, booking_ref)
+            .unwrap()
+            .status()
+    };
+    db.set_hold_minutes(Some(15));
+
+    // 保留中的预定占用余量
+    let held = db
+        .book(&Flight::CATEGORY, NINA, "T-LFC-F1".to_string())
+        .unwrap();
+    assert_eq!(status(&mut db, &held), ReservationStatus::Held);
+    assert_eq!(available(&mut conn), 0);
+    assert!(db
+        .book(&Flight::CATEGORY, NINA, "T-LFC-F1".to_string())
+        .is_none());
+
+    // 保留时间已到，清理后归还余量，不能再确认
+    conn.exec_drop(
+        "UPDATE Reservation SET hold_until = NOW() - INTERVAL 1 MINUTE WHERE booking_ref = :booking_ref",
+        params! { "booking_ref" => &held },
+    )
+    .unwrap();
+    assert!(db.sweep().0 >= 1);
+    assert_eq!(status(&mut db, &held), ReservationStatus::Expired);
+    assert_eq!(available(&mut conn), 1);
+    assert!(!db.confirm_booking(NINA, &held));
+    assert!(db.query_reservations(NINA).is_empty());
+
+    // 取消的预定保留为历史记录
+    let confirmed = db
+        .book(&Flight::CATEGORY, NINA, "T-LFC-F1".to_string())
+        .unwrap();
+    assert!(db.confirm_booking(NINA, &confirmed));
+    let id = db.query_reservation_by_ref(NINA, &confirmed).unwrap().id();
+    assert!(db.cancel_reservation(NINA, id));
+    assert_eq!(status(&mut db, &confirmed), ReservationStatus::Cancelled);
+    assert_eq!(available(&mut conn), 1);
+    assert!(db.cancel_quote(NINA, id).is_none());
+
+    // 到达时间已过的行程标记为已完成
+    db.set_hold_minutes(None);
+    conn.exec_drop(
+        "UPDATE Flights SET arrive_time = NOW() - INTERVAL 1 HOUR WHERE flight_num = 'T-LFC-F1'",
+        (),
+    )
+    .unwrap();
+    let travelled = db
+        .book(&Flight::CATEGORY, NINA, "T-LFC-F1".to_string())
+        .unwrap();
+    assert_eq!(status(&mut db, &travelled), ReservationStatus::Confirmed);
+    assert!(db.sweep().1 >= 1);
+    assert_eq!(status(&mut db, &travelled), ReservationStatus::Completed);
+    assert_eq!(db.query_reservations(NINA).len(), 1);
+
+    remove_flight(&mut conn, "T-LFC-F1");
+    remove_customer(&mut conn, NINA);
+}
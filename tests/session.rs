@@ -0,0 +1,109 @@
+mod common;
+
+use common::*;
+use std::io::Cursor;
+use tour_booking_system::bookable::CATEGORIES;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::{t, Status};
+
+const CAROL: u32 = 910_101;
+const DAVE: u32 = 910_102;
+
+/// run the prompt mode on `script` and return how it ended and what it printed
+fn session(script: &str) -> (Status, String) {
+    let out = Transcript::default();
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(script.to_string())),
+        Box::new(out.clone()),
+    );
+    let status = controller.run();
+    (status, out.text())
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn scripted_session_books_queries_and_cancels() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, CAROL, "carol");
+    add_flight(&mut conn, "T-SES-F1", 10, "北京", "上海");
+
+    // 查询菜单在各个目录之后才是“我的预订”
+    let my_bookings = (CATEGORIES.len() + 1).to_string();
+    let script = [
+        &CAROL.to_string(),
+        // 预定航班
+        "1",
+        "1",
+        "T-SES-F1",
+        // 查看自己的预定
+        "3",
+        &my_bookings,
+        // 完整性检查
+        "5",
+        // 取消航班
+        "2",
+        "1",
+        "T-SES-F1",
+        "y",
+        "9",
+    ]
+    .join("\n");
+    let (status, output) = session(&script);
+
+    assert!(status == Status::Quit);
+    assert!(output.contains(&t!("login.welcome", "carol")));
+    assert!(output.contains(t!("booking.success", "").trim_end()));
+    assert!(output.contains("T-SES-F1"));
+    assert!(output.contains(t!("completeness.ok")));
+    assert!(output.contains(t!("cancel.title")));
+    assert!(output.contains(t!("common.success")));
+    assert_eq!(reservation_count(&mut conn, CAROL, 1, "T-SES-F1"), 0);
+
+    remove_flight(&mut conn, "T-SES-F1");
+    remove_customer(&mut conn, CAROL);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn scripted_session_signs_up_unknown_ids() {
+    let mut conn = raw_conn();
+    remove_customer(&mut conn, DAVE);
+
+    let (status, output) = session(&format!("{}\ny\ndave\n0\n", DAVE));
+
+    assert!(status == Status::Login);
+    assert!(output.contains(&t!("signup.prompt", DAVE)));
+    assert!(connection().user_exist(DAVE));
+
+    remove_customer(&mut conn, DAVE);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn end_of_script_quits_the_session() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, CAROL, "carol");
+
+    let (status, output) = session(&format!("{}\nabc\n", CAROL));
+
+    assert!(status == Status::Quit);
+    assert!(output.contains(t!("prompt.invalid")));
+
+    remove_customer(&mut conn, CAROL);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn failures_are_written_to_the_session() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, CAROL, "carol");
+    remove_flight(&mut conn, "T-SES-NONE");
+
+    // 预定不存在的航班
+    let (_, output) = session(&format!("{}\n1\n1\nT-SES-NONE\n9\n", CAROL));
+
+    assert!(output.contains(&t!("error.item_not_found", t!("type.flight"))));
+
+    remove_customer(&mut conn, CAROL);
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use common::*;
+use std::io::Cursor;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::suggestion::route_cities;
+use tour_booking_system::t;
+
+const IVAN: u32 = 910_701;
+
+#[test]
+fn route_cities_are_listed_once_in_visiting_order() {
+    let routes = vec![
+        vec!["北京".to_string(), "上海".to_string()],
+        vec!["上海".to_string(), "杭州".to_string()],
+        vec!["杭州".to_string(), "北京".to_string()],
+    ];
+    assert_eq!(route_cities(&routes), vec!["北京", "上海", "杭州"]);
+    assert!(route_cities(&[]).is_empty());
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn cheapest_hotels_on_the_route_are_suggested_and_booked() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, IVAN, "ivan");
+    add_flight(&mut conn, "T-SUG-F1", 5, "T-SUG-起", "T-SUG-城");
+    add_hotel(&mut conn, "T-SUG-H1", 5, "T-SUG-城");
+    add_bus(&mut conn, "T-SUG-B1", 5, "T-SUG-城");
+    add_hotel(&mut conn, "T-SUG-H2", 5, "T-SUG-别处");
+    let mut db = connection();
+    db.book(&Flight::CATEGORY, IVAN, "T-SUG-F1".to_string())
+        .unwrap();
+
+    // 大巴排在酒店前面，2 号是目的地唯一的酒店
+    let out = Transcript::default();
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!("{}\n6\n2\n9\n", IVAN))),
+        Box::new(out.clone()),
+    );
+    controller.run();
+    let output = out.text();
+    assert!(output.contains("T-SUG-H1"));
+    assert!(output.contains("T-SUG-B1"));
+    assert!(!output.contains("T-SUG-H2"));
+    assert!(output.contains(&t!("suggest.item", t!("type.hotel"))));
+    assert_eq!(db.query::<Hotels>(IVAN).len(), 1);
+
+    remove_flight(&mut conn, "T-SUG-F1");
+    remove_hotel(&mut conn, "T-SUG-H1");
+    remove_bus(&mut conn, "T-SUG-B1");
+    remove_hotel(&mut conn, "T-SUG-H2");
+    remove_customer(&mut conn, IVAN);
+}
@@ -0,0 +1,78 @@
+mod common;
+
+use common::*;
+use std::io::Cursor;
+use tour_booking_system::bookable::Bookable;
+use tour_booking_system::controller::Controller;
+use tour_booking_system::flight::Flight;
+use tour_booking_system::hotels::Hotels;
+use tour_booking_system::t;
+use tour_booking_system::train::Train;
+
+const ERIN: u32 = 910_401;
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn trains_are_booked_queried_and_cancelled() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, ERIN, "erin");
+    add_train(&mut conn, "T-TRN-1", 2, "北京", "天津");
+    let mut db = connection();
+
+    assert!(db
+        .book(&Train::CATEGORY, ERIN, "T-TRN-1".to_string())
+        .is_some());
+    let trains = db.query::<Train>(ERIN);
+    assert_eq!(trains.len(), 1);
+    assert_eq!(trains[0].seat_class(), "二等座");
+    assert!(trains[0].depart_time().is_some());
+
+    let booking_ref = db
+        .book(&Train::CATEGORY, ERIN, "T-TRN-1".to_string())
+        .unwrap();
+    assert!(db
+        .book(&Train::CATEGORY, ERIN, "T-TRN-1".to_string())
+        .is_none());
+    let reservation = db.query_reservation_by_ref(ERIN, &booking_ref).unwrap();
+    assert_eq!(reservation.type_name(), t!("type.train"));
+
+    assert!(db.cancel(&Train::CATEGORY, ERIN, "T-TRN-1".to_string()));
+    assert_eq!(reservation_count(&mut conn, ERIN, 4, "T-TRN-1"), 1);
+
+    remove_train(&mut conn, "T-TRN-1");
+    remove_customer(&mut conn, ERIN);
+}
+
+#[test]
+#[ignore = "requires the BookingSystem MySQL database"]
+fn a_train_leg_completes_the_route() {
+    let mut conn = raw_conn();
+    add_customer(&mut conn, ERIN, "erin");
+    add_flight(&mut conn, "T-TRN-F1", 5, "广州", "北京");
+    add_train(&mut conn, "T-TRN-2", 5, "北京", "天津");
+    add_hotel(&mut conn, "T-TRN-H1", 5, "天津");
+    let mut db = connection();
+    db.book(&Flight::CATEGORY, ERIN, "T-TRN-F1".to_string())
+        .unwrap();
+    db.book(&Train::CATEGORY, ERIN, "T-TRN-2".to_string())
+        .unwrap();
+    db.book(&Hotels::CATEGORY, ERIN, "T-TRN-H1".to_string())
+        .unwrap();
+
+    let out = Transcript::default();
+    let mut controller = Controller::with_io(
+        connection(),
+        Box::new(Cursor::new(format!("{}\n5\n4\n9\n", ERIN))),
+        Box::new(out.clone()),
+    );
+    controller.run();
+    let output = out.text();
+    assert!(output.contains(t!("completeness.ok")));
+    assert!(output.contains(&t!("travel.item", t!("type.train"))));
+    assert!(output.contains("T-TRN-2"));
+
+    remove_flight(&mut conn, "T-TRN-F1");
+    remove_train(&mut conn, "T-TRN-2");
+    remove_hotel(&mut conn, "T-TRN-H1");
+    remove_customer(&mut conn, ERIN);
+}
@@ -0,0 +1,109 @@
+use ratatui::crossterm::event::KeyCode;
+use tour_booking_system::bookable::{Bookable, CATEGORIES};
+use tour_booking_system::cancellation::CancellationPolicy;
+use tour_booking_system::car_rental::CarRental;
+use tour_booking_system::t;
+use tour_booking_system::tui::{Action, Focus, Screen};
+
+fn rows(ids: &[&str]) -> Vec<Vec<String>> {
+    ids.iter().map(|x| vec![x.to_string()]).collect()
+}
+
+#[test]
+fn pages_stay_within_the_catalog() {
+    let mut screen = Screen::new();
+    screen.set_total_pages(3);
+    // 第一页不能再往前翻
+    assert_eq!(screen.handle_key(KeyCode::Left), Action::Nothing);
+    assert_eq!(screen.handle_key(KeyCode::Right), Action::Reload);
+    assert_eq!(screen.handle_key(KeyCode::PageDown), Action::Reload);
+    assert_eq!(screen.handle_key(KeyCode::Right), Action::Nothing);
+    assert_eq!(screen.page().number(), 3);
+
+    // 目录变短后停在最后一页
+    screen.set_total_pages(2);
+    assert_eq!(screen.page().number(), 2);
+    assert_eq!(screen.handle_key(KeyCode::PageUp), Action::Reload);
+    assert_eq!(screen.page().number(), 1);
+
+    // 切换类型回到第一页
+    screen.set_total_pages(3);
+    screen.handle_key(KeyCode::Right);
+    assert_eq!(screen.handle_key(KeyCode::Tab), Action::Reload);
+    assert_eq!(screen.page().number(), 1);
+    assert_eq!(screen.category().res_type(), CATEGORIES[1].res_type());
+}
+
+#[test]
+fn selection_is_clamped_to_the_rows() {
+    let mut screen = Screen::new();
+    screen.set_catalog(vec!["id".to_string()], rows(&["A", "B", "C"]));
+    assert_eq!(screen.selected_item(), Some(&["A".to_string()][..]));
+    screen.handle_key(KeyCode::Up);
+    assert_eq!(screen.selected_item().unwrap()[0], "A");
+    for _ in 0..5 {
+        screen.handle_key(KeyCode::Down);
+    }
+    assert_eq!(screen.selected_item().unwrap()[0], "C");
+    assert_eq!(
+        screen.handle_key(KeyCode::Char('b')),
+        Action::Book("C".to_string())
+    );
+
+    // 新的一页从第一行开始，空页没有选中行
+    screen.set_catalog(vec!["id".to_string()], rows(&["D"]));
+    assert_eq!(screen.selected_item().unwrap()[0], "D");
+    screen.set_catalog(vec!["id".to_string()], vec![]);
+    assert_eq!(screen.selected_item(), None);
+    assert_eq!(screen.handle_key(KeyCode::Char('b')), Action::Nothing);
+
+    // 行程表变短后选中最后一行
+    screen.handle_key(KeyCode::Char('f'));
+    assert_eq!(screen.focus(), Focus::Itinerary);
+    screen.set_itinerary(vec![(1, vec![]), (2, vec![]), (3, vec![])], true);
+    screen.handle_key(KeyCode::Down);
+    screen.handle_key(KeyCode::Down);
+    assert_eq!(screen.selected_reservation(), Some(3));
+    screen.set_itinerary(vec![(1, vec![]), (2, vec![])], true);
+    assert_eq!(screen.selected_reservation(), Some(2));
+    screen.set_itinerary(vec![], true);
+    assert_eq!(screen.selected_reservation(), None);
+}
+
+#[test]
+fn cancelling_asks_for_confirmation() {
+    let mut screen = Screen::new();
+    assert_eq!(screen.handle_key(KeyCode::Char('c')), Action::Nothing);
+    assert_eq!(screen.status(), t!("tui.select_reservation"));
+
+    screen.set_itinerary(vec![(7, vec![]), (8, vec![])], true);
+    screen.handle_key(KeyCode::Char('f'));
+    screen.handle_key(KeyCode::Down);
+    assert_eq!(screen.handle_key(KeyCode::Char('c')), Action::AskCancel(8));
+
+    let quote = CancellationPolicy::free().quote(100, None);
+    screen.ask_confirm(8, quote.clone());
+    assert_eq!(screen.confirming(), Some(8));
+    // 其他按键放弃取消，也不会退出
+    assert_eq!(screen.handle_key(KeyCode::Char('q')), Action::Nothing);
+    assert_eq!(screen.confirming(), None);
+    assert_eq!(screen.status(), t!("tui.cancel_aborted"));
+
+    screen.ask_confirm(8, quote);
+    assert_eq!(screen.handle_key(KeyCode::Char('y')), Action::Cancel(8));
+    assert_eq!(screen.confirming(), None);
+}
+
+#[test]
+fn dated_categories_are_booked_in_the_prompt_mode() {
+    let mut screen = Screen::new();
+    while screen.category().res_type() != CarRental::CATEGORY.res_type() {
+        screen.handle_key(KeyCode::Tab);
+    }
+    screen.set_catalog(vec!["id".to_string()], rows(&["CAR-1"]));
+    assert_eq!(screen.handle_key(KeyCode::Char('b')), Action::Nothing);
+    assert_eq!(
+        screen.status(),
+        t!("tui.dated_unsupported", CarRental::CATEGORY.name())
+    );
+}